@@ -0,0 +1,8 @@
+
+use sgr_cubes::tool::run_tool;
+
+fn main() {
+    env_logger::init();
+    let args = std::env::args().skip(1).collect();
+    std::process::exit( run_tool(args) );
+}
@@ -0,0 +1,8 @@
+
+use sgr_cubes::wctx::headless::run_render;
+
+fn main() {
+    env_logger::init();
+    let args = std::env::args().skip(1).collect();
+    std::process::exit( run_render(args) );
+}
@@ -0,0 +1,43 @@
+
+// the world model: blocks, chunks, meshing and saves, without any graphics or windowing.
+// the game in wctx draws it with wgpu, tools work on it directly.
+
+pub mod block;
+pub mod block_data;
+pub mod blueprint;
+pub mod palette;
+pub mod rotation_group;
+
+pub mod camera;
+pub mod chunk;
+pub mod heightmap;
+pub mod mesh;
+pub mod mesh_export;
+pub mod mural;
+
+pub mod journal;
+pub mod nbt;
+pub mod region;
+pub mod saves;
+pub mod schematic;
+pub mod vox;
+pub mod voxelize;
+pub mod world;
+pub mod world_edit;
+pub mod world_format;
+pub mod world_gen;
+
+// the error for a file whose contents don't make sense, shared by the readers above
+pub(crate) fn invalid(message: String) -> std::io::Error {
+    std::io::Error::new( std::io::ErrorKind::InvalidData, message )
+}
+
+// a fresh directory under the system temp folder for one test
+#[cfg(test)]
+pub(crate) fn test_dir(name: &str) -> std::path::PathBuf {
+    let mut pbuf = std::env::temp_dir();
+    pbuf.push( format!( "sgr_cubes_{}_{}", name, std::process::id() ) );
+    let _ = std::fs::remove_dir_all(&pbuf);
+    std::fs::create_dir_all(&pbuf).unwrap();
+    pbuf
+}
@@ -1,13 +1,14 @@
 
 use std::cmp::min;
+use std::collections::HashMap;
 
 use cgmath::{
     Rotation,
 };
 
-use crate::wctx::rotation_group;
-use crate::wctx::rotation_group::RotFace;
-use crate::wctx::world::Vertex;
+use crate::engine::rotation_group;
+use crate::engine::rotation_group::RotFace;
+use crate::engine::mesh::Vertex;
 
 
 use cgmath::One;
@@ -15,6 +16,7 @@ use cgmath::One;
 
 pub struct Block {
     registry_id: u16,
+    pub name: String,
     pub shape_id: u32,
     //parameter_type: ParamType,
     pub textures: Vec<u32>,
@@ -24,33 +26,50 @@ pub struct Block {
 
 pub struct BlockRegistry {
     blocks: Vec<Block>,
+    names: HashMap<String, u16>,
 }
 
 impl BlockRegistry {
     pub fn new() -> BlockRegistry {
         // Always create the air block at position zero!
-        let air = Block { registry_id: 0, shape_id: 0, pretty_name: "Air".into(), transparent: true, textures: vec![0] };
+        let air = Block { registry_id: 0, name: "air".into(), shape_id: 0, pretty_name: "Air".into(), transparent: true, textures: vec![0] };
         let mut blocks = Vec::<Block>::new();
         blocks.push(air);
+        let mut names = HashMap::<String, u16>::new();
+        names.insert( "air".into(), 0 );
 
         Self {
-            blocks
+            blocks,
+            names
         }
     }
 
-    pub fn add(&mut self, shape_id: u32, pretty_name: String, textures: Vec<u32>, transparent: bool ) -> u16 {
+    pub fn add(&mut self, name: String, shape_id: u32, pretty_name: String, textures: Vec<u32>, transparent: bool ) -> Option<u16> {
+        if self.names.contains_key(&name) {
+            return None;
+        }
         let registry_id = self.blocks.len() as u16;
-        self.blocks.push( Block { registry_id, shape_id, pretty_name, textures, transparent } );
-        registry_id
+        self.names.insert( name.clone(), registry_id );
+        self.blocks.push( Block { registry_id, name, shape_id, pretty_name, textures, transparent } );
+        Some(registry_id)
     }
 
     pub fn get(&self, index: u16) -> Option<&Block> {
         self.blocks.get(index as usize)
     }
 
+    pub fn get_id(&self, name: &str) -> Option<u16> {
+        self.names.get(name).copied()
+    }
+
     pub fn get_num_blocks(&self) -> u16 {
         return self.blocks.len() as u16;
     }
+
+    // block names in ID order, as stored alongside world saves
+    pub fn name_table(&self) -> Vec<String> {
+        self.blocks.iter().map( |b| b.name.clone() ).collect()
+    }
 }
 
 pub struct BlockShape {
@@ -60,7 +79,7 @@ pub struct BlockShape {
 }
 
 impl BlockShape {
-    pub fn generate_draw_buffers(&self, vertex_buffer: &mut Vec<Vertex>, index_buffer: &mut Vec<u16>, blockdef: &Block, exparam: u8, bdc: crate::wctx::chunk::BlockDrawContext, world_pos: (usize, usize, usize), pos: (usize, usize, usize) ) {
+    pub fn generate_draw_buffers(&self, vertex_buffer: &mut Vec<Vertex>, index_buffer: &mut Vec<u16>, blockdef: &Block, exparam: u8, bdc: crate::engine::chunk::BlockDrawContext, world_pos: (usize, usize, usize), pos: (usize, usize, usize) ) {
         let mut quat = cgmath::Quaternion::<f32>::one();
         match self.rot_group {
             rotation_group::RotType::RotFace => {
@@ -103,6 +122,25 @@ impl BlockShape {
 
     }
 
+    // solid on all six sides whatever the rotation
+    pub fn is_full_cube(&self) -> bool {
+        self.obstructs.iter().all( |o| *o )
+    }
+
+    pub fn rot_group(&self) -> &rotation_group::RotType {
+        &self.rot_group
+    }
+
+    // false if the rotation bits don't name a rotation of this shape's group, meshing such a block would panic
+    pub fn valid_exparam(&self, exparam: u8) -> bool {
+        match self.rot_group {
+            rotation_group::RotType::RotFace => rotation_group::num_to_rf( exparam & 0b0000_0111 ).is_some(),
+            rotation_group::RotType::RotVert => rotation_group::num_to_rv( exparam & 0b0000_0111 ).is_some(),
+            rotation_group::RotType::RotEdge => rotation_group::num_to_re( exparam & 0b0000_1111 ).is_some(),
+            rotation_group::RotType::Static => true,
+        }
+    }
+
     pub fn does_obstruct(&self, exparam: u8, dir: rotation_group::RotFace) -> bool {
         let mut quat = cgmath::Quaternion::<f32>::one();
         match self.rot_group {
@@ -151,6 +189,15 @@ impl BlockShapeRegistry {
     }
 }
 
+// the shapes block.toml can refer to by shape_name
+pub fn builtin_shapes() -> Vec<(String, BlockShape)> {
+    vec![
+        ( "CubeStatic".to_string(), make_cube_shape() ),
+        ( "Slope".to_string(), make_slope_shape() ),
+        ( "CornerSlope".to_string(), make_corner_shape() ),
+    ]
+}
+
 pub fn make_cube_shape() -> BlockShape {
     BlockShape {
         faces: vec![
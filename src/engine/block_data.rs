@@ -0,0 +1,76 @@
+use std::io::Error;
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use figment::Figment;
+use figment::providers::{Format, Toml};
+
+use serde::Deserialize;
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct Config {
+    pub block: Vec<BlockPlan>,
+}
+
+#[derive(Debug, PartialEq, Clone, Deserialize)]
+pub struct BlockPlan {
+    pub name: String,
+    pub pretty_name: String,
+    pub textures: Vec<String>,
+    pub shape_name: String,
+    pub transparent: Option<bool>,
+}
+
+pub const BLOCK_DATA_PATH: &str = "res/data/block.toml";
+
+pub fn load_block_config(filename: PathBuf) -> Result<Config, Error> {
+    Figment::new().merge(Toml::file(filename)).extract().map_err( |e| Error::new( std::io::ErrorKind::InvalidData, e.to_string() ) )
+}
+
+// the block name table the registry will end up with, readable without a GPU
+pub fn load_block_names(filename: PathBuf) -> Result<Vec<String>, Error> {
+    let config = load_block_config(filename)?;
+    let mut names = vec![ "air".to_string() ];
+    for bp in config.block {
+        names.push(bp.name);
+    }
+    Ok(names)
+}
+
+// every block texture file named in the config, each once, in order of first use
+pub fn texture_names(config: &Config) -> Vec<String> {
+    let mut names = Vec::<String>::new();
+    for bp in config.block.iter() {
+        for tex_name in bp.textures.iter() {
+            if !names.contains(tex_name) {
+                names.push( tex_name.clone() );
+            }
+        }
+    }
+    names
+}
+
+// block and shape registries without loading any textures, for tools that never draw on the GPU.
+// IDs come out the same as in the game's registry, texture indices point into texture_names.
+pub fn load_block_registry(filename: PathBuf) -> Result<(crate::engine::block::BlockRegistry, crate::engine::block::BlockShapeRegistry), Error> {
+    let config = load_block_config(filename)?;
+    let tex_names = texture_names(&config);
+
+    let mut shape_registry = crate::engine::block::BlockShapeRegistry::new();
+    let mut shape_names = HashMap::<String, u32>::new();
+    for (name, shape) in crate::engine::block::builtin_shapes() {
+        shape_names.insert( name, shape_registry.add(shape) );
+    }
+
+    let mut block_registry = crate::engine::block::BlockRegistry::new();
+    for bp in config.block {
+        let shape_idx = shape_names.get( &bp.shape_name ).ok_or(Error::new::<String>(std::io::ErrorKind::Other, format!("Shape name {} not found!", bp.shape_name) ))?;
+        let mut textures: Vec<u32> = bp.textures.iter().map( |t| tex_names.iter().position( |n| n == t ).unwrap() as u32 ).collect();
+        if textures.is_empty() {
+            textures.push(0);
+        }
+        block_registry.add( bp.name.clone(), *shape_idx, bp.pretty_name, textures, bp.transparent.unwrap_or(false) ).ok_or(Error::new::<String>(std::io::ErrorKind::Other, format!("Duplicate block name {}!", bp.name) ))?;
+    }
+
+    Ok( (block_registry, shape_registry) )
+}
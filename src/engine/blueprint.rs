@@ -0,0 +1,84 @@
+use std::collections::{HashMap, HashSet};
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use crate::engine::block::BlockRegistry;
+use crate::engine::saves;
+
+// blocks planned for a world but not built yet. the game draws them as see-through ghosts
+// until the same block is placed there. kept as TOML next to the save so it can be edited by hand
+
+pub const BLUEPRINT_FILE_NAME: &str = "blueprint.toml";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct BlueprintBlock {
+    pub pos: [i64; 3],
+    // block name from block.toml, so the blueprint survives blocks being added or reordered
+    pub name: String,
+    #[serde(default)]
+    pub exparam: u8,
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Blueprint {
+    #[serde(default)]
+    pub block: Vec<BlueprintBlock>,
+}
+
+impl Blueprint {
+    fn path(world_dir: &Path) -> PathBuf {
+        let mut pbuf = world_dir.to_path_buf();
+        pbuf.push(BLUEPRINT_FILE_NAME);
+        pbuf
+    }
+
+    // an empty blueprint for worlds that don't have one
+    pub fn read(world_dir: &Path) -> Result<Blueprint, Error> {
+        let pbuf = Self::path(world_dir);
+        if !pbuf.exists() {
+            return Ok( Blueprint::default() );
+        }
+        let contents = std::fs::read_to_string(&pbuf)?;
+        toml::from_str::<Blueprint>(&contents).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, format!( "{}: {}", pbuf.display(), e ) ) )
+    }
+
+    // an empty blueprint removes the file
+    pub fn write(&self, world_dir: &Path) -> Result<(), Error> {
+        let pbuf = Self::path(world_dir);
+        if self.block.is_empty() {
+            if pbuf.exists() {
+                std::fs::remove_file(pbuf)?;
+            }
+            return Ok(());
+        }
+        let datastring = toml::to_string(self).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, e ) )?;
+        saves::write_file_atomic( &pbuf, datastring.as_bytes() )
+    }
+
+    // replaces whatever was planned at the same positions
+    pub fn add(&mut self, blocks: Vec<BlueprintBlock>) {
+        let taken: HashSet<[i64; 3]> = blocks.iter().map( |b| b.pos ).collect();
+        self.block.retain( |b| !taken.contains(&b.pos) );
+        self.block.extend(blocks);
+    }
+
+    // block ID and exparam by position, leaving out blocks outside a world of the given width
+    // and names block.toml doesn't have
+    pub fn resolve(&self, registry: &BlockRegistry, width: usize) -> HashMap<(usize, usize, usize), (u16, u8)> {
+        let mut out = HashMap::new();
+        for b in self.block.iter() {
+            if b.pos.iter().any( |p| *p < 0 || *p >= width as i64 ) {
+                continue;
+            }
+            if let Some(id) = registry.get_id(&b.name) {
+                out.insert( ( b.pos[0] as usize, b.pos[1] as usize, b.pos[2] as usize ), (id, b.exparam) );
+            }
+        }
+        out
+    }
+}
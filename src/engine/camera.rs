@@ -0,0 +1,102 @@
+use cgmath::*;
+
+use serde::{
+    Serialize,
+    Deserialize,
+    Serializer,
+    Deserializer,
+    ser::SerializeTuple,
+    de::Visitor,
+    de::SeqAccess,
+};
+
+use std::fmt;
+
+
+#[derive(Debug)]
+pub struct Camera {
+    pub position: Point3<f32>,
+    pub yaw: Rad<f32>,
+    pub pitch: Rad<f32>,
+}
+
+impl Camera {
+    pub fn new<V: Into<Point3<f32>>, Y: Into<Rad<f32>>, P: Into<Rad<f32>>>(
+        position: V,
+        yaw: Y,
+        pitch: P,
+    ) -> Self {
+        Self {
+            position: position.into(),
+            yaw: yaw.into(),
+            pitch: pitch.into(),
+        }
+    }
+
+    pub fn calc_matrix(&self) -> Matrix4<f32> {
+        let (sin_pitch, cos_pitch) = self.pitch.0.sin_cos();
+        let (sin_yaw, cos_yaw) = self.yaw.0.sin_cos();
+
+        Matrix4::look_to_rh(
+            self.position,
+            Vector3::new(cos_pitch * cos_yaw, sin_pitch, cos_pitch * sin_yaw).normalize(),
+            Vector3::unit_y(),
+        )
+    }
+
+    pub fn get_forward_vector(&self) -> Vector3<f32> {
+        let mut forward = Vector3::new(1.0, 0.0, 0.0);
+        let pitch_quat = Quaternion::from_axis_angle( Vector3::<f32>::unit_z(), self.pitch );
+        let yaw_quat = Quaternion::from_axis_angle( -1.0 * Vector3::<f32>::unit_y(), self.yaw );
+        yaw_quat * (pitch_quat * forward)
+    }
+}
+
+impl Serialize for Camera {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut tup = serializer.serialize_tuple(5)?;
+        tup.serialize_element(&self.position.x)?;
+        tup.serialize_element(&self.position.y)?;
+        tup.serialize_element(&self.position.z)?;
+        tup.serialize_element(&self.yaw.0)?;
+        tup.serialize_element(&self.pitch.0)?;
+        tup.end()
+    }
+}
+
+struct CameraVisitor;
+impl<'de> Visitor<'de> for CameraVisitor {
+    type Value = Camera;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a tuple of five f32 values")
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, <A as SeqAccess<'de>>::Error>
+        where
+            A: SeqAccess<'de>,
+    {
+        Ok(Camera::new(
+            cgmath::Point3{
+                x: seq.next_element()?.unwrap(),
+                y: seq.next_element()?.unwrap(),
+                z: seq.next_element()?.unwrap()
+            },
+            cgmath::Rad( seq.next_element()?.unwrap() ),
+            cgmath::Rad( seq.next_element()?.unwrap() )
+        ))
+    }
+
+}
+
+impl<'de> Deserialize<'de> for Camera {
+    fn deserialize<D>(deserializer: D) -> Result<Camera, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_tuple(5, CameraVisitor)
+    }
+}
@@ -5,22 +5,30 @@ use ndarray::{
     s
 };
 
+use std::io::Error;
+use std::path::PathBuf;
+
 use serde::{
     Serialize,
     Deserialize
 };
 
-use crate::wctx::world::Vertex;
+use crate::engine::mesh::Vertex;
 
-use crate::wctx::block::{
+use crate::engine::block::{
     BlockRegistry,
     BlockShapeRegistry
 };
 
-use crate::wctx::rotation_group;
+use crate::engine::rotation_group;
+use crate::engine::region;
+use crate::engine::world_gen::WorldGenSettings;
 
 pub const CHUNK_SIZE: usize = 16;
 pub const WORLD_CHUNKS: [usize; 3] = [ 8, 12, 16 ];
+// chunks within this many chunks of the camera are streamed in from region files
+pub const LOAD_RADIUS: usize = 6;
+pub const MAX_LOADS_PER_FRAME: usize = 8;
 
 #[repr(C)]
 #[derive(Copy, Clone, Serialize, Deserialize)]
@@ -36,6 +44,14 @@ pub struct Chunk {
     #[serde(skip_serializing)]
     #[serde(default = "get_a_true")]
     pub dirty: bool,
+    // changed since it was last written to its region file
+    #[serde(skip)]
+    #[serde(default = "get_a_true")]
+    pub unsaved: bool,
+    // false for placeholders of chunks still waiting in a region file
+    #[serde(skip)]
+    #[serde(default = "get_a_true")]
+    pub loaded: bool,
     #[serde(skip)]
     pub draw_cache: ChunkDrawCache
 }
@@ -62,6 +78,8 @@ impl Chunk {
         Self {
             data,
             dirty,
+            unsaved: true,
+            loaded: true,
             draw_cache
         }
     }
@@ -135,10 +153,12 @@ impl Chunk {
                 bi = self.data[ (opos.0 as usize, opos.1 as usize, opos.2 as usize) ];
             }
 
-            let bdef = registry.get(bi.blockdef).unwrap();
-            if !bdef.transparent {
-                let sdef = shape_registry.get(bdef.shape_id).unwrap();
-                out[ idx as usize ] = sdef.does_obstruct( bi.exparam, rotation_group::reverse_rf( rotation_group::num_to_rf( idx ).unwrap() ) );
+            // blocks missing from the registry are not drawn, so they never obstruct
+            if let Some(bdef) = registry.get(bi.blockdef) {
+                if !bdef.transparent {
+                    let sdef = shape_registry.get(bdef.shape_id).unwrap();
+                    out[ idx as usize ] = sdef.does_obstruct( bi.exparam, rotation_group::reverse_rf( rotation_group::num_to_rf( idx ).unwrap() ) );
+                }
             }
         }
 
@@ -212,28 +232,28 @@ impl<'a> ChunkDrawContext<'a> {
 #[derive(Serialize, Deserialize)]
 pub struct ChunkManager {
     pub size: usize,
-    pub data: Array3<Chunk>
+    pub data: Array3<Chunk>,
+    // where chunks are streamed from and saved to, None until the world has a save directory
+    #[serde(skip)]
+    pub region_dir: Option<PathBuf>,
 }
 
 impl ChunkManager {
     pub fn new(size: usize) -> ChunkManager {
+        Self::generate( &WorldGenSettings::new(size) )
+    }
+
+    pub fn generate(settings: &WorldGenSettings) -> ChunkManager {
+        let size = settings.size;
         let constr = | loc: (usize, usize, usize) | -> Chunk {
-            if loc.1 < 2 {
-                let proto_bi = BlockInstance{
-                    blockdef: 1,
-                    exparam: 0,
-                    light: 255,
-                };
-                Chunk::from_blockinstance(proto_bi)
-            } else {
-                Chunk::new()
-            }
+            settings.generate_chunk(loc)
         };
         let data = Array3::from_shape_fn( (WORLD_CHUNKS[size], WORLD_CHUNKS[size], WORLD_CHUNKS[size]), constr );
 
         Self{
             size,
-            data
+            data,
+            region_dir: None,
         }
     }
 
@@ -243,10 +263,12 @@ impl ChunkManager {
         &self.data[chunk_index].data[inner_index]
     }
 
+    // the chunk has to be loaded first, a placeholder's edits are lost when the real chunk streams in over it
     pub fn get_mut_block(&mut self, world_pos: (usize, usize, usize) ) -> &mut BlockInstance {
         let chunk_index = ( world_pos.0 / CHUNK_SIZE, world_pos.1 / CHUNK_SIZE, world_pos.2 / CHUNK_SIZE );
         let inner_index = ( world_pos.0 % CHUNK_SIZE, world_pos.1 % CHUNK_SIZE, world_pos.2 % CHUNK_SIZE );
         self.data[chunk_index].dirty = true;
+        self.data[chunk_index].unsaved = true;
         // set adjacent chunks as dirty if needed
         if inner_index.0 == 0 && chunk_index.0 > 0 { self.data[ (chunk_index.0 - 1, chunk_index.1, chunk_index.2) ].dirty = true; }
         if inner_index.0 == CHUNK_SIZE - 1 && chunk_index.0 < WORLD_CHUNKS[self.size] - 1 { self.data[ (chunk_index.0 + 1, chunk_index.1, chunk_index.2) ].dirty = true; }
@@ -260,6 +282,11 @@ impl ChunkManager {
     }
 
     pub fn update_dirty_chunks(&mut self, registry: &BlockRegistry, shape_registry: &BlockShapeRegistry ) {
+        self.update_dirty_chunks_progress( registry, shape_registry, |_, _| {} );
+    }
+
+    // same as update_dirty_chunks, reporting ( chunks done, chunk count ) as it goes
+    pub fn update_dirty_chunks_progress(&mut self, registry: &BlockRegistry, shape_registry: &BlockShapeRegistry, mut progress: impl FnMut(usize, usize) ) {
         let rebuild = |this: &mut Self, ch_idx: (usize, usize, usize), wpos: (usize, usize, usize)| {
             let mut dirty = false;
             {
@@ -311,15 +338,131 @@ impl ChunkManager {
                 ch.update_draw_cache(wpos, registry, shape_registry, cdc);
             }
         };
+        let total = self.data.len();
+        let mut done = 0;
         for x in 0..WORLD_CHUNKS[self.size] {
             for y in 0..WORLD_CHUNKS[self.size] {
                 for z in 0..WORLD_CHUNKS[self.size] {
                     rebuild(self, (x, y, z), (x * CHUNK_SIZE, y * CHUNK_SIZE, z * CHUNK_SIZE) );
+                    done += 1;
+                    progress(done, total);
                 }
             }
         }
     }
 
+    fn mark_neighbors_dirty(&mut self, chunk_index: (usize, usize, usize)) {
+        let max = WORLD_CHUNKS[self.size] - 1;
+        if chunk_index.0 > 0 { self.data[ (chunk_index.0 - 1, chunk_index.1, chunk_index.2) ].dirty = true; }
+        if chunk_index.0 < max { self.data[ (chunk_index.0 + 1, chunk_index.1, chunk_index.2) ].dirty = true; }
+        if chunk_index.1 > 0 { self.data[ (chunk_index.0, chunk_index.1 - 1, chunk_index.2) ].dirty = true; }
+        if chunk_index.1 < max { self.data[ (chunk_index.0, chunk_index.1 + 1, chunk_index.2) ].dirty = true; }
+        if chunk_index.2 > 0 { self.data[ (chunk_index.0, chunk_index.1, chunk_index.2 - 1) ].dirty = true; }
+        if chunk_index.2 < max { self.data[ (chunk_index.0, chunk_index.1, chunk_index.2 + 1) ].dirty = true; }
+    }
+
+    pub fn load_chunk(&mut self, chunk_index: (usize, usize, usize)) -> Result<(), Error> {
+        if self.data[chunk_index].loaded {
+            return Ok(());
+        }
+        let mut chunk = match &self.region_dir {
+            Some(dir) => region::read_chunk(dir, chunk_index)?.unwrap_or( Chunk::new() ),
+            None => Chunk::new(),
+        };
+        chunk.loaded = true;
+        chunk.unsaved = false;
+        chunk.dirty = true;
+        self.data[chunk_index] = chunk;
+        self.mark_neighbors_dirty(chunk_index);
+        Ok(())
+    }
+
+    // loads up to max_loads unloaded chunks within radius chunks of a world position, nearest first
+    pub fn load_near(&mut self, world_pos: (f32, f32, f32), radius: usize, max_loads: usize) -> Result<usize, Error> {
+        let world_chunks = WORLD_CHUNKS[self.size] as i64;
+        let center = (
+            ( world_pos.0 / CHUNK_SIZE as f32 ).floor() as i64,
+            ( world_pos.1 / CHUNK_SIZE as f32 ).floor() as i64,
+            ( world_pos.2 / CHUNK_SIZE as f32 ).floor() as i64,
+        );
+        let r = radius as i64;
+
+        let mut wanted = Vec::<(i64, (usize, usize, usize))>::new();
+        for x in std::cmp::max(center.0 - r, 0)..std::cmp::min(center.0 + r + 1, world_chunks) {
+            for y in std::cmp::max(center.1 - r, 0)..std::cmp::min(center.1 + r + 1, world_chunks) {
+                for z in std::cmp::max(center.2 - r, 0)..std::cmp::min(center.2 + r + 1, world_chunks) {
+                    let idx = (x as usize, y as usize, z as usize);
+                    if !self.data[idx].loaded {
+                        let dist = (x - center.0).pow(2) + (y - center.1).pow(2) + (z - center.2).pow(2);
+                        wanted.push( (dist, idx) );
+                    }
+                }
+            }
+        }
+        wanted.sort_by_key( |w| w.0 );
+
+        let mut count = 0;
+        for (_, idx) in wanted.iter().take(max_loads) {
+            self.load_chunk(*idx)?;
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    pub fn load_all(&mut self) -> Result<(), Error> {
+        let world_chunks = WORLD_CHUNKS[self.size];
+        for x in 0..world_chunks {
+            for y in 0..world_chunks {
+                for z in 0..world_chunks {
+                    self.load_chunk( (x, y, z) )?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    // writes every chunk changed since the last save to the region files, returns how many were written
+    pub fn save_unsaved(&mut self) -> Result<usize, Error> {
+        let dir = self.region_dir.clone().ok_or( Error::new( std::io::ErrorKind::NotFound, "world has no region directory" ) )?;
+        let mut written = Vec::<(usize, usize, usize)>::new();
+        {
+            let mut to_write = Vec::<( (usize, usize, usize), &Chunk )>::new();
+            for (idx, ch) in self.data.indexed_iter() {
+                if ch.loaded && ch.unsaved {
+                    to_write.push( (idx, ch) );
+                    written.push(idx);
+                }
+            }
+            region::write_chunks(&dir, &to_write)?;
+        }
+        for idx in written.iter() {
+            self.data[*idx].unsaved = false;
+        }
+        Ok(written.len())
+    }
+
+    // copies out the block data of every changed chunk and marks them saved, for writing off the main thread
+    pub fn snapshot_unsaved(&mut self) -> Vec<( (usize, usize, usize), Chunk )> {
+        let mut out = Vec::<( (usize, usize, usize), Chunk )>::new();
+        for (idx, ch) in self.data.indexed_iter_mut() {
+            if ch.loaded && ch.unsaved {
+                let mut copy = Chunk::new();
+                copy.data = ch.data.clone();
+                out.push( (idx, copy) );
+                ch.unsaved = false;
+            }
+        }
+        out
+    }
+
+    pub fn mark_unsaved(&mut self, indices: &[(usize, usize, usize)]) {
+        for idx in indices.iter() {
+            if let Some(ch) = self.data.get_mut(*idx) {
+                ch.unsaved = true;
+            }
+        }
+    }
+
     pub fn get_render_chunks(&self) -> Vec<ChunkDrawCache> {
         let mut cache_vec = Vec::<ChunkDrawCache>::new();
 
@@ -0,0 +1,122 @@
+use std::io::Error;
+use std::path::Path;
+
+use cgmath::{InnerSpace, Vector3};
+
+use crate::engine::palette;
+use crate::engine::voxelize::{self, CellFit, ShapeFitter};
+use crate::engine::world_edit::BlockFamily;
+
+// terrain from a grayscale picture, one column of blocks per pixel. the surface is blended
+// between column centers, and blocks it cuts through get slope shapes where they fit
+
+// heights in blocks, picture rows run along z
+pub struct Heightmap {
+    pub width: usize,
+    pub depth: usize,
+    heights: Vec<f32>,
+}
+
+impl Heightmap {
+    // black is base blocks tall and white base + height. columns resamples the picture to
+    // that many pixels across, keeping its aspect ratio
+    pub fn load(path: &Path, base: f32, height: f32, columns: Option<u32>) -> Result<Heightmap, Error> {
+        let img = image::open(path).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, format!( "{}: {}", path.display(), e ) ) )?;
+        let img = palette::resize_to_columns( img, columns );
+
+        // 16 bit pictures keep their precision, 8 bit ones are widened
+        let luma = img.to_luma16();
+        Ok( Heightmap {
+            width: luma.width() as usize,
+            depth: luma.height() as usize,
+            heights: luma.pixels().map( |p| base + p.0[0] as f32 / 65535.0 * height ).collect(),
+        } )
+    }
+
+    fn column(&self, x: i64, z: i64) -> f32 {
+        let x = x.clamp( 0, self.width as i64 - 1 ) as usize;
+        let z = z.clamp( 0, self.depth as i64 - 1 ) as usize;
+        self.heights[ z * self.width + x ]
+    }
+
+    // surface height anywhere on the map, x and z in blocks from the map's corner
+    pub fn surface(&self, x: f32, z: f32) -> f32 {
+        let (fx, fz) = ( x - 0.5, z - 0.5 );
+        let (x0, z0) = ( fx.floor(), fz.floor() );
+        let (tx, tz) = ( fx - x0, fz - z0 );
+        let (x0, z0) = ( x0 as i64, z0 as i64 );
+        let near = self.column(x0, z0) * ( 1.0 - tx ) + self.column(x0 + 1, z0) * tx;
+        let far = self.column(x0, z0 + 1) * ( 1.0 - tx ) + self.column(x0 + 1, z0 + 1) * tx;
+        near * ( 1.0 - tz ) + far * tz
+    }
+
+    pub fn normal(&self, x: f32, z: f32) -> Vector3<f32> {
+        let e = 0.25;
+        let dx = ( self.surface( x + e, z ) - self.surface( x - e, z ) ) / ( 2.0 * e );
+        let dz = ( self.surface( x, z + e ) - self.surface( x, z - e ) ) / ( 2.0 * e );
+        Vector3::new( -dx, 1.0, -dz ).normalize()
+    }
+}
+
+// one material of the terrain from the top down, depth is how many blocks thick it is
+// and None for the last one, which goes all the way down
+pub struct Layer {
+    pub family: BlockFamily,
+    pub depth: Option<usize>,
+}
+
+fn layer_at(layers: &[Layer], depth: usize) -> usize {
+    let mut top = 0;
+    for (i, layer) in layers.iter().enumerate() {
+        match layer.depth {
+            Some(d) if depth >= top + d => top += d,
+            _ => return i,
+        }
+    }
+    layers.len() - 1
+}
+
+// calls place with each block of the terrain, positions counted from the map's corner at
+// the bottom of the terrain. nothing is placed at max_height or above, so a steep scale can't run away
+pub fn build_terrain(map: &Heightmap, layers: &[Layer], max_height: usize, mut place: impl FnMut((usize, usize, usize), u16, u8)) {
+    if layers.is_empty() {
+        return;
+    }
+    let fitters: Vec<ShapeFitter> = layers.iter().map( |l| ShapeFitter::new(&l.family) ).collect();
+    let full = voxelize::shape_mask( |_| true );
+
+    for z in 0..map.depth {
+        for x in 0..map.width {
+            let (cx, cz) = ( x as f32 + 0.5, z as f32 + 0.5 );
+            // the lowest and highest the surface gets over this column
+            let mut lo = f32::MAX;
+            let mut hi = f32::MIN;
+            for ox in [ -0.5, 0.0, 0.5 ] {
+                for oz in [ -0.5, 0.0, 0.5 ] {
+                    let h = map.surface( cx + ox, cz + oz );
+                    lo = lo.min(h);
+                    hi = hi.max(h);
+                }
+            }
+            if hi <= 0.0 {
+                continue;
+            }
+
+            let top = ( map.surface(cx, cz).ceil() as usize ).max(1) - 1;
+            let normal = map.normal(cx, cz);
+            for y in 0..( hi.ceil() as usize ).min(max_height) {
+                let layer = layer_at( layers, top.saturating_sub(y) );
+                let fit = if ( y + 1 ) as f32 <= lo {
+                    Some( CellFit::Cube )
+                } else {
+                    let mask = voxelize::shape_mask( |p| y as f32 + 0.5 + p.y < map.surface( cx + p.x, cz + p.z ) );
+                    if mask == full { Some( CellFit::Cube ) } else { fitters[layer].fit( mask, Some(normal) ) }
+                };
+                if let Some(fit) = fit {
+                    let (blockdef, exparam) = fit.block( &layers[layer].family );
+                    place( (x, y, z), blockdef, exparam );
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,229 @@
+use std::fs::{
+    File,
+    OpenOptions,
+};
+use std::io::{
+    BufWriter,
+    Error,
+    ErrorKind,
+    Read,
+    Write,
+};
+use std::path::{Path, PathBuf};
+
+use crate::engine::chunk::{
+    BlockInstance,
+    ChunkManager,
+    CHUNK_SIZE,
+    WORLD_CHUNKS
+};
+
+// Journal file layout (all integers little endian):
+//   magic "SGRJ", format version u16, reserved u16, base save ID u64
+//   records of ( x u16, y u16, z u16, old block, new block ), blocks being ( blockdef u16, exparam u8, light u8 )
+// The base save ID is the save the edits apply on top of. When a snapshot is taken for saving,
+// the journal is moved to journal.prev.bin and a new one started, so edits made while the save
+// is being written are never lost; the previous journal is deleted once the save is on disk.
+
+pub const JOURNAL_FILE_NAME: &str = "journal.bin";
+pub const PREV_JOURNAL_FILE_NAME: &str = "journal.prev.bin";
+const JOURNAL_VERSION: u16 = 1;
+const MAGIC: &[u8; 4] = b"SGRJ";
+const HEADER_LEN: usize = 16;
+const RECORD_LEN: usize = 14;
+// unflushed edits are at most this old
+const FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(1);
+
+fn journal_path(world_dir: &Path, name: &str) -> PathBuf {
+    let mut pbuf = world_dir.to_path_buf();
+    pbuf.push(name);
+    pbuf
+}
+
+fn read_header(bytes: &[u8]) -> Result<u64, Error> {
+    if bytes.len() < HEADER_LEN || &bytes[0..4] != MAGIC {
+        return Err( Error::new( ErrorKind::InvalidData, "not an edit journal" ) );
+    }
+    let version = u16::from_le_bytes( [bytes[4], bytes[5]] );
+    if version > JOURNAL_VERSION {
+        return Err( Error::new( ErrorKind::InvalidData, format!("journal version {} is newer than this game supports", version) ) );
+    }
+    let mut base = [0_u8; 8];
+    base.copy_from_slice( &bytes[8..16] );
+    Ok( u64::from_le_bytes(base) )
+}
+
+fn push_block(out: &mut Vec<u8>, bi: &BlockInstance) {
+    out.extend_from_slice( &bi.blockdef.to_le_bytes() );
+    out.push( bi.exparam );
+    out.push( bi.light );
+}
+
+fn read_block(bytes: &[u8]) -> BlockInstance {
+    BlockInstance{
+        blockdef: u16::from_le_bytes( [bytes[0], bytes[1]] ),
+        exparam: bytes[2],
+        light: bytes[3],
+    }
+}
+
+pub struct EditJournal {
+    world_dir: PathBuf,
+    writer: BufWriter<File>,
+    base_save_id: u64,
+    last_flush: std::time::Instant,
+    unflushed: bool,
+}
+
+impl EditJournal {
+    // opens the world's journal for appending. a journal left over from before the current save is started over.
+    pub fn open(world_dir: &Path, save_id: u64) -> Result<EditJournal, Error> {
+        let path = journal_path(world_dir, JOURNAL_FILE_NAME);
+        let mut existing_base = None;
+        if path.exists() {
+            let mut header = vec![ 0_u8; HEADER_LEN ];
+            let mut fi = File::open(&path)?;
+            if fi.read_exact(&mut header).is_ok() {
+                existing_base = read_header(&header).ok();
+            }
+        }
+
+        match existing_base {
+            Some(base) if base >= save_id => {
+                let file = OpenOptions::new().append(true).open(&path)?;
+                Ok( Self::from_file(world_dir, file, base) )
+            }
+            _ => Self::create(world_dir, save_id),
+        }
+    }
+
+    fn create(world_dir: &Path, save_id: u64) -> Result<EditJournal, Error> {
+        let mut file = File::create( journal_path(world_dir, JOURNAL_FILE_NAME) )?;
+        let mut header = Vec::<u8>::with_capacity(HEADER_LEN);
+        header.extend_from_slice(MAGIC);
+        header.extend_from_slice( &JOURNAL_VERSION.to_le_bytes() );
+        header.extend_from_slice( &0_u16.to_le_bytes() );
+        header.extend_from_slice( &save_id.to_le_bytes() );
+        file.write_all(&header)?;
+        file.sync_data()?;
+        Ok( Self::from_file(world_dir, file, save_id) )
+    }
+
+    fn from_file(world_dir: &Path, file: File, base_save_id: u64) -> EditJournal {
+        Self {
+            world_dir: world_dir.to_path_buf(),
+            writer: BufWriter::new(file),
+            base_save_id,
+            last_flush: std::time::Instant::now(),
+            unflushed: false,
+        }
+    }
+
+    pub fn record(&mut self, pos: (usize, usize, usize), old: &BlockInstance, new: &BlockInstance) -> Result<(), Error> {
+        let mut rec = Vec::<u8>::with_capacity(RECORD_LEN);
+        rec.extend_from_slice( &(pos.0 as u16).to_le_bytes() );
+        rec.extend_from_slice( &(pos.1 as u16).to_le_bytes() );
+        rec.extend_from_slice( &(pos.2 as u16).to_le_bytes() );
+        push_block(&mut rec, old);
+        push_block(&mut rec, new);
+        self.writer.write_all(&rec)?;
+        self.unflushed = true;
+        Ok(())
+    }
+
+    // called every frame, pushes buffered edits to disk once a second
+    pub fn flush_if_due(&mut self) -> Result<(), Error> {
+        if self.unflushed && self.last_flush.elapsed() >= FLUSH_INTERVAL {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), Error> {
+        self.writer.flush()?;
+        self.writer.get_ref().sync_data()?;
+        self.last_flush = std::time::Instant::now();
+        self.unflushed = false;
+        Ok(())
+    }
+
+    // a snapshot for save save_id was just taken: keep the current edits aside until that save is written
+    pub fn rotate(&mut self, save_id: u64) -> Result<(), Error> {
+        self.flush()?;
+        let prev = journal_path(&self.world_dir, PREV_JOURNAL_FILE_NAME);
+        // an older previous journal is still waiting on a failed save, fold the current edits into it
+        if prev.exists() {
+            let current = std::fs::read( journal_path(&self.world_dir, JOURNAL_FILE_NAME) )?;
+            let mut fi = OpenOptions::new().append(true).open(&prev)?;
+            fi.write_all( &current[ std::cmp::min(HEADER_LEN, current.len()).. ] )?;
+            fi.sync_data()?;
+        } else {
+            std::fs::rename( journal_path(&self.world_dir, JOURNAL_FILE_NAME), &prev )?;
+        }
+        *self = Self::create(&self.world_dir, save_id)?;
+        Ok(())
+    }
+
+    // the save the previous journal was waiting on is on disk now
+    pub fn drop_previous(&mut self) -> Result<(), Error> {
+        let prev = journal_path(&self.world_dir, PREV_JOURNAL_FILE_NAME);
+        if prev.exists() {
+            std::fs::remove_file(prev)?;
+        }
+        Ok(())
+    }
+
+    // a full save of save_id finished, nothing journaled so far is needed anymore
+    pub fn clear(&mut self, save_id: u64) -> Result<(), Error> {
+        self.drop_previous()?;
+        self.writer.flush()?;
+        *self = Self::create(&self.world_dir, save_id)?;
+        Ok(())
+    }
+
+    pub fn base_save_id(&self) -> u64 {
+        self.base_save_id
+    }
+}
+
+// applies journaled edits newer than the loaded save on top of it, returns how many were replayed
+pub fn replay(world_dir: &Path, save_id: u64, chunk_manager: &mut ChunkManager) -> Result<usize, Error> {
+    let world_width = CHUNK_SIZE * WORLD_CHUNKS[chunk_manager.size];
+    let mut count = 0;
+
+    for name in [ PREV_JOURNAL_FILE_NAME, JOURNAL_FILE_NAME ] {
+        let path = journal_path(world_dir, name);
+        if !path.exists() {
+            continue;
+        }
+        let bytes = std::fs::read(&path)?;
+        let base = match read_header(&bytes) {
+            Ok(base) => base,
+            Err(e) => {
+                log::warn!("skipping unreadable journal {}: {}", path.display(), e);
+                continue;
+            }
+        };
+        // these edits are already part of the save
+        if base < save_id {
+            continue;
+        }
+
+        // a torn final record from a crash mid-write is simply ignored
+        for rec in bytes[HEADER_LEN..].chunks_exact(RECORD_LEN) {
+            let pos = (
+                u16::from_le_bytes( [rec[0], rec[1]] ) as usize,
+                u16::from_le_bytes( [rec[2], rec[3]] ) as usize,
+                u16::from_le_bytes( [rec[4], rec[5]] ) as usize,
+            );
+            if pos.0 >= world_width || pos.1 >= world_width || pos.2 >= world_width {
+                continue;
+            }
+            chunk_manager.load_chunk( ( pos.0 / CHUNK_SIZE, pos.1 / CHUNK_SIZE, pos.2 / CHUNK_SIZE ) )?;
+            *chunk_manager.get_mut_block(pos) = read_block( &rec[10..14] );
+            count += 1;
+        }
+    }
+
+    Ok(count)
+}
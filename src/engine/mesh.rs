@@ -0,0 +1,20 @@
+// one corner of a meshed block face, laid out the way the block shaders read it
+#[repr(C)]
+#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct Vertex {
+    pub position: [f32; 3],
+    pub uv: [f32; 2],
+    pub array_index: u32,
+    pub light: f32,
+}
+
+impl Vertex {
+    pub fn new(position: [f32; 3], uv: [f32; 2], array_index: u32, light: f32) -> Vertex {
+        Self{
+            position,
+            uv,
+            array_index,
+            light
+        }
+    }
+}
@@ -0,0 +1,241 @@
+use std::io::Error;
+use std::path::PathBuf;
+
+use cgmath::InnerSpace;
+
+use crate::engine::block_data;
+use crate::engine::chunk::BlockDrawContext;
+use crate::engine::mesh::Vertex;
+use crate::engine::palette;
+use crate::engine::rotation_group;
+use crate::engine::world_edit::{BlockArea, WorldEditor};
+
+// turns blocks into a plain triangle mesh with one baked texture, for use outside the game
+
+// empty pixels around each texture in the atlas, filled with the texture's edge so
+// filtering near a face's border doesn't pick up the neighbouring texture
+const GUTTER: u32 = 1;
+
+// every block texture side by side in one picture, as it looks in game at full light
+pub struct TextureAtlas {
+    pub image: image::RgbaImage,
+    cell: (u32, u32),
+    columns: u32,
+}
+
+impl TextureAtlas {
+    pub fn bake(config: &block_data::Config) -> Result<TextureAtlas, Error> {
+        let palette = palette::load_png( &PathBuf::from(palette::PALETTE_PATH) )?;
+        let colormap = palette::load_png( &PathBuf::from(palette::COLORMAP_PATH) )?;
+
+        let mut textures = Vec::new();
+        for tex_name in block_data::texture_names(config).iter() {
+            textures.push( palette::load_block_texture( tex_name, &palette, &colormap )? );
+        }
+
+        let cell = textures.iter().fold( (1, 1), |c, t| ( c.0.max( t.width() ), c.1.max( t.height() ) ) );
+        let columns = ( ( textures.len() as f32 ).sqrt().ceil() as u32 ).max(1);
+        let rows = ( ( textures.len() as u32 + columns - 1 ) / columns ).max(1);
+        let stride = ( cell.0 + 2 * GUTTER, cell.1 + 2 * GUTTER );
+
+        let mut image = image::RgbaImage::new( columns * stride.0, rows * stride.1 );
+        for (i, tex) in textures.iter().enumerate() {
+            let tex = if tex.dimensions() == cell { tex.clone() } else { image::imageops::resize( tex, cell.0, cell.1, image::imageops::FilterType::Nearest ) };
+            let origin = ( ( i as u32 % columns ) * stride.0, ( i as u32 / columns ) * stride.1 );
+            for y in 0..stride.1 {
+                for x in 0..stride.0 {
+                    let sx = ( x as i64 - GUTTER as i64 ).clamp( 0, cell.0 as i64 - 1 ) as u32;
+                    let sy = ( y as i64 - GUTTER as i64 ).clamp( 0, cell.1 as i64 - 1 ) as u32;
+                    image.put_pixel( origin.0 + x, origin.1 + y, *tex.get_pixel(sx, sy) );
+                }
+            }
+        }
+
+        Ok( TextureAtlas{ image, cell, columns } )
+    }
+
+    // where a texture coordinate of one block texture ends up in the atlas, v pointing down
+    pub fn uv(&self, array_index: u32, uv: [f32; 2]) -> [f32; 2] {
+        let stride = ( self.cell.0 + 2 * GUTTER, self.cell.1 + 2 * GUTTER );
+        let x = ( array_index % self.columns ) * stride.0 + GUTTER;
+        let y = ( array_index / self.columns ) * stride.1 + GUTTER;
+        [
+            ( x as f32 + uv[0] * self.cell.0 as f32 ) / self.image.width() as f32,
+            ( y as f32 + uv[1] * self.cell.1 as f32 ) / self.image.height() as f32,
+        ]
+    }
+}
+
+#[derive(Default)]
+pub struct ExportMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    pub uvs: Vec<[f32; 2]>,
+    pub indices: Vec<u32>,
+}
+
+impl ExportMesh {
+    pub fn triangle_count(&self) -> usize {
+        self.indices.len() / 3
+    }
+
+    // appends one block's vertices, moved so the area's minimum corner sits at the origin
+    fn append(&mut self, vertices: &[Vertex], indices: &[u16], origin: (usize, usize, usize), atlas: &TextureAtlas) {
+        let base = self.positions.len() as u32;
+        for v in vertices.iter() {
+            self.positions.push( [ v.position[0] - origin.0 as f32, v.position[1] - origin.1 as f32, v.position[2] - origin.2 as f32 ] );
+            self.normals.push( [0.0; 3] );
+            self.uvs.push( atlas.uv( v.array_index, v.uv ) );
+        }
+
+        // faces never share vertices, so each vertex takes the normal of its face
+        for tri in indices.chunks_exact(3) {
+            let p: Vec<cgmath::Vector3<f32>> = tri.iter().map( |i| cgmath::Vector3::from( vertices[ *i as usize ].position ) ).collect();
+            let n = ( p[1] - p[0] ).cross( p[2] - p[0] ).normalize();
+            for i in tri.iter() {
+                self.normals[ ( base + *i as u32 ) as usize ] = [ n.x, n.y, n.z ];
+            }
+            self.indices.extend( tri.iter().map( |i| base + *i as u32 ) );
+        }
+    }
+}
+
+// what hides each face of the block at pos. blocks outside the area count as air, so the
+// mesh of a selection is closed where it was cut out of the world
+fn draw_context(editor: &WorldEditor, area: &BlockArea, pos: (usize, usize, usize)) -> BlockDrawContext {
+    let mut out = [false; 6];
+    for idx in 0..6 {
+        let rf = rotation_group::num_to_rf(idx).unwrap();
+        let v = rotation_group::rf_to_vector(rf);
+        let opos = ( pos.0 as i64 + v.x as i64, pos.1 as i64 + v.y as i64, pos.2 as i64 + v.z as i64 );
+        let opos = match editor.in_world(opos) {
+            Some(opos) if area.contains(opos) => opos,
+            _ => continue,
+        };
+
+        let bi = editor.get(opos);
+        if let Some(bdef) = editor.registry.get(bi.blockdef) {
+            if !bdef.transparent {
+                let sdef = editor.shape_registry.get(bdef.shape_id).unwrap();
+                if sdef.valid_exparam(bi.exparam) {
+                    out[ idx as usize ] = sdef.does_obstruct( bi.exparam, rotation_group::reverse_rf(rf) );
+                }
+            }
+        }
+    }
+    BlockDrawContext{ obstructions: out }
+}
+
+// meshes every block in the area the same way the game meshes chunks, hidden faces left out
+pub fn build_mesh(editor: &WorldEditor, area: &BlockArea, atlas: &TextureAtlas) -> ExportMesh {
+    let mut mesh = ExportMesh::default();
+    let mut vertices = Vec::<Vertex>::new();
+    let mut indices = Vec::<u16>::new();
+
+    for x in area.min.0..=area.max.0 {
+        for y in area.min.1..=area.max.1 {
+            for z in area.min.2..=area.max.2 {
+                let bi = editor.get( (x, y, z) );
+                if bi.blockdef == 0 {
+                    continue;
+                }
+                let bdef = match editor.registry.get(bi.blockdef) {
+                    Some(bdef) => bdef,
+                    None => continue,
+                };
+                let shape = editor.shape_registry.get(bdef.shape_id).unwrap();
+                if !shape.valid_exparam(bi.exparam) {
+                    continue;
+                }
+
+                vertices.clear();
+                indices.clear();
+                shape.generate_draw_buffers( &mut vertices, &mut indices, bdef, bi.exparam, draw_context( editor, area, (x, y, z) ), (0, 0, 0), (x, y, z) );
+                mesh.append( &vertices, &indices, area.min, atlas );
+            }
+        }
+    }
+    mesh
+}
+
+fn push_aligned(bin: &mut Vec<u8>, data: &[u8]) -> (usize, usize) {
+    let offset = bin.len();
+    bin.extend_from_slice(data);
+    while bin.len() % 4 != 0 {
+        bin.push(0);
+    }
+    (offset, data.len())
+}
+
+// a binary glTF 2.0 file holding the mesh and the atlas as its only texture
+pub fn write_glb(mesh: &ExportMesh, atlas_png: &[u8]) -> Result<Vec<u8>, Error> {
+    if mesh.indices.is_empty() {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, "there are no block faces to export" ) );
+    }
+
+    let mut bin = Vec::<u8>::new();
+    let positions = push_aligned( &mut bin, bytemuck::cast_slice(&mesh.positions) );
+    let normals = push_aligned( &mut bin, bytemuck::cast_slice(&mesh.normals) );
+    let uvs = push_aligned( &mut bin, bytemuck::cast_slice(&mesh.uvs) );
+    let indices = push_aligned( &mut bin, bytemuck::cast_slice(&mesh.indices) );
+    let png = push_aligned( &mut bin, atlas_png );
+
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for p in mesh.positions.iter() {
+        for i in 0..3 {
+            min[i] = min[i].min(p[i]);
+            max[i] = max[i].max(p[i]);
+        }
+    }
+
+    // 34962 is ARRAY_BUFFER, 34963 ELEMENT_ARRAY_BUFFER, 5126 FLOAT, 5125 UNSIGNED_INT, 9728 NEAREST
+    let json = format!( r#"{{"asset":{{"version":"2.0","generator":"SGR Cubes {version}"}},"scene":0,"scenes":[{{"nodes":[0]}}],"nodes":[{{"mesh":0,"name":"blocks"}}],"meshes":[{{"name":"blocks","primitives":[{{"attributes":{{"POSITION":0,"NORMAL":1,"TEXCOORD_0":2}},"indices":3,"material":0}}]}}],"materials":[{{"name":"blocks","pbrMetallicRoughness":{{"baseColorTexture":{{"index":0}},"metallicFactor":0.0,"roughnessFactor":1.0}},"alphaMode":"MASK","alphaCutoff":0.5}}],"textures":[{{"sampler":0,"source":0}}],"samplers":[{{"magFilter":9728,"minFilter":9728}}],"images":[{{"bufferView":4,"mimeType":"image/png"}}],"accessors":[{{"bufferView":0,"componentType":5126,"count":{verts},"type":"VEC3","min":[{min0},{min1},{min2}],"max":[{max0},{max1},{max2}]}},{{"bufferView":1,"componentType":5126,"count":{verts},"type":"VEC3"}},{{"bufferView":2,"componentType":5126,"count":{verts},"type":"VEC2"}},{{"bufferView":3,"componentType":5125,"count":{inds},"type":"SCALAR"}}],"bufferViews":[{{"buffer":0,"byteOffset":{po},"byteLength":{pl},"target":34962}},{{"buffer":0,"byteOffset":{no},"byteLength":{nl},"target":34962}},{{"buffer":0,"byteOffset":{uo},"byteLength":{ul},"target":34962}},{{"buffer":0,"byteOffset":{io},"byteLength":{il},"target":34963}},{{"buffer":0,"byteOffset":{go},"byteLength":{gl}}}],"buffers":[{{"byteLength":{bl}}}]}}"#,
+        version = env!("CARGO_PKG_VERSION"),
+        verts = mesh.positions.len(), inds = mesh.indices.len(),
+        min0 = min[0], min1 = min[1], min2 = min[2], max0 = max[0], max1 = max[1], max2 = max[2],
+        po = positions.0, pl = positions.1, no = normals.0, nl = normals.1, uo = uvs.0, ul = uvs.1,
+        io = indices.0, il = indices.1, go = png.0, gl = png.1, bl = bin.len() );
+
+    let mut json = json.into_bytes();
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+
+    let mut out = Vec::<u8>::with_capacity( 28 + json.len() + bin.len() );
+    out.extend_from_slice(b"glTF");
+    out.extend_from_slice( &2_u32.to_le_bytes() );
+    out.extend_from_slice( &( ( 28 + json.len() + bin.len() ) as u32 ).to_le_bytes() );
+    out.extend_from_slice( &( json.len() as u32 ).to_le_bytes() );
+    out.extend_from_slice(b"JSON");
+    out.extend_from_slice(&json);
+    out.extend_from_slice( &( bin.len() as u32 ).to_le_bytes() );
+    out.extend_from_slice(b"BIN\0");
+    out.extend_from_slice(&bin);
+    Ok(out)
+}
+
+// Wavefront OBJ text, using the material called blocks from mtl_file
+pub fn write_obj(mesh: &ExportMesh, mtl_file: &str) -> String {
+    let mut out = String::new();
+    out.push_str( &format!( "# SGR Cubes {}\nmtllib {}\no blocks\n", env!("CARGO_PKG_VERSION"), mtl_file ) );
+    for p in mesh.positions.iter() {
+        out.push_str( &format!( "v {} {} {}\n", p[0], p[1], p[2] ) );
+    }
+    // OBJ texture coordinates have v pointing up
+    for uv in mesh.uvs.iter() {
+        out.push_str( &format!( "vt {} {}\n", uv[0], 1.0 - uv[1] ) );
+    }
+    for n in mesh.normals.iter() {
+        out.push_str( &format!( "vn {} {} {}\n", n[0], n[1], n[2] ) );
+    }
+    out.push_str("usemtl blocks\n");
+    for tri in mesh.indices.chunks_exact(3) {
+        out.push_str( &format!( "f {0}/{0}/{0} {1}/{1}/{1} {2}/{2}/{2}\n", tri[0] + 1, tri[1] + 1, tri[2] + 1 ) );
+    }
+    out
+}
+
+pub fn write_mtl(texture_file: &str) -> String {
+    format!( "newmtl blocks\nKa 1 1 1\nKd 1 1 1\nKs 0 0 0\nillum 1\nmap_Kd {0}\nmap_d {0}\n", texture_file )
+}
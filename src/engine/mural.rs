@@ -0,0 +1,78 @@
+use crate::engine::palette::BlockColors;
+
+// pictures built out of blocks, one block per pixel, each the block whose colour comes closest
+
+// which way the picture is laid out in the world
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum MuralPlane {
+    // upright, running along x
+    WallX,
+    // upright, running along z
+    WallZ,
+    // lying flat, picture rows running along z
+    Floor,
+}
+
+impl MuralPlane {
+    pub fn parse(text: &str) -> Option<MuralPlane> {
+        match text {
+            "wall-x" => Some( MuralPlane::WallX ),
+            "wall-z" => Some( MuralPlane::WallZ ),
+            "floor" => Some( MuralPlane::Floor ),
+            _ => None,
+        }
+    }
+
+    // block offset of a pixel from the mural's lowest corner, the picture's top row is at the top of walls
+    pub fn offset(&self, px: u32, py: u32, height: u32) -> (i64, i64, i64) {
+        let up = ( height - 1 - py ) as i64;
+        match self {
+            MuralPlane::WallX => ( px as i64, up, 0 ),
+            MuralPlane::WallZ => ( 0, up, px as i64 ),
+            MuralPlane::Floor => ( px as i64, 0, py as i64 ),
+        }
+    }
+}
+
+// below this alpha a pixel is left empty
+const ALPHA_CUTOFF: u8 = 128;
+
+// the block for each pixel that isn't see-through. with dither the colour each block
+// misses by is spread onto the pixels not done yet (Floyd-Steinberg), which trades
+// flat patches for a closer match over an area
+pub fn build_mural(img: &image::RgbaImage, colors: &BlockColors, candidates: &[u16], dither: bool) -> Vec<( (u32, u32), u16 )> {
+    let (width, height) = img.dimensions();
+    let mut wanted: Vec<[f32; 3]> = img.pixels().map( |p| [ p.0[0] as f32, p.0[1] as f32, p.0[2] as f32 ] ).collect();
+    let mut out = Vec::new();
+
+    for y in 0..height {
+        for x in 0..width {
+            if img.get_pixel(x, y).0[3] < ALPHA_CUTOFF {
+                continue;
+            }
+            let want = wanted[ ( y * width + x ) as usize ];
+            let color = [ want[0].clamp(0.0, 255.0) as u8, want[1].clamp(0.0, 255.0) as u8, want[2].clamp(0.0, 255.0) as u8 ];
+            let id = match colors.nearest_block( color, candidates ) {
+                Some(id) => id,
+                None => continue,
+            };
+            out.push( ( (x, y), id ) );
+
+            if dither {
+                let got = colors.get(id).unwrap();
+                let error = [ want[0] - got[0] as f32, want[1] - got[1] as f32, want[2] - got[2] as f32 ];
+                for (dx, dy, weight) in [ (1, 0, 7.0), (-1, 1, 3.0), (0, 1, 5.0), (1, 1, 1.0) ] {
+                    let (nx, ny) = ( x as i64 + dx, y as i64 + dy );
+                    if nx < 0 || nx >= width as i64 || ny >= height as i64 {
+                        continue;
+                    }
+                    let n = &mut wanted[ ( ny as u32 * width + nx as u32 ) as usize ];
+                    for ch in 0..3 {
+                        n[ch] += error[ch] * weight / 16.0;
+                    }
+                }
+            }
+        }
+    }
+    out
+}
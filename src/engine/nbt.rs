@@ -0,0 +1,275 @@
+use std::collections::HashMap;
+use std::io::{Error, Read};
+
+use crate::engine::invalid;
+
+// Minecraft's Named Binary Tag format, as used by schematic files. big endian, usually gzipped
+
+// deeper nesting than this is a broken or hostile file
+const MAX_DEPTH: usize = 512;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Tag {
+    Byte(i8),
+    Short(i16),
+    Int(i32),
+    Long(i64),
+    Float(f32),
+    Double(f64),
+    ByteArray(Vec<i8>),
+    String(String),
+    List(Vec<Tag>),
+    Compound(HashMap<String, Tag>),
+    IntArray(Vec<i32>),
+    LongArray(Vec<i64>),
+}
+
+impl Tag {
+    pub fn get(&self, key: &str) -> Option<&Tag> {
+        match self {
+            Tag::Compound(map) => map.get(key),
+            _ => None,
+        }
+    }
+
+    // any of the integer tags
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            Tag::Byte(v) => Some( *v as i64 ),
+            Tag::Short(v) => Some( *v as i64 ),
+            Tag::Int(v) => Some( *v as i64 ),
+            Tag::Long(v) => Some(*v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Tag::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    pub fn as_compound(&self) -> Option<&HashMap<String, Tag>> {
+        match self {
+            Tag::Compound(map) => Some(map),
+            _ => None,
+        }
+    }
+
+    pub fn as_list(&self) -> Option<&Vec<Tag>> {
+        match self {
+            Tag::List(list) => Some(list),
+            _ => None,
+        }
+    }
+}
+
+struct NbtReader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> NbtReader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], Error> {
+        let out = self.bytes.get( self.at..self.at + n ).ok_or( invalid( "the NBT data ends too early".to_string() ) )?;
+        self.at += n;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, Error> {
+        Ok( self.take(1)?[0] )
+    }
+
+    fn i16(&mut self) -> Result<i16, Error> {
+        let b = self.take(2)?;
+        Ok( i16::from_be_bytes( [b[0], b[1]] ) )
+    }
+
+    fn i32(&mut self) -> Result<i32, Error> {
+        let b = self.take(4)?;
+        Ok( i32::from_be_bytes( [b[0], b[1], b[2], b[3]] ) )
+    }
+
+    fn i64(&mut self) -> Result<i64, Error> {
+        let b = self.take(8)?;
+        Ok( i64::from_be_bytes( [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]] ) )
+    }
+
+    // array lengths are signed, a negative one can only come from a broken file
+    fn len(&mut self) -> Result<usize, Error> {
+        let len = self.i32()?;
+        if len < 0 {
+            return Err( invalid( format!( "negative length {} in the NBT data", len ) ) );
+        }
+        Ok( len as usize )
+    }
+
+    // Java's modified UTF-8 only differs from UTF-8 for characters schematics don't use
+    fn string(&mut self) -> Result<String, Error> {
+        let len = self.i16()? as u16 as usize;
+        Ok( String::from_utf8_lossy( self.take(len)? ).into_owned() )
+    }
+
+    fn payload(&mut self, tag_type: u8, depth: usize) -> Result<Tag, Error> {
+        if depth > MAX_DEPTH {
+            return Err( invalid( "the NBT data is nested too deep".to_string() ) );
+        }
+        Ok( match tag_type {
+            1 => Tag::Byte( self.u8()? as i8 ),
+            2 => Tag::Short( self.i16()? ),
+            3 => Tag::Int( self.i32()? ),
+            4 => Tag::Long( self.i64()? ),
+            5 => Tag::Float( f32::from_bits( self.i32()? as u32 ) ),
+            6 => Tag::Double( f64::from_bits( self.i64()? as u64 ) ),
+            7 => {
+                let len = self.len()?;
+                Tag::ByteArray( self.take(len)?.iter().map( |b| *b as i8 ).collect() )
+            }
+            8 => Tag::String( self.string()? ),
+            9 => {
+                let item_type = self.u8()?;
+                let len = self.len()?;
+                let mut list = Vec::with_capacity( len.min(65536) );
+                for _ in 0..len {
+                    list.push( self.payload( item_type, depth + 1 )? );
+                }
+                Tag::List(list)
+            }
+            10 => {
+                let mut map = HashMap::new();
+                loop {
+                    let item_type = self.u8()?;
+                    if item_type == 0 {
+                        break;
+                    }
+                    let name = self.string()?;
+                    map.insert( name, self.payload( item_type, depth + 1 )? );
+                }
+                Tag::Compound(map)
+            }
+            11 => {
+                let len = self.len()?;
+                let mut arr = Vec::with_capacity( len.min(65536) );
+                for _ in 0..len {
+                    arr.push( self.i32()? );
+                }
+                Tag::IntArray(arr)
+            }
+            12 => {
+                let len = self.len()?;
+                let mut arr = Vec::with_capacity( len.min(65536) );
+                for _ in 0..len {
+                    arr.push( self.i64()? );
+                }
+                Tag::LongArray(arr)
+            }
+            t => return Err( invalid( format!( "unknown NBT tag type {}", t ) ) ),
+        } )
+    }
+}
+
+// the root tag and its name, gzipped data is unpacked first
+pub fn read_nbt(bytes: &[u8]) -> Result<(String, Tag), Error> {
+    let unpacked;
+    let bytes = if bytes.starts_with( &[0x1f, 0x8b] ) {
+        let mut out = Vec::new();
+        flate2::read::GzDecoder::new(bytes).read_to_end(&mut out)?;
+        unpacked = out;
+        &unpacked[..]
+    } else {
+        bytes
+    };
+
+    let mut rd = NbtReader{ bytes, at: 0 };
+    let root_type = rd.u8()?;
+    if root_type == 0 {
+        return Err( invalid( "the NBT data is empty".to_string() ) );
+    }
+    let name = rd.string()?;
+    let tag = rd.payload( root_type, 0 )?;
+    Ok( (name, tag) )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    fn named(out: &mut Vec<u8>, tag_type: u8, name: &str) {
+        out.push(tag_type);
+        out.extend_from_slice( &( name.len() as u16 ).to_be_bytes() );
+        out.extend_from_slice( name.as_bytes() );
+    }
+
+    // a compound called Schematic holding one tag of most types
+    fn sample() -> Vec<u8> {
+        let mut out = Vec::new();
+        named( &mut out, 10, "Schematic" );
+        named( &mut out, 2, "Width" );
+        out.extend_from_slice( &300_i16.to_be_bytes() );
+        named( &mut out, 8, "Name" );
+        out.extend_from_slice( &5_u16.to_be_bytes() );
+        out.extend_from_slice( b"stone" );
+        named( &mut out, 7, "Data" );
+        out.extend_from_slice( &3_i32.to_be_bytes() );
+        out.extend_from_slice( &[ 1, 2, 0xff ] );
+        named( &mut out, 12, "States" );
+        out.extend_from_slice( &1_i32.to_be_bytes() );
+        out.extend_from_slice( &( -2_i64 ).to_be_bytes() );
+        named( &mut out, 9, "Offsets" );
+        out.push(3);
+        out.extend_from_slice( &2_i32.to_be_bytes() );
+        out.extend_from_slice( &7_i32.to_be_bytes() );
+        out.extend_from_slice( &( -7_i32 ).to_be_bytes() );
+        out.push(0);
+        out
+    }
+
+    fn check_sample(name: &str, root: &Tag) {
+        assert_eq!( name, "Schematic" );
+        assert_eq!( root.get("Width").and_then( |t| t.as_int() ), Some(300) );
+        assert_eq!( root.get("Name").and_then( |t| t.as_str() ), Some("stone") );
+        assert_eq!( root.get("Data"), Some( &Tag::ByteArray( vec![ 1, 2, -1 ] ) ) );
+        assert_eq!( root.get("States"), Some( &Tag::LongArray( vec![ -2 ] ) ) );
+        assert_eq!( root.get("Offsets").and_then( |t| t.as_list() ), Some( &vec![ Tag::Int(7), Tag::Int(-7) ] ) );
+    }
+
+    #[test]
+    fn reads_plain_nbt() {
+        let (name, root) = read_nbt( &sample() ).unwrap();
+        check_sample( &name, &root );
+    }
+
+    #[test]
+    fn reads_gzipped_nbt() {
+        let mut gz = flate2::write::GzEncoder::new( Vec::new(), flate2::Compression::default() );
+        gz.write_all( &sample() ).unwrap();
+        let (name, root) = read_nbt( &gz.finish().unwrap() ).unwrap();
+        check_sample( &name, &root );
+    }
+
+    #[test]
+    fn rejects_broken_nbt() {
+        let bytes = sample();
+        for cut in [ 1, 10, bytes.len() / 2, bytes.len() - 1 ] {
+            assert!( read_nbt( &bytes[ ..cut ] ).is_err() );
+        }
+        assert!( read_nbt( &[0] ).is_err() );
+
+        // a byte array claiming more data than there is
+        let mut out = Vec::new();
+        named( &mut out, 7, "" );
+        out.extend_from_slice( &i32::MAX.to_be_bytes() );
+        assert!( read_nbt(&out).is_err() );
+
+        // lists of lists nested past the limit
+        let mut out = Vec::new();
+        named( &mut out, 9, "" );
+        for _ in 0..( MAX_DEPTH + 2 ) {
+            out.push(9);
+            out.extend_from_slice( &1_i32.to_be_bytes() );
+        }
+        assert!( read_nbt(&out).is_err() );
+    }
+}
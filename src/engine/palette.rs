@@ -0,0 +1,124 @@
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use crate::engine::block_data::Config;
+
+pub const PALETTE_PATH: &str = "res/texture/core/palette.png";
+pub const COLORMAP_PATH: &str = "res/texture/core/colormap.png";
+pub const BLOCK_TEXTURE_PATH: &str = "res/texture/block";
+// the block shader draws nothing for this palette index
+pub const TRANSPARENT_INDEX: u8 = 255;
+
+pub fn load_png(path: &Path) -> Result<image::RgbaImage, Error> {
+    let img = image::open(path).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, format!( "{}: {}", path.display(), e ) ) )?;
+    Ok( img.to_rgba8() )
+}
+
+// resamples a picture to columns pixels across, keeping its aspect ratio. None keeps it as it is
+pub fn resize_to_columns(img: image::DynamicImage, columns: Option<u32>) -> image::DynamicImage {
+    match columns {
+        Some(w) if w != img.width() => {
+            let h = ( ( img.height() as f32 * w as f32 / img.width() as f32 ).round() as u32 ).max(1);
+            img.resize_exact( w.max(1), h, image::imageops::FilterType::Triangle )
+        }
+        _ => img,
+    }
+}
+
+// squared distance in RGB, alpha is ignored like it is when textures are palettized
+pub fn color_distance(a: [u8; 3], b: [u8; 3]) -> f32 {
+    ( a[0] as f32 - b[0] as f32 ).powi(2) + ( a[1] as f32 - b[1] as f32 ).powi(2) + ( a[2] as f32 - b[2] as f32 ).powi(2)
+}
+
+// index of the palette entry closest to color, palette entries are read row by row
+pub fn nearest_index(palette: &image::RgbaImage, color: [u8; 3]) -> u8 {
+    let (width, height) = palette.dimensions();
+    let mut d = f32::MAX;
+    let mut out_index: u8 = 0;
+    for i in 0..( width * height ) {
+        let pc = palette.get_pixel(i % width, i / width);
+        let nd = color_distance( color, [pc.0[0], pc.0[1], pc.0[2]] );
+        if nd < d {
+            d = nd;
+            out_index = i as u8;
+        }
+    }
+    out_index
+}
+
+// turns a block texture into palette indices, which is how block textures are stored on the GPU
+pub fn palettize(img: &image::RgbaImage, palette: &image::RgbaImage) -> image::GrayImage {
+    image::GrayImage::from_fn( img.width(), img.height(), |x, y| {
+        let ic = img.get_pixel(x, y);
+        image::Luma( [ nearest_index( palette, [ic.0[0], ic.0[1], ic.0[2]] ) ] )
+    } )
+}
+
+// a block texture the way the block shader draws it in full light, see-through pixels get alpha 0
+pub fn load_block_texture(tex_name: &str, palette: &image::RgbaImage, colormap: &image::RgbaImage) -> Result<image::RgbaImage, Error> {
+    let mut pbuf = PathBuf::from(BLOCK_TEXTURE_PATH);
+    pbuf.push(tex_name);
+    let indices = palettize( &load_png(&pbuf)?, palette );
+    Ok( image::RgbaImage::from_fn( indices.width(), indices.height(), |x, y| {
+        let idx = indices.get_pixel(x, y).0[0];
+        if idx == TRANSPARENT_INDEX {
+            image::Rgba( [0, 0, 0, 0] )
+        } else {
+            // row 0 of the colormap is full light
+            *colormap.get_pixel( std::cmp::min( idx as u32, colormap.width() - 1 ), 0 )
+        }
+    } ) )
+}
+
+// what each block looks like on screen in full light, averaged over all of its textures
+pub struct BlockColors {
+    // by block ID, None for air and blocks that are entirely see-through
+    colors: Vec<Option<[u8; 3]>>,
+}
+
+impl BlockColors {
+    pub fn load(config: &Config) -> Result<BlockColors, Error> {
+        let palette = load_png( &PathBuf::from(PALETTE_PATH) )?;
+        let colormap = load_png( &PathBuf::from(COLORMAP_PATH) )?;
+
+        let mut colors = vec![ None ];
+        for bp in config.block.iter() {
+            let mut sum = [0_u64; 3];
+            let mut count = 0_u64;
+            for tex_name in bp.textures.iter() {
+                for c in load_block_texture( tex_name, &palette, &colormap )?.pixels() {
+                    if c.0[3] == 0 {
+                        continue;
+                    }
+                    for ch in 0..3 {
+                        sum[ch] += c.0[ch] as u64;
+                    }
+                    count += 1;
+                }
+            }
+            colors.push( if count == 0 { None } else { Some( [ ( sum[0] / count ) as u8, ( sum[1] / count ) as u8, ( sum[2] / count ) as u8 ] ) } );
+        }
+
+        Ok( BlockColors{ colors } )
+    }
+
+    pub fn get(&self, id: u16) -> Option<[u8; 3]> {
+        self.colors.get(id as usize).copied().flatten()
+    }
+
+    // the block among candidates whose colour is closest, None if none of them have a colour
+    pub fn nearest_block(&self, color: [u8; 3], candidates: &[u16]) -> Option<u16> {
+        let mut best = None;
+        let mut d = f32::MAX;
+        for id in candidates.iter() {
+            if let Some(bc) = self.get(*id) {
+                let nd = color_distance(color, bc);
+                if nd < d {
+                    d = nd;
+                    best = Some(*id);
+                }
+            }
+        }
+        best
+    }
+}
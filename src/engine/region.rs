@@ -0,0 +1,198 @@
+use std::collections::HashMap;
+use std::fs::{
+    File,
+    OpenOptions,
+};
+use std::io::{
+    Error,
+    ErrorKind,
+    Read,
+    Seek,
+    SeekFrom,
+    Write,
+};
+use std::path::{Path, PathBuf};
+
+use crate::engine::chunk::Chunk;
+use crate::engine::world_format;
+
+// Region file layout (all integers little endian):
+//   magic "SGRR", format version u16, reserved u16
+//   offset table: REGION_CHUNKS^3 entries of ( offset u32, length u32 ), x major
+//   chunk payloads, encoded with world_format::encode_chunk
+// An entry with length 0 is an all-air chunk. Rewritten chunks are appended to the end of the file
+// and their table entry updated; the file is compacted once too much of it is dead space.
+//
+// The table is rewritten in place, which stays safe through a crash mid-save:
+//   - payloads are synced before the table, and stored payloads are never written over (compaction writes a new
+//     file and renames it into place), so every entry in the table, old or new, points at a complete payload
+//   - entries are 8 bytes at 8 byte aligned offsets and never straddle a disk sector, so a torn table write leaves
+//     each entry either old or new, and the magic and version at the start never change once written
+//   - a new file gets an empty table before any payload, a file shorter than the header holds no chunks
+//   - world.sgrw with the new save ID is only written after every region, so a save interrupted partway still
+//     loads with the previous save ID and the edit journal replays everything since over the mix of chunks
+
+pub const REGION_CHUNKS: usize = 8;
+pub const REGION_DIR_NAME: &str = "region";
+const REGION_VERSION: u16 = 1;
+const MAGIC: &[u8; 4] = b"SGRR";
+const TABLE_ENTRIES: usize = REGION_CHUNKS * REGION_CHUNKS * REGION_CHUNKS;
+pub const HEADER_LEN: u64 = 8 + 8 * TABLE_ENTRIES as u64;
+const COMPACT_SLACK: u64 = 64 * 1024;
+
+pub fn region_dir(world_dir: &Path) -> PathBuf {
+    let mut pbuf = world_dir.to_path_buf();
+    pbuf.push(REGION_DIR_NAME);
+    pbuf
+}
+
+pub fn region_path(region_dir: &Path, region: (usize, usize, usize)) -> PathBuf {
+    let mut pbuf = region_dir.to_path_buf();
+    pbuf.push( format!("r.{}.{}.{}.bin", region.0, region.1, region.2) );
+    pbuf
+}
+
+fn split_index(chunk_index: (usize, usize, usize)) -> ( (usize, usize, usize), usize ) {
+    let region = ( chunk_index.0 / REGION_CHUNKS, chunk_index.1 / REGION_CHUNKS, chunk_index.2 / REGION_CHUNKS );
+    let local = ( chunk_index.0 % REGION_CHUNKS, chunk_index.1 % REGION_CHUNKS, chunk_index.2 % REGION_CHUNKS );
+    ( region, ( local.0 * REGION_CHUNKS + local.1 ) * REGION_CHUNKS + local.2 )
+}
+
+fn read_table(file: &mut File) -> Result<Vec<(u32, u32)>, Error> {
+    let mut header = vec![ 0_u8; HEADER_LEN as usize ];
+    file.seek( SeekFrom::Start(0) )?;
+    file.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err( Error::new( ErrorKind::InvalidData, "not a region file" ) );
+    }
+    let version = u16::from_le_bytes( [header[4], header[5]] );
+    if version > REGION_VERSION {
+        return Err( Error::new( ErrorKind::InvalidData, format!("region format version {} is newer than this game supports", version) ) );
+    }
+
+    let mut table = Vec::<(u32, u32)>::with_capacity(TABLE_ENTRIES);
+    for entry in header[8..].chunks_exact(8) {
+        table.push( (
+            u32::from_le_bytes( [entry[0], entry[1], entry[2], entry[3]] ),
+            u32::from_le_bytes( [entry[4], entry[5], entry[6], entry[7]] ),
+        ) );
+    }
+    Ok(table)
+}
+
+fn encode_header(table: &[(u32, u32)]) -> Vec<u8> {
+    let mut out = Vec::<u8>::with_capacity( HEADER_LEN as usize );
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice( &REGION_VERSION.to_le_bytes() );
+    out.extend_from_slice( &0_u16.to_le_bytes() );
+    for (offset, length) in table.iter() {
+        out.extend_from_slice( &offset.to_le_bytes() );
+        out.extend_from_slice( &length.to_le_bytes() );
+    }
+    out
+}
+
+// reads a single chunk, None if the region or chunk was never stored (i.e. it is all air)
+pub fn read_chunk(region_dir: &Path, chunk_index: (usize, usize, usize)) -> Result<Option<Chunk>, Error> {
+    let (region, slot) = split_index(chunk_index);
+    let path = region_path(region_dir, region);
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let mut file = File::open(path)?;
+    // created by a save that crashed before its empty table was written
+    if file.metadata()?.len() < HEADER_LEN {
+        return Ok(None);
+    }
+    let table = read_table(&mut file)?;
+    let (offset, length) = table[slot];
+    if length == 0 {
+        return Ok(None);
+    }
+
+    let mut payload = vec![ 0_u8; length as usize ];
+    file.seek( SeekFrom::Start( offset as u64 ) )?;
+    file.read_exact(&mut payload)?;
+    let mut chunk = world_format::decode_chunk(&payload)?;
+    chunk.unsaved = false;
+    Ok( Some(chunk) )
+}
+
+// writes the given chunks into their region files, leaving every other stored chunk untouched
+pub fn write_chunks(region_dir: &Path, chunks: &[( (usize, usize, usize), &Chunk )]) -> Result<(), Error> {
+    std::fs::create_dir_all(region_dir)?;
+
+    let mut by_region = HashMap::<(usize, usize, usize), Vec<(usize, &Chunk)>>::new();
+    for (chunk_index, ch) in chunks.iter() {
+        let (region, slot) = split_index(*chunk_index);
+        by_region.entry(region).or_default().push( (slot, *ch) );
+    }
+
+    for (region, entries) in by_region.iter() {
+        let path = region_path(region_dir, *region);
+        let mut file = OpenOptions::new().read(true).write(true).create(true).open(&path)?;
+        let mut table = if file.metadata()?.len() >= HEADER_LEN {
+            read_table(&mut file)?
+        } else {
+            let table = vec![ (0_u32, 0_u32); TABLE_ENTRIES ];
+            file.set_len(0)?;
+            file.write_all( &encode_header(&table) )?;
+            file.sync_data()?;
+            table
+        };
+
+        let mut end = std::cmp::max( file.seek( SeekFrom::End(0) )?, HEADER_LEN );
+        file.seek( SeekFrom::Start(end) )?;
+        for (slot, ch) in entries.iter() {
+            if world_format::is_empty_chunk(ch) {
+                table[*slot] = (0, 0);
+                continue;
+            }
+            let payload = world_format::encode_chunk(ch);
+            file.write_all(&payload)?;
+            table[*slot] = ( end as u32, payload.len() as u32 );
+            end += payload.len() as u64;
+        }
+
+        // payloads go down before the table that points at them
+        file.sync_data()?;
+        file.seek( SeekFrom::Start(0) )?;
+        file.write_all( &encode_header(&table) )?;
+        file.sync_data()?;
+
+        let live: u64 = table.iter().map( |e| e.1 as u64 ).sum();
+        if end > HEADER_LEN + 2 * live + COMPACT_SLACK {
+            compact_region(&path, file, &table)?;
+        }
+    }
+
+    Ok(())
+}
+
+// rewrites a region file with only its live payloads, replacing the old file once complete.
+// takes the file so it is closed before the rename, which Windows refuses while it's open
+fn compact_region(path: &Path, mut file: File, table: &[(u32, u32)]) -> Result<(), Error> {
+    let mut new_table = vec![ (0_u32, 0_u32); TABLE_ENTRIES ];
+    let mut body = Vec::<u8>::new();
+    for (slot, (offset, length)) in table.iter().enumerate() {
+        if *length == 0 {
+            continue;
+        }
+        let mut payload = vec![ 0_u8; *length as usize ];
+        file.seek( SeekFrom::Start( *offset as u64 ) )?;
+        file.read_exact(&mut payload)?;
+        new_table[slot] = ( ( HEADER_LEN + body.len() as u64 ) as u32, *length );
+        body.extend_from_slice(&payload);
+    }
+    drop(file);
+
+    let tmp_path = path.with_extension("bin.tmp");
+    {
+        let mut tmp = File::create(&tmp_path)?;
+        tmp.write_all( &encode_header(&new_table) )?;
+        tmp.write_all(&body)?;
+        tmp.sync_all()?;
+    }
+    std::fs::rename(tmp_path, path)
+}
@@ -0,0 +1,767 @@
+use std::io::Error;
+use std::path::{
+    Path,
+    PathBuf
+};
+use std::fs::File;
+use std::io::{
+    Write,
+    Read
+};
+
+use fs_extra::dir::create_all;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+
+use crate::engine::journal;
+use crate::engine::region;
+use crate::engine::world::WorldSavestate;
+use crate::engine::world_format;
+use crate::engine::world_gen::{
+    WorldGenerator,
+    WorldGenSettings
+};
+
+
+pub const BACKUP_DIR_NAME: &str = "backups";
+// files making up a world's saved state, besides the region directory.
+// edit journals are included so a restored backup doesn't get newer edits replayed on top of it.
+pub const PREVIEW_FILE_NAME: &str = "preview.png";
+const SAVE_FILE_NAMES: [&str; 5] = [ world_format::SAVE_FILE_NAME, world_format::LEGACY_SAVE_FILE_NAME, PREVIEW_FILE_NAME, journal::JOURNAL_FILE_NAME, journal::PREV_JOURNAL_FILE_NAME ];
+// how many previous saves are kept around
+pub const BACKUP_COUNT: usize = 5;
+
+// what a backup recorded about the region files it holds, so the next backup can tell which are unchanged
+const BACKUP_STAMPS_FILE_NAME: &str = "backup.toml";
+
+#[derive(Serialize, Deserialize, Default)]
+struct BackupStamps {
+    #[serde(default)]
+    region: std::collections::HashMap<String, RegionStamp>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq)]
+struct RegionStamp {
+    len: u64,
+    // nanoseconds since the unix epoch
+    modified: u64,
+}
+
+// backups made before stamps were recorded just have everything copied again
+fn read_stamps(backup: &Path) -> BackupStamps {
+    let mut sbuf = backup.to_path_buf();
+    sbuf.push(BACKUP_STAMPS_FILE_NAME);
+    std::fs::read_to_string(sbuf).ok().and_then( |text| toml::from_str(&text).ok() ).unwrap_or_default()
+}
+
+// whether two region files start with the same header and chunk table
+fn same_table(a: &Path, b: &Path) -> bool {
+    let read_table = |path: &Path| -> Option<Vec<u8>> {
+        let mut buf = vec![ 0u8; region::HEADER_LEN as usize ];
+        File::open(path).ok()?.read_exact(&mut buf).ok()?;
+        Some(buf)
+    };
+    match ( read_table(a), read_table(b) ) {
+        ( Some(ta), Some(tb) ) => ta == tb,
+        _ => false,
+    }
+}
+
+// writes to a temporary file next to the destination, syncs it and renames it into place,
+// so a crash mid-write leaves the previous file intact
+pub fn write_file_atomic(path: &Path, bytes: &[u8]) -> Result<(), Error> {
+    let mut tmp_name = path.file_name().ok_or( Error::new( std::io::ErrorKind::InvalidInput, "path has no file name" ) )?.to_os_string();
+    tmp_name.push(".tmp");
+    let tmp_path = path.with_file_name(tmp_name);
+    {
+        let mut fi = File::create(&tmp_path)?;
+        fi.write_all(bytes)?;
+        fi.sync_all()?;
+    }
+    std::fs::rename(&tmp_path, path)?;
+
+    // make the rename itself durable where the platform allows syncing a directory
+    if let Some(parent) = path.parent() {
+        if let Ok(dir) = File::open(parent) {
+            let _ = dir.sync_all();
+        }
+    }
+    Ok(())
+}
+
+// templates shipped with the game
+pub const BUNDLED_TEMPLATE_PATH: &str = "res/templates";
+
+#[derive(Clone, Debug)]
+pub struct TemplateInfo {
+    pub name: String,
+    pub path: PathBuf,
+    pub bundled: bool,
+}
+
+impl TemplateInfo {
+    pub fn label(&self) -> String {
+        if self.bundled {
+            self.name.clone()
+        } else {
+            format!("{} (yours)", self.name)
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    // milliseconds since the unix epoch
+    pub timestamp: u64,
+}
+
+impl BackupInfo {
+    pub fn label(&self) -> String {
+        format!( "Backup from {}", format_age( self.timestamp / 1000 ) )
+    }
+}
+
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+pub fn unix_now() -> u64 {
+    std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_secs()
+}
+
+// "3 h ago" style description of a unix timestamp
+pub fn format_age(unix_secs: u64) -> String {
+    if unix_secs == 0 {
+        return "an unknown time".to_string();
+    }
+    let minutes = unix_now().saturating_sub(unix_secs) / 60;
+    if minutes < 1 {
+        "just now".to_string()
+    } else if minutes < 60 {
+        format!("{} min ago", minutes)
+    } else if minutes < 48 * 60 {
+        format!("{} h ago", minutes / 60)
+    } else {
+        format!("{} days ago", minutes / (24 * 60))
+    }
+}
+
+pub fn format_duration(secs: u64) -> String {
+    if secs < 3600 {
+        format!("{} min", secs / 60)
+    } else {
+        format!("{} h {} min", secs / 3600, (secs % 3600) / 60)
+    }
+}
+
+// contents of a world's info.toml
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct WorldInfo {
+    pub name: String,
+    pub size: usize,
+    #[serde(default)]
+    pub seed: u32,
+    #[serde(default)]
+    pub generator: WorldGenerator,
+    // unix timestamps in seconds, 0 when unknown
+    #[serde(default)]
+    pub created: u64,
+    #[serde(default)]
+    pub last_played: u64,
+    // total seconds spent in the world
+    #[serde(default)]
+    pub play_time: u64,
+    // version of the game that last saved the world
+    #[serde(default)]
+    pub game_version: String,
+    // templates are offered as starting points when creating a world
+    #[serde(default)]
+    pub template: bool,
+}
+
+impl WorldInfo {
+    pub fn new(name: String, settings: &WorldGenSettings) -> WorldInfo {
+        Self {
+            name,
+            size: settings.size,
+            seed: settings.seed,
+            generator: settings.generator,
+            created: unix_now(),
+            last_played: unix_now(),
+            play_time: 0,
+            game_version: GAME_VERSION.to_string(),
+            template: false,
+        }
+    }
+
+    pub fn read(world_dir: &Path) -> Result<WorldInfo, Error> {
+        let mut ibuf = world_dir.to_path_buf();
+        ibuf.push("info.toml");
+        let mut contents = String::new();
+        File::open(ibuf)?.read_to_string(&mut contents)?;
+        toml::from_str::<WorldInfo>(&contents).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, e ) )
+    }
+
+    pub fn write(&self, world_dir: &Path) -> Result<(), Error> {
+        let mut ibuf = world_dir.to_path_buf();
+        ibuf.push("info.toml");
+        let datastring = toml::to_string(self).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, e ) )?;
+        write_file_atomic( &ibuf, datastring.as_bytes() )
+    }
+}
+
+pub struct WorldSaver {
+}
+
+impl WorldSaver {
+
+    // the folder every world of the game lives in
+    pub fn worlds_dir() -> PathBuf {
+        let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
+        let mut pbuf = pdirs.data_dir().to_path_buf();
+        pbuf.push( "worlds/" );
+        pbuf
+    }
+
+    pub fn world_dir(name: &str) -> PathBuf {
+        let mut pbuf = Self::worlds_dir();
+        pbuf.push( name );
+        pbuf
+    }
+
+    // command line tools take either a folder or the name of a world in the game's worlds folder
+    pub fn find_world_dir(arg: &str) -> PathBuf {
+        let path = PathBuf::from(arg);
+        if path.is_dir() {
+            path
+        } else {
+            Self::world_dir(arg)
+        }
+    }
+
+    pub fn create_world_dir(info: &WorldInfo) -> PathBuf {
+        let pbuf = Self::world_dir(&info.name);
+        create_all( &pbuf, false );
+
+        info.write(&pbuf).expect("failed to write world info");
+
+        pbuf
+    }
+
+    pub fn backup_dir(world_dir: &Path) -> PathBuf {
+        let mut pbuf = world_dir.to_path_buf();
+        pbuf.push(BACKUP_DIR_NAME);
+        pbuf
+    }
+
+    // backups of a world, newest first
+    pub fn list_backups(world_dir: &Path) -> Vec<BackupInfo> {
+        let mut backups = Vec::<BackupInfo>::new();
+        if let Ok(entries) = std::fs::read_dir( Self::backup_dir(world_dir) ) {
+            for entry in entries.flatten() {
+                let name = entry.file_name().to_string_lossy().to_string();
+                if let Ok(timestamp) = name.parse::<u64>() {
+                    if entry.path().is_dir() {
+                        backups.push( BackupInfo{ path: entry.path(), timestamp } );
+                    }
+                }
+            }
+        }
+        backups.sort_by( |a, b| b.timestamp.cmp(&a.timestamp) );
+        backups
+    }
+
+    // the save of a freshly generated world, written before play starts so the edit journal always
+    // has a save to replay onto, even if the game stops before the first real save
+    pub fn write_first_save(world: &mut WorldSavestate, world_dir: &Path) -> Result<(), Error> {
+        if world.chunk_manager.region_dir.is_none() {
+            world.chunk_manager.region_dir = Some( region::region_dir(world_dir) );
+        }
+        world.chunk_manager.save_unsaved()?;
+
+        let mut savebuf = world_dir.to_path_buf();
+        savebuf.push(world_format::SAVE_FILE_NAME);
+        write_file_atomic( &savebuf, &world_format::encode_world(world)? )
+    }
+
+    // copies the save as it currently is on disk into a new backup, then drops the oldest ones
+    pub fn make_backup(world_dir: &Path) -> Result<(), Error> {
+        let mut save_files = Vec::<PathBuf>::new();
+        for name in SAVE_FILE_NAMES {
+            let mut fbuf = world_dir.to_path_buf();
+            fbuf.push(name);
+            if fbuf.exists() {
+                save_files.push(fbuf);
+            }
+        }
+        // nothing saved yet
+        if save_files.is_empty() {
+            return Ok(());
+        }
+
+        let previous = Self::list_backups(world_dir).into_iter().next();
+        let timestamp = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_millis() as u64;
+        let mut bbuf = Self::backup_dir(world_dir);
+        bbuf.push( format!("{:015}", timestamp) );
+        std::fs::create_dir_all(&bbuf)?;
+
+        for fbuf in save_files.iter() {
+            let mut dest = bbuf.clone();
+            dest.push( fbuf.file_name().unwrap() );
+            std::fs::copy(fbuf, dest)?;
+        }
+        let rbuf = region::region_dir(world_dir);
+        if rbuf.exists() {
+            Self::backup_regions( &rbuf, &bbuf, previous.as_ref() )?;
+        }
+
+        for old in Self::list_backups(world_dir).iter().skip(BACKUP_COUNT) {
+            std::fs::remove_dir_all(&old.path)?;
+        }
+        Ok(())
+    }
+
+    // region files that haven't changed since the previous backup are hardlinked to its copy instead of copied again,
+    // so autosaves don't copy the whole world every time. backups are never written to after they are made, and
+    // restoring copies files out of them, so the shared copies can't change under any backup.
+    // files are taken as unchanged when their length, modification time and region table all match what the previous
+    // backup recorded; the table is compared too because some filesystems only keep modification times to the second.
+    fn backup_regions(region_dir: &Path, backup: &Path, previous: Option<&BackupInfo>) -> Result<(), Error> {
+        let mut dest_dir = backup.to_path_buf();
+        dest_dir.push(region::REGION_DIR_NAME);
+        std::fs::create_dir_all(&dest_dir)?;
+
+        let prev_stamps = previous.map( |b| read_stamps(&b.path) ).unwrap_or_default();
+        let mut stamps = BackupStamps::default();
+        for entry in std::fs::read_dir(region_dir)?.flatten() {
+            let meta = entry.metadata()?;
+            if !meta.is_file() {
+                continue;
+            }
+            let name = entry.file_name().to_string_lossy().to_string();
+            let modified = meta.modified()?.duration_since(std::time::UNIX_EPOCH).unwrap_or_default().as_nanos() as u64;
+            let stamp = RegionStamp{ len: meta.len(), modified };
+
+            let mut dest = dest_dir.clone();
+            dest.push(&name);
+            let mut linked = false;
+            if let ( Some(prev), Some(prev_stamp) ) = ( previous, prev_stamps.region.get(&name) ) {
+                let mut prev_copy = prev.path.clone();
+                prev_copy.push(region::REGION_DIR_NAME);
+                prev_copy.push(&name);
+                if *prev_stamp == stamp && same_table( &entry.path(), &prev_copy ) {
+                    linked = std::fs::hard_link( &prev_copy, &dest ).is_ok();
+                }
+            }
+            if !linked {
+                std::fs::copy( entry.path(), &dest )?;
+            }
+            stamps.region.insert(name, stamp);
+        }
+
+        let mut sbuf = backup.to_path_buf();
+        sbuf.push(BACKUP_STAMPS_FILE_NAME);
+        let text = toml::to_string(&stamps).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+        std::fs::write(sbuf, text)
+    }
+
+    // replaces the world's save with a backup. the current save is backed up first, so a restore can be undone.
+    pub fn restore_backup(world_dir: &Path, backup: &BackupInfo) -> Result<(), Error> {
+        // keep the chosen backup alive through the rotation make_backup does
+        let mut staged = Self::backup_dir(world_dir);
+        staged.push("restoring");
+        if staged.exists() {
+            std::fs::remove_dir_all(&staged)?;
+        }
+        std::fs::create_dir_all(&staged)?;
+        fs_extra::dir::copy( &backup.path, &staged, &fs_extra::dir::CopyOptions::new().content_only(true) ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+
+        Self::make_backup(world_dir)?;
+
+        let rbuf = region::region_dir(world_dir);
+        if rbuf.exists() {
+            std::fs::remove_dir_all(&rbuf)?;
+        }
+        for name in SAVE_FILE_NAMES {
+            let mut fbuf = world_dir.to_path_buf();
+            fbuf.push(name);
+            if fbuf.exists() {
+                std::fs::remove_file(&fbuf)?;
+            }
+        }
+
+        fs_extra::dir::copy( &staged, world_dir, &fs_extra::dir::CopyOptions::new().content_only(true) ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+        let mut sbuf = world_dir.to_path_buf();
+        sbuf.push(BACKUP_STAMPS_FILE_NAME);
+        if sbuf.exists() {
+            std::fs::remove_file(sbuf)?;
+        }
+        std::fs::remove_dir_all(&staged)
+    }
+
+    // world names double as directory names
+    pub fn validate_name(name: &str) -> Result<(), Error> {
+        let trimmed = name.trim();
+        if trimmed.is_empty() {
+            return Err( Error::new( std::io::ErrorKind::InvalidInput, "the world needs a name" ) );
+        }
+        if trimmed.starts_with('.') || trimmed.contains( |c: char| c == '/' || c == '\\' || c == ':' || c.is_control() ) {
+            return Err( Error::new( std::io::ErrorKind::InvalidInput, "world names can't contain / \\ : or start with a dot" ) );
+        }
+        if Self::world_dir(trimmed).exists() {
+            return Err( Error::new( std::io::ErrorKind::AlreadyExists, format!("a world called {} already exists", trimmed) ) );
+        }
+        Ok(())
+    }
+
+    // renames the world in info.toml and moves its directory to match, returns the new directory
+    pub fn rename_world(world_dir: &Path, new_name: &str) -> Result<PathBuf, Error> {
+        Self::validate_name(new_name)?;
+        let new_name = new_name.trim();
+
+        let mut info = WorldInfo::read(world_dir)?;
+        let new_dir = Self::world_dir(new_name);
+        std::fs::rename(world_dir, &new_dir)?;
+        info.name = new_name.to_string();
+        info.write(&new_dir)?;
+        Ok(new_dir)
+    }
+
+    // copies a world under a free "(copy)" name, leaving its backups behind. returns the new name.
+    pub fn duplicate_world(world_dir: &Path) -> Result<String, Error> {
+        let mut info = WorldInfo::read(world_dir)?;
+        let mut new_name = format!("{} (copy)", info.name);
+        let mut n = 2;
+        while Self::world_dir(&new_name).exists() {
+            new_name = format!("{} (copy {})", info.name, n);
+            n += 1;
+        }
+
+        let new_dir = Self::world_dir(&new_name);
+        Self::copy_world_files(world_dir, &new_dir)?;
+
+        info.name = new_name.clone();
+        info.write(&new_dir)?;
+        Ok(new_name)
+    }
+
+    // copies everything but the backups from one world directory into a new one
+    fn copy_world_files(world_dir: &Path, new_dir: &Path) -> Result<(), Error> {
+        std::fs::create_dir_all(new_dir)?;
+        for entry in std::fs::read_dir(world_dir)?.flatten() {
+            if entry.file_name() == BACKUP_DIR_NAME {
+                continue;
+            }
+            if entry.path().is_dir() {
+                fs_extra::dir::copy( entry.path(), new_dir, &fs_extra::dir::CopyOptions::new() ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+            } else {
+                let mut dest = new_dir.to_path_buf();
+                dest.push( entry.file_name() );
+                std::fs::copy( entry.path(), dest )?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn set_template(world_dir: &Path, template: bool) -> Result<(), Error> {
+        let mut info = WorldInfo::read(world_dir)?;
+        info.template = template;
+        info.write(world_dir)
+    }
+
+    // bundled templates first, then the player's own worlds marked as templates
+    pub fn list_templates() -> Vec<TemplateInfo> {
+        let mut templates = Vec::<TemplateInfo>::new();
+        let sources = [ ( PathBuf::from(BUNDLED_TEMPLATE_PATH), true ), ( Self::worlds_dir(), false ) ];
+
+        for (dir, bundled) in sources {
+            let mut found = Vec::<TemplateInfo>::new();
+            if let Ok(entries) = std::fs::read_dir(&dir) {
+                for entry in entries.flatten() {
+                    let path = entry.path();
+                    if !path.is_dir() || entry.file_name().to_string_lossy().starts_with('.') {
+                        continue;
+                    }
+                    if let Ok(info) = WorldInfo::read(&path) {
+                        if info.template {
+                            found.push( TemplateInfo{ name: info.name, path, bundled } );
+                        }
+                    }
+                }
+            }
+            found.sort_by( |a, b| a.name.to_lowercase().cmp(&b.name.to_lowercase()) );
+            templates.append(&mut found);
+        }
+        templates
+    }
+
+    // makes a new world as a copy of a template, returns its directory
+    pub fn create_from_template(template_dir: &Path, name: &str) -> Result<PathBuf, Error> {
+        Self::validate_name(name)?;
+        let name = name.trim();
+        let mut info = WorldInfo::read(template_dir)?;
+
+        let new_dir = Self::world_dir(name);
+        if let Err(e) = Self::copy_world_files(template_dir, &new_dir).and_then( |_| Self::migrate_world(&new_dir) ) {
+            let _ = std::fs::remove_dir_all(&new_dir);
+            return Err(e);
+        }
+
+        info.name = name.to_string();
+        info.template = false;
+        info.created = unix_now();
+        info.last_played = unix_now();
+        info.play_time = 0;
+        info.write(&new_dir)?;
+        Ok(new_dir)
+    }
+
+    pub fn delete_world(world_dir: &Path) -> Result<(), Error> {
+        std::fs::remove_dir_all(world_dir)
+    }
+
+    pub fn export_dir() -> PathBuf {
+        let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
+        let mut pbuf = pdirs.data_dir().to_path_buf();
+        pbuf.push( "exports/" );
+        pbuf
+    }
+
+    // packs the world into a .sgrworld zip archive in the exports directory, returns the archive path.
+    // backups and temporary files are left out.
+    pub fn export_world(world_dir: &Path) -> Result<PathBuf, Error> {
+        let info = WorldInfo::read(world_dir)?;
+        let export_dir = Self::export_dir();
+        std::fs::create_dir_all(&export_dir)?;
+        // earlier exports of a world with the same name are kept
+        let mut abuf = export_dir.clone();
+        abuf.push( format!("{}.sgrworld", info.name) );
+        let mut n = 2;
+        while abuf.exists() {
+            abuf = export_dir.clone();
+            abuf.push( format!("{} ({}).sgrworld", info.name, n) );
+            n += 1;
+        }
+
+        let mut files = Vec::<PathBuf>::new();
+        let mut stack = vec![ world_dir.to_path_buf() ];
+        while let Some(dir) = stack.pop() {
+            for entry in std::fs::read_dir(&dir)?.flatten() {
+                let path = entry.path();
+                if path.is_dir() {
+                    if entry.file_name() != BACKUP_DIR_NAME {
+                        stack.push(path);
+                    }
+                } else if path.extension().map_or( true, |ext| ext != "tmp" ) {
+                    files.push(path);
+                }
+            }
+        }
+        files.sort();
+
+        let zip_err = |e: zip::result::ZipError| Error::new( std::io::ErrorKind::Other, e.to_string() );
+        let mut tmp_path = abuf.clone();
+        tmp_path.set_extension("sgrworld.tmp");
+        {
+            let mut zip = zip::ZipWriter::new( File::create(&tmp_path)? );
+            let options = zip::write::SimpleFileOptions::default().compression_method( zip::CompressionMethod::Deflated );
+            for path in files.iter() {
+                let rel = path.strip_prefix(world_dir).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+                let rel_name = rel.components().map( |c| c.as_os_str().to_string_lossy().to_string() ).collect::<Vec<String>>().join("/");
+                zip.start_file( rel_name, options ).map_err(zip_err)?;
+                zip.write_all( &std::fs::read(path)? )?;
+            }
+            zip.finish().map_err(zip_err)?.sync_all()?;
+        }
+        std::fs::rename(tmp_path, &abuf)?;
+        Ok(abuf)
+    }
+
+    // unpacks a .sgrworld archive into a new world directory, returns the name it was imported under.
+    // archives zipped by hand with the world folder at the top level are accepted too.
+    pub fn import_world(archive_path: &Path) -> Result<String, Error> {
+        let zip_err = |e: zip::result::ZipError| Error::new( std::io::ErrorKind::InvalidData, e.to_string() );
+        let mut archive = zip::ZipArchive::new( File::open(archive_path)? ).map_err(zip_err)?;
+
+        // find where the world lives inside the archive
+        let mut prefix: Option<PathBuf> = None;
+        for i in 0..archive.len() {
+            let entry = archive.by_index(i).map_err(zip_err)?;
+            if let Some(path) = entry.enclosed_name() {
+                if path.file_name().map_or( false, |n| n == "info.toml" ) && path.components().count() <= 2 {
+                    let parent = path.parent().map( |p| p.to_path_buf() ).unwrap_or_default();
+                    if prefix.as_ref().map_or( true, |p| parent.components().count() < p.components().count() ) {
+                        prefix = Some(parent);
+                    }
+                }
+            }
+        }
+        let prefix = prefix.ok_or( Error::new( std::io::ErrorKind::InvalidData, "the archive doesn't contain a world" ) )?;
+
+        // unpack into a hidden staging directory first, world names can't start with a dot
+        let mut staging = Self::world_dir(".importing");
+        if staging.exists() {
+            std::fs::remove_dir_all(&staging)?;
+        }
+        std::fs::create_dir_all(&staging)?;
+        let unpacked = Self::unpack_world(&mut archive, &prefix, &staging).and_then( |_| Self::migrate_world(&staging) );
+        if let Err(e) = unpacked {
+            let _ = std::fs::remove_dir_all(&staging);
+            return Err(e);
+        }
+
+        let mut info = WorldInfo::read(&staging)?;
+        let base_name = info.name.trim().to_string();
+        let mut new_name = base_name.clone();
+        let mut n = 2;
+        while Self::validate_name(&new_name).is_err() {
+            new_name = format!("{} ({})", base_name, n);
+            n += 1;
+            // names that are invalid no matter the suffix
+            if n > 1000 {
+                new_name = format!("Imported world ({})", unix_now());
+                break;
+            }
+        }
+
+        let world_dir = Self::world_dir(&new_name);
+        std::fs::rename(&staging, &world_dir)?;
+        info.name = new_name.clone();
+        info.write(&world_dir)?;
+        Ok(new_name)
+    }
+
+    fn unpack_world(archive: &mut zip::ZipArchive<File>, prefix: &Path, dest: &Path) -> Result<(), Error> {
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, e.to_string() ) )?;
+            // entries that would land outside the world directory are skipped
+            let path = match entry.enclosed_name() {
+                Some(path) => path,
+                None => continue,
+            };
+            let rel = match path.strip_prefix(prefix) {
+                Ok(rel) => rel.to_path_buf(),
+                Err(_) => continue,
+            };
+            if entry.is_dir() || rel.starts_with(BACKUP_DIR_NAME) || rel.as_os_str().is_empty() {
+                continue;
+            }
+
+            let mut out_path = dest.to_path_buf();
+            out_path.push(&rel);
+            if let Some(parent) = out_path.parent() {
+                std::fs::create_dir_all(parent)?;
+            }
+            let mut out = File::create(&out_path)?;
+            std::io::copy( &mut entry, &mut out )?;
+        }
+        Ok(())
+    }
+
+    // brings a world from an older save format up to the current one
+    pub fn migrate_world(world_dir: &Path) -> Result<(), Error> {
+        match world_format::read_version(world_dir)? {
+            Some(version) if version == world_format::FORMAT_VERSION => return Ok(()),
+            Some(version) if version > world_format::FORMAT_VERSION => {
+                return Err( Error::new( std::io::ErrorKind::InvalidData, format!("the world was saved in format version {}, which is newer than this game supports", version) ) );
+            }
+            _ => {}
+        }
+
+        let mut world = world_format::read_world_file(world_dir)?;
+        // older formats come back fully loaded and unsaved
+        world.chunk_manager.save_unsaved()?;
+        let mut savebuf = world_dir.to_path_buf();
+        savebuf.push(world_format::SAVE_FILE_NAME);
+        write_file_atomic( &savebuf, &world_format::encode_world(&world)? )?;
+
+        let mut pklbuf = world_dir.to_path_buf();
+        pklbuf.push(world_format::LEGACY_SAVE_FILE_NAME);
+        if pklbuf.exists() {
+            std::fs::remove_file(pklbuf)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_dir;
+
+    fn write_region(world_dir: &Path, name: &str, fill: u8) {
+        let mut rbuf = region::region_dir(world_dir);
+        std::fs::create_dir_all(&rbuf).unwrap();
+        rbuf.push(name);
+        std::fs::write( rbuf, vec![ fill; region::HEADER_LEN as usize + 16 ] ).unwrap();
+    }
+
+    fn backed_up_region(backup: &BackupInfo, name: &str) -> Vec<u8> {
+        let mut rbuf = backup.path.clone();
+        rbuf.push(region::REGION_DIR_NAME);
+        rbuf.push(name);
+        std::fs::read(rbuf).unwrap()
+    }
+
+    #[test]
+    fn journal_replays_onto_first_save() {
+        let world_dir = test_dir("first_save");
+        let mut world = WorldSavestate::new(0);
+        world.block_table = vec![ "air".to_string(), "st_100".to_string() ];
+        WorldSaver::write_first_save( &mut world, &world_dir ).unwrap();
+
+        // an edit made right after the world was created, then the game stops without saving
+        let pos = (3, 100, 5);
+        let old = *world.chunk_manager.get_block(pos);
+        let mut new = old;
+        new.blockdef = 1;
+        let mut journal = journal::EditJournal::open( &world_dir, world.save_id ).unwrap();
+        journal.record( pos, &old, &new ).unwrap();
+        journal.flush().unwrap();
+        drop(journal);
+
+        let mut read = world_format::read_world_file(&world_dir).unwrap();
+        assert_eq!( read.save_id, 0 );
+        assert_eq!( journal::replay( &world_dir, read.save_id, &mut read.chunk_manager ).unwrap(), 1 );
+        read.chunk_manager.load_all().unwrap();
+        assert_eq!( read.chunk_manager.get_block(pos).blockdef, 1 );
+        std::fs::remove_dir_all(&world_dir).unwrap();
+    }
+
+    #[test]
+    fn backups_keep_each_save() {
+        let world_dir = test_dir("backups");
+        let sbuf = world_dir.join(world_format::SAVE_FILE_NAME);
+        std::fs::write( &sbuf, b"first" ).unwrap();
+        write_region( &world_dir, "r.0.0.0.bin", 1 );
+        write_region( &world_dir, "r.1.0.0.bin", 2 );
+        WorldSaver::make_backup(&world_dir).unwrap();
+
+        // the timestamps naming backups are in milliseconds
+        std::thread::sleep( std::time::Duration::from_millis(5) );
+        std::fs::write( &sbuf, b"second" ).unwrap();
+        write_region( &world_dir, "r.1.0.0.bin", 3 );
+        WorldSaver::make_backup(&world_dir).unwrap();
+
+        let backups = WorldSaver::list_backups(&world_dir);
+        assert_eq!( backups.len(), 2 );
+        assert_eq!( backed_up_region( &backups[0], "r.0.0.0.bin" ), backed_up_region( &backups[1], "r.0.0.0.bin" ) );
+        assert_eq!( backed_up_region( &backups[0], "r.1.0.0.bin" )[0], 3 );
+        assert_eq!( backed_up_region( &backups[1], "r.1.0.0.bin" )[0], 2 );
+
+        // restoring writes fresh files, so editing the restored world leaves the backups alone
+        WorldSaver::restore_backup( &world_dir, &backups[1] ).unwrap();
+        assert_eq!( std::fs::read(&sbuf).unwrap(), b"first" );
+        write_region( &world_dir, "r.0.0.0.bin", 4 );
+        let backups = WorldSaver::list_backups(&world_dir);
+        assert!( backups.iter().all( |b| backed_up_region( b, "r.0.0.0.bin" )[0] == 1 ) );
+
+        let mut stamps = world_dir.clone();
+        stamps.push(BACKUP_STAMPS_FILE_NAME);
+        assert!( !stamps.exists() );
+        std::fs::remove_dir_all(&world_dir).unwrap();
+    }
+}
@@ -0,0 +1,461 @@
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::Path;
+
+use cgmath::{InnerSpace, Quaternion, Vector3};
+use serde::Deserialize;
+
+use crate::engine::invalid;
+use crate::engine::block::{BlockRegistry, BlockShape, BlockShapeRegistry};
+use crate::engine::nbt::{self, Tag};
+use crate::engine::rotation_group::{self, RotType};
+
+// builds saved from Minecraft with WorldEdit (Sponge .schem) or Litematica (.litematic), and the
+// table that says which of our blocks stands in for each Minecraft block
+
+pub const MAPPING_PATH: &str = "res/data/minecraft_blocks.toml";
+
+// Minecraft's empty blocks, never placed
+const AIR_BLOCKS: [&str; 4] = [ "minecraft:air", "minecraft:cave_air", "minecraft:void_air", "minecraft:structure_void" ];
+
+// a block name with its properties, like minecraft:oak_stairs[facing=east,half=bottom]
+#[derive(Clone, Debug, PartialEq)]
+pub struct BlockState {
+    pub name: String,
+    pub properties: HashMap<String, String>,
+}
+
+impl BlockState {
+    pub fn parse(text: &str) -> BlockState {
+        let (name, props) = match text.split_once('[') {
+            Some( (name, rest) ) => ( name, rest.trim_end_matches(']') ),
+            None => ( text, "" ),
+        };
+        let properties = props.split(',').filter_map( |p| p.split_once('=') ).map( |(k, v)| ( k.trim().to_string(), v.trim().to_string() ) ).collect();
+        BlockState{ name: full_name( name.trim() ), properties }
+    }
+
+    pub fn is_air(&self) -> bool {
+        AIR_BLOCKS.contains( &self.name.as_str() )
+    }
+}
+
+// names without a namespace are Minecraft's own
+fn full_name(name: &str) -> String {
+    if name.contains(':') { name.to_string() } else { format!( "minecraft:{}", name ) }
+}
+
+// every block of the schematic that isn't air, by palette index. positions count from the
+// schematic's lowest corner
+pub struct Schematic {
+    pub size: (usize, usize, usize),
+    pub palette: Vec<BlockState>,
+    pub blocks: Vec<( (usize, usize, usize), usize )>,
+}
+
+fn int_of(tag: &Tag, key: &str) -> Result<i64, Error> {
+    tag.get(key).and_then( |t| t.as_int() ).ok_or( invalid( format!( "the schematic has no {}", key ) ) )
+}
+
+fn dimension(tag: &Tag, key: &str) -> Result<usize, Error> {
+    // sizes are stored as shorts, which Sponge treats as unsigned
+    Ok( ( int_of( tag, key )? as u16 ) as usize )
+}
+
+// Sponge block data is one varint palette index per block
+fn read_varints(data: &[i8], count: usize) -> Result<Vec<usize>, Error> {
+    // every varint takes at least a byte, checked before the size from the file is trusted with an allocation
+    if count > data.len() {
+        return Err( invalid( format!( "the schematic has {} bytes of block data for {} blocks", data.len(), count ) ) );
+    }
+    let mut out = Vec::with_capacity(count);
+    let mut value = 0usize;
+    let mut shift = 0;
+    for b in data.iter().map( |b| *b as u8 ) {
+        if shift > 28 {
+            return Err( invalid( "the schematic's block data has a varint that's too long".to_string() ) );
+        }
+        value |= ( ( b & 0x7f ) as usize ) << shift;
+        if b & 0x80 == 0 {
+            out.push(value);
+            value = 0;
+            shift = 0;
+        } else {
+            shift += 7;
+        }
+    }
+    if out.len() < count {
+        return Err( invalid( format!( "the schematic has {} blocks of data for {} blocks", out.len(), count ) ) );
+    }
+    Ok(out)
+}
+
+// versions 1 and 2 keep the palette at the top, version 3 moves it into Blocks
+fn read_sponge(schem: &Tag) -> Result<Schematic, Error> {
+    let size = ( dimension( schem, "Width" )?, dimension( schem, "Height" )?, dimension( schem, "Length" )? );
+    let (palette_tag, data) = match schem.get("Blocks") {
+        Some(blocks) => ( blocks.get("Palette"), blocks.get("Data") ),
+        None => ( schem.get("Palette"), schem.get("BlockData") ),
+    };
+    let palette_tag = palette_tag.and_then( |p| p.as_compound() ).ok_or( invalid( "the schematic has no block palette".to_string() ) )?;
+    let data = match data {
+        Some( Tag::ByteArray(data) ) => data,
+        _ => return Err( invalid( "the schematic has no block data".to_string() ) ),
+    };
+
+    let mut palette = vec![ BlockState::parse("minecraft:air"); palette_tag.len() ];
+    for (text, idx) in palette_tag.iter() {
+        let idx = idx.as_int().filter( |i| *i >= 0 && ( *i as usize ) < palette.len() ).ok_or( invalid( format!( "the palette entry for {} is out of range", text ) ) )?;
+        palette[ idx as usize ] = BlockState::parse(text);
+    }
+
+    let count = block_count(size)?;
+    let indices = read_varints( data, count )?;
+    let mut blocks = Vec::new();
+    for (i, idx) in indices.iter().take(count).enumerate() {
+        let state = palette.get(*idx).ok_or( invalid( format!( "block data uses palette entry {}, which doesn't exist", idx ) ) )?;
+        if state.is_air() {
+            continue;
+        }
+        let (x, z, y) = ( i % size.0, ( i / size.0 ) % size.2, i / ( size.0 * size.2 ) );
+        blocks.push( ( (x, y, z), *idx ) );
+    }
+    Ok( Schematic{ size, palette, blocks } )
+}
+
+// sizes come straight from the file, so their product can overflow
+fn block_count(size: (usize, usize, usize)) -> Result<usize, Error> {
+    size.0.checked_mul(size.1).and_then( |n| n.checked_mul(size.2) ).ok_or( invalid( format!( "the schematic's size {}x{}x{} is too big", size.0, size.1, size.2 ) ) )
+}
+
+fn vec3_of(tag: &Tag, key: &str) -> Result<(i64, i64, i64), Error> {
+    let v = tag.get(key).ok_or( invalid( format!( "a region has no {}", key ) ) )?;
+    Ok( ( int_of( v, "x" )?, int_of( v, "y" )?, int_of( v, "z" )? ) )
+}
+
+// Litematica packs palette indices into longs with as few bits as the palette needs (at least 2),
+// an index can carry on into the next long
+fn packed_index(states: &[i64], bits: usize, i: usize) -> Option<usize> {
+    let start = i * bits;
+    let (word, offset) = ( start / 64, start % 64 );
+    let mut value = ( *states.get(word)? as u64 ) >> offset;
+    if offset + bits > 64 {
+        value |= ( *states.get( word + 1 )? as u64 ) << ( 64 - offset );
+    }
+    Some( ( value & ( ( 1u64 << bits ) - 1 ) ) as usize )
+}
+
+// every region goes in, placed where it sits relative to the others
+fn read_litematic(regions: &HashMap<String, Tag>) -> Result<Schematic, Error> {
+    struct Region<'a> {
+        min: (i64, i64, i64),
+        size: (usize, usize, usize),
+        tag: &'a Tag,
+    }
+    let mut parsed = Vec::new();
+    for region in regions.values() {
+        let pos = vec3_of( region, "Position" )?;
+        let size = vec3_of( region, "Size" )?;
+        // a negative size reaches back from the position
+        let from = |p: i64, s: i64| if s < 0 { p + s + 1 } else { p };
+        parsed.push( Region {
+            min: ( from( pos.0, size.0 ), from( pos.1, size.1 ), from( pos.2, size.2 ) ),
+            size: ( size.0.unsigned_abs() as usize, size.1.unsigned_abs() as usize, size.2.unsigned_abs() as usize ),
+            tag: region,
+        } );
+    }
+    if parsed.is_empty() {
+        return Err( invalid( "the litematic has no regions".to_string() ) );
+    }
+
+    let min = parsed.iter().fold( ( i64::MAX, i64::MAX, i64::MAX ), |m, r| ( m.0.min( r.min.0 ), m.1.min( r.min.1 ), m.2.min( r.min.2 ) ) );
+    let max = parsed.iter().fold( ( i64::MIN, i64::MIN, i64::MIN ), |m, r| ( m.0.max( r.min.0 + r.size.0 as i64 ), m.1.max( r.min.1 + r.size.1 as i64 ), m.2.max( r.min.2 + r.size.2 as i64 ) ) );
+
+    let mut palette = Vec::new();
+    let mut blocks = Vec::new();
+    for region in parsed.iter() {
+        let region_palette = region.tag.get("BlockStatePalette").and_then( |p| p.as_list() ).ok_or( invalid( "a region has no block palette".to_string() ) )?;
+        let states = match region.tag.get("BlockStates") {
+            Some( Tag::LongArray(states) ) => states,
+            _ => return Err( invalid( "a region has no block states".to_string() ) ),
+        };
+
+        // the region's palette appended to the whole schematic's
+        let first = palette.len();
+        for entry in region_palette.iter() {
+            let name = entry.get("Name").and_then( |n| n.as_str() ).ok_or( invalid( "a palette entry has no name".to_string() ) )?;
+            let mut properties = HashMap::new();
+            if let Some(props) = entry.get("Properties").and_then( |p| p.as_compound() ) {
+                for (k, v) in props.iter() {
+                    if let Some(v) = v.as_str() {
+                        properties.insert( k.clone(), v.to_string() );
+                    }
+                }
+            }
+            palette.push( BlockState{ name: full_name(name), properties } );
+        }
+
+        let bits = ( usize::BITS - region_palette.len().saturating_sub(1).leading_zeros() ).max(2) as usize;
+        let (sx, _, sz) = region.size;
+        let count = block_count(region.size)?;
+        if count > states.len() * 64 / bits {
+            return Err( invalid( "a region has fewer block states than blocks".to_string() ) );
+        }
+        for i in 0..count {
+            let idx = packed_index( states, bits, i ).ok_or( invalid( "a region has fewer block states than blocks".to_string() ) )?;
+            if idx >= region_palette.len() {
+                return Err( invalid( format!( "block states use palette entry {}, which doesn't exist", idx ) ) );
+            }
+            if palette[ first + idx ].is_air() {
+                continue;
+            }
+            let (x, z, y) = ( i % sx, ( i / sx ) % sz, i / ( sx * sz ) );
+            let pos = ( ( region.min.0 - min.0 ) as usize + x, ( region.min.1 - min.1 ) as usize + y, ( region.min.2 - min.2 ) as usize + z );
+            blocks.push( ( pos, first + idx ) );
+        }
+    }
+
+    let size = ( ( max.0 - min.0 ) as usize, ( max.1 - min.1 ) as usize, ( max.2 - min.2 ) as usize );
+    Ok( Schematic{ size, palette, blocks } )
+}
+
+pub fn read_schematic(path: &Path) -> Result<Schematic, Error> {
+    let bytes = std::fs::read(path)?;
+    let (_, root) = nbt::read_nbt(&bytes).map_err( |e| invalid( format!( "{}: {}", path.display(), e ) ) )?;
+    let result = if let Some(regions) = root.get("Regions").and_then( |r| r.as_compound() ) {
+        read_litematic(regions)
+    } else {
+        // version 3 wraps everything in a Schematic compound
+        read_sponge( root.get("Schematic").unwrap_or(&root) )
+    };
+    result.map_err( |e| invalid( format!( "{}: {}", path.display(), e ) ) )
+}
+
+// how a Minecraft block's properties turn our block
+#[derive(Copy, Clone, Debug, PartialEq)]
+enum Orientation {
+    Stairs,
+    Slab,
+    Facing,
+    Axis,
+}
+
+impl Orientation {
+    fn parse(text: &str) -> Option<Orientation> {
+        match text {
+            "stairs" => Some( Orientation::Stairs ),
+            "slab" => Some( Orientation::Slab ),
+            "facing" => Some( Orientation::Facing ),
+            "axis" => Some( Orientation::Axis ),
+            _ => None,
+        }
+    }
+}
+
+fn direction(name: &str) -> Option<Vector3<f32>> {
+    match name {
+        "north" => Some( Vector3::new( 0.0, 0.0, -1.0 ) ),
+        "south" => Some( Vector3::new( 0.0, 0.0, 1.0 ) ),
+        "east" => Some( Vector3::new( 1.0, 0.0, 0.0 ) ),
+        "west" => Some( Vector3::new( -1.0, 0.0, 0.0 ) ),
+        "up" => Some( Vector3::new( 0.0, 1.0, 0.0 ) ),
+        "down" => Some( Vector3::new( 0.0, -1.0, 0.0 ) ),
+        _ => None,
+    }
+}
+
+// the way the solid part of the block should point, None leaves the block unturned
+fn wanted_direction(orientation: Orientation, props: &HashMap<String, String>) -> Option<Vector3<f32>> {
+    let prop = |name: &str| props.get(name).map( |v| v.as_str() );
+    match orientation {
+        // toward the stair's tall back, and down or up with its half. outer corners lean toward
+        // the side that's left standing
+        Orientation::Stairs => {
+            let facing = direction( prop("facing")? )?;
+            let left = Vector3::new( facing.z, 0.0, -facing.x );
+            let side = match prop("shape") {
+                Some("outer_left") => left,
+                Some("outer_right") => -left,
+                _ => Vector3::new( 0.0, 0.0, 0.0 ),
+            };
+            let up = if prop("half") == Some("top") { 1.0 } else { -1.0 };
+            Some( facing + side + Vector3::new( 0.0, up, 0.0 ) )
+        }
+        // toward the filled half
+        Orientation::Slab => match prop("type")? {
+            "top" => Some( Vector3::unit_y() ),
+            "bottom" => Some( -Vector3::unit_y() ),
+            _ => None,
+        },
+        Orientation::Facing => direction( prop("facing")? ),
+        Orientation::Axis => match prop("axis")? {
+            "x" => Some( Vector3::unit_x() ),
+            "y" => Some( Vector3::unit_y() ),
+            "z" => Some( Vector3::unit_z() ),
+            _ => None,
+        },
+    }
+}
+
+// the exparam that turns the shape's solid side closest to the wanted direction. unturned the
+// solid side of a RotFace shape faces +z, a slope's is its low -z edge and a corner's its -x -y -z vertex
+fn exparam_toward(shape: &BlockShape, wanted: Vector3<f32>) -> u8 {
+    let (canonical, count, quat_of): ( Vector3<f32>, u8, fn(u8) -> Quaternion<f32> ) = match shape.rot_group() {
+        RotType::RotFace => ( Vector3::unit_z(), 6, |e| rotation_group::generate_quat_from_rf( rotation_group::num_to_rf(e).unwrap() ) ),
+        RotType::RotEdge => ( Vector3::new( 0.0, -1.0, -1.0 ), 12, |e| rotation_group::generate_quat_from_re( rotation_group::num_to_re(e).unwrap() ) ),
+        RotType::RotVert => ( Vector3::new( -1.0, -1.0, -1.0 ), 8, |e| rotation_group::generate_quat_from_rv( rotation_group::num_to_rv(e).unwrap() ) ),
+        RotType::Static => return 0,
+    };
+    let wanted = wanted.normalize();
+    let mut best = ( 0, f32::MIN );
+    for e in 0..count {
+        let dot = ( quat_of(e) * canonical ).normalize().dot(wanted);
+        if dot > best.1 + 1e-4 {
+            best = ( e, dot );
+        }
+    }
+    best.0
+}
+
+// one [[block]] entry of the mapping file
+#[derive(Clone, Debug, Deserialize)]
+struct MappingRule {
+    // a block name, * matches any run of characters
+    minecraft: String,
+    // properties the block needs to have for the rule to apply, a|b accepts either value
+    #[serde(default)]
+    properties: HashMap<String, String>,
+    block: String,
+    #[serde(default)]
+    orientation: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct MappingFile {
+    #[serde(default)]
+    block: Vec<MappingRule>,
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = ( parts[0], parts[ parts.len() - 1 ] );
+    if !text.starts_with(first) || !text[ first.len().. ].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[ first.len()..( text.len() - last.len() ) ];
+    for part in parts[ 1..( parts.len() - 1 ) ].iter() {
+        match rest.find(part) {
+            Some(at) => rest = &rest[ at + part.len().. ],
+            None => return false,
+        }
+    }
+    true
+}
+
+struct Rule {
+    name: String,
+    properties: HashMap<String, String>,
+    blockdef: u16,
+    orientation: Option<Orientation>,
+}
+
+impl Rule {
+    fn matches(&self, state: &BlockState) -> bool {
+        glob_match( &self.name, &state.name ) && self.properties.iter().all( |(k, v)| {
+            state.properties.get(k).map( |have| v.split('|').any( |want| want == have ) ).unwrap_or(false)
+        } )
+    }
+}
+
+// rules are tried in the order the file lists them and the first that matches wins
+pub struct BlockMapping {
+    rules: Vec<Rule>,
+}
+
+impl BlockMapping {
+    pub fn load(path: &Path, registry: &BlockRegistry) -> Result<BlockMapping, Error> {
+        let contents = std::fs::read_to_string(path)?;
+        let file = toml::from_str::<MappingFile>(&contents).map_err( |e| invalid( format!( "{}: {}", path.display(), e ) ) )?;
+        let mut rules = Vec::new();
+        for rule in file.block {
+            let blockdef = registry.get_id(&rule.block).ok_or( invalid( format!( "{}: there is no block called '{}' in block.toml", path.display(), rule.block ) ) )?;
+            let orientation = match &rule.orientation {
+                Some(o) => Some( Orientation::parse(o).ok_or( invalid( format!( "{}: unknown orientation '{}', the orientations are stairs, slab, facing and axis", path.display(), o ) ) )? ),
+                None => None,
+            };
+            rules.push( Rule{ name: full_name(&rule.minecraft), properties: rule.properties, blockdef, orientation } );
+        }
+        Ok( BlockMapping{ rules } )
+    }
+
+    // block ID and exparam standing in for a Minecraft block, None when no rule covers it
+    pub fn map(&self, state: &BlockState, registry: &BlockRegistry, shapes: &BlockShapeRegistry) -> Option<(u16, u8)> {
+        let rule = self.rules.iter().find( |r| r.matches(state) )?;
+        let shape = shapes.get( registry.get(rule.blockdef)?.shape_id )?;
+        let exparam = match rule.orientation.and_then( |o| wanted_direction( o, &state.properties ) ) {
+            Some(wanted) => exparam_toward( shape, wanted ),
+            None => 0,
+        };
+        if shape.valid_exparam(exparam) { Some( (rule.blockdef, exparam) ) } else { Some( (rule.blockdef, 0) ) }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varints() {
+        // 1, 300 and 0, 300 taking two bytes
+        let data: Vec<i8> = [ 0x01_u8, 0xac, 0x02, 0x00 ].iter().map( |b| *b as i8 ).collect();
+        assert_eq!( read_varints( &data, 3 ).unwrap(), vec![ 1, 300, 0 ] );
+        assert!( read_varints( &data, 4 ).is_err() );
+        // more blocks than bytes is refused before allocating for them
+        assert!( read_varints( &data, usize::MAX ).is_err() );
+        let too_long: Vec<i8> = [ 0xff_u8; 6 ].iter().map( |b| *b as i8 ).collect();
+        assert!( read_varints( &too_long, 1 ).is_err() );
+    }
+
+    #[test]
+    fn packed_indices() {
+        // 5 bit indices 0, 1, 2, ... with the 13th split across both longs
+        let bits = 5;
+        let mut states = [ 0_i64; 2 ];
+        for i in 0..25 {
+            let start = i * bits;
+            states[ start / 64 ] |= ( ( i as u64 ) << ( start % 64 ) ) as i64;
+            if start % 64 + bits > 64 {
+                states[ start / 64 + 1 ] |= ( ( i as u64 ) >> ( 64 - start % 64 ) ) as i64;
+            }
+        }
+        for i in 0..25 {
+            assert_eq!( packed_index( &states, bits, i ), Some(i) );
+        }
+        assert_eq!( packed_index( &states, bits, 26 ), None );
+        assert_eq!( packed_index( &[ -1 ], 2, 31 ), Some(3) );
+    }
+
+    #[test]
+    fn block_count_overflow() {
+        assert_eq!( block_count( (2, 3, 4) ).unwrap(), 24 );
+        assert!( block_count( ( usize::MAX / 2, 3, 1 ) ).is_err() );
+        assert!( block_count( ( 1 << 31, 1 << 31, 1 << 31 ) ).is_err() );
+    }
+
+    #[test]
+    fn globs() {
+        assert!( glob_match( "minecraft:stone", "minecraft:stone" ) );
+        assert!( !glob_match( "minecraft:stone", "minecraft:stone_bricks" ) );
+        assert!( glob_match( "minecraft:*_planks", "minecraft:oak_planks" ) );
+        assert!( glob_match( "*", "" ) );
+        assert!( glob_match( "minecraft:*stone*", "minecraft:stone" ) );
+        assert!( glob_match( "minecraft:*_stairs", "minecraft:dark_oak_stairs" ) );
+        assert!( glob_match( "*:*_log", "mod:birch_log" ) );
+        assert!( !glob_match( "*:*_log", "mod:birch_logs" ) );
+        // the ends can't share characters
+        assert!( !glob_match( "ab*ba", "aba" ) );
+    }
+}
@@ -0,0 +1,193 @@
+use std::io::Error;
+
+use crate::engine::invalid;
+
+// reading and writing MagicaVoxel .vox files. MagicaVoxel is z-up, conversion to world
+// coordinates is left to the caller.
+
+const VOX_VERSION: u32 = 150;
+// MagicaVoxel can't open models bigger than this along any side
+pub const MAX_MODEL_SIZE: usize = 256;
+
+pub struct VoxModel {
+    pub size: (usize, usize, usize),
+    // x, y, z and colour index 1 to 255
+    pub voxels: Vec<(u8, u8, u8, u8)>,
+    // colour of each colour index, entry 0 is never used by voxels
+    pub palette: [[u8; 4]; 256],
+}
+
+// the palette MagicaVoxel uses for files without an RGBA chunk: a 6x6x6 colour cube without black,
+// then ramps of red, green, blue and gray
+pub fn default_palette() -> [[u8; 4]; 256] {
+    let mut palette = [[0_u8; 4]; 256];
+    let steps = [ 0xff, 0xcc, 0x99, 0x66, 0x33, 0x00 ];
+    let mut i = 1;
+    for r in steps {
+        for g in steps {
+            for b in steps {
+                if r == 0 && g == 0 && b == 0 {
+                    continue;
+                }
+                palette[i] = [r, g, b, 0xff];
+                i += 1;
+            }
+        }
+    }
+    let ramp = [ 0xee, 0xdd, 0xbb, 0xaa, 0x88, 0x77, 0x55, 0x44, 0x22, 0x11 ];
+    for channel in 0..4 {
+        for v in ramp {
+            palette[i] = match channel {
+                0 => [v, 0, 0, 0xff],
+                1 => [0, v, 0, 0xff],
+                2 => [0, 0, v, 0xff],
+                _ => [v, v, v, 0xff],
+            };
+            i += 1;
+        }
+    }
+    palette
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> Result<u32, Error> {
+    bytes.get( at..at + 4 ).map( |b| u32::from_le_bytes( [b[0], b[1], b[2], b[3]] ) ).ok_or( invalid( "the file ends in the middle of a chunk".to_string() ) )
+}
+
+// every model in the file, in the order they are stored
+pub fn read_vox(bytes: &[u8]) -> Result<Vec<VoxModel>, Error> {
+    if bytes.len() < 8 || &bytes[0..4] != b"VOX " {
+        return Err( invalid( "not a MagicaVoxel file".to_string() ) );
+    }
+    if bytes.get(8..12) != Some( &b"MAIN"[..] ) {
+        return Err( invalid( "the file has no MAIN chunk".to_string() ) );
+    }
+
+    let mut sizes = Vec::<(usize, usize, usize)>::new();
+    let mut voxel_lists = Vec::<Vec<(u8, u8, u8, u8)>>::new();
+    let mut palette = None;
+
+    // the MAIN chunk has no content of its own, its children follow its 12 byte header
+    let mut at = 20 + read_u32(bytes, 12)? as usize;
+    let end = at + read_u32(bytes, 16)? as usize;
+    if end > bytes.len() {
+        return Err( invalid( "the file ends before its last chunk".to_string() ) );
+    }
+    while at + 12 <= end {
+        let id = &bytes[at..at + 4];
+        let content_len = read_u32(bytes, at + 4)? as usize;
+        let children_len = read_u32(bytes, at + 8)? as usize;
+        let content = bytes.get( at + 12..at + 12 + content_len ).ok_or( invalid( "the file ends in the middle of a chunk".to_string() ) )?;
+
+        match id {
+            b"SIZE" => {
+                sizes.push( ( read_u32(content, 0)? as usize, read_u32(content, 4)? as usize, read_u32(content, 8)? as usize ) );
+            }
+            b"XYZI" => {
+                let count = read_u32(content, 0)? as usize;
+                let data = content.get( 4..4 + count * 4 ).ok_or( invalid( "voxel list is shorter than its count".to_string() ) )?;
+                voxel_lists.push( data.chunks_exact(4).map( |v| (v[0], v[1], v[2], v[3]) ).collect() );
+            }
+            b"RGBA" => {
+                // entry i of the chunk is colour index i + 1
+                let mut pal = [[0_u8; 4]; 256];
+                for (i, c) in content.chunks_exact(4).take(255).enumerate() {
+                    pal[i + 1] = [c[0], c[1], c[2], c[3]];
+                }
+                palette = Some(pal);
+            }
+            // scene graph, materials and other chunks don't matter for blocks
+            _ => {}
+        }
+        at += 12 + content_len + children_len;
+    }
+
+    if sizes.len() != voxel_lists.len() {
+        return Err( invalid( "model sizes and voxel lists don't match up".to_string() ) );
+    }
+    let palette = palette.unwrap_or_else(default_palette);
+    Ok( sizes.into_iter().zip( voxel_lists.into_iter() ).map( |(size, voxels)| VoxModel{ size, voxels, palette } ).collect() )
+}
+
+fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], content: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice( &( content.len() as u32 ).to_le_bytes() );
+    out.extend_from_slice( &0_u32.to_le_bytes() );
+    out.extend_from_slice(content);
+}
+
+pub fn write_vox(model: &VoxModel) -> Result<Vec<u8>, Error> {
+    if model.size.0 > MAX_MODEL_SIZE || model.size.1 > MAX_MODEL_SIZE || model.size.2 > MAX_MODEL_SIZE {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "MagicaVoxel models can be at most {} voxels along each side", MAX_MODEL_SIZE ) ) );
+    }
+
+    let mut children = Vec::<u8>::new();
+    let mut size = Vec::<u8>::new();
+    for s in [ model.size.0, model.size.1, model.size.2 ] {
+        size.extend_from_slice( &( s as u32 ).to_le_bytes() );
+    }
+    push_chunk( &mut children, b"SIZE", &size );
+
+    let mut xyzi = Vec::<u8>::with_capacity( 4 + model.voxels.len() * 4 );
+    xyzi.extend_from_slice( &( model.voxels.len() as u32 ).to_le_bytes() );
+    for v in model.voxels.iter() {
+        xyzi.extend_from_slice( &[v.0, v.1, v.2, v.3] );
+    }
+    push_chunk( &mut children, b"XYZI", &xyzi );
+
+    let mut rgba = Vec::<u8>::with_capacity(1024);
+    for i in 0..256 {
+        rgba.extend_from_slice( &model.palette[ ( i + 1 ) % 256 ] );
+    }
+    push_chunk( &mut children, b"RGBA", &rgba );
+
+    let mut out = Vec::<u8>::new();
+    out.extend_from_slice(b"VOX ");
+    out.extend_from_slice( &VOX_VERSION.to_le_bytes() );
+    out.extend_from_slice(b"MAIN");
+    out.extend_from_slice( &0_u32.to_le_bytes() );
+    out.extend_from_slice( &( children.len() as u32 ).to_le_bytes() );
+    out.extend_from_slice(&children);
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> VoxModel {
+        let mut palette = default_palette();
+        palette[1] = [ 1, 2, 3, 4 ];
+        palette[255] = [ 250, 251, 252, 253 ];
+        VoxModel {
+            size: (3, 2, 1),
+            voxels: vec![ (0, 0, 0, 1), (2, 1, 0, 255), (1, 0, 0, 7) ],
+            palette,
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let model = sample();
+        let models = read_vox( &write_vox(&model).unwrap() ).unwrap();
+        assert_eq!( models.len(), 1 );
+        assert_eq!( models[0].size, model.size );
+        assert_eq!( models[0].voxels, model.voxels );
+        // index 0 isn't stored, voxels can't use it
+        assert_eq!( models[0].palette[1..], model.palette[1..] );
+    }
+
+    #[test]
+    fn truncated() {
+        let bytes = write_vox( &sample() ).unwrap();
+        for cut in 0..bytes.len() {
+            assert!( read_vox( &bytes[ ..cut ] ).is_err(), "cut at {} was read", cut );
+        }
+    }
+
+    #[test]
+    fn too_big() {
+        let mut model = sample();
+        model.size = ( MAX_MODEL_SIZE + 1, 1, 1 );
+        assert!( write_vox(&model).is_err() );
+    }
+}
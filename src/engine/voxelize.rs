@@ -0,0 +1,554 @@
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::Path;
+
+use cgmath::{InnerSpace, Matrix4, Rotation, SquareMatrix, Vector3, Vector4};
+
+use crate::engine::invalid;
+use crate::engine::rotation_group;
+use crate::engine::world_edit::BlockFamily;
+
+// turning triangle meshes into blocks. each block is tested at a grid of sample points,
+// and the block shape and rotation that covers the same samples is picked for it
+
+// samples per block along each side, all of a block's samples have to fit in a u64
+const SAMPLES: usize = 4;
+// moves the samples off the diagonal planes of the slope shapes, where rounding would
+// decide whether they are inside or not
+const SAMPLE_JITTER: [f32; 3] = [ 0.011, 0.023, 0.037 ];
+// how many samples a slope may get wrong to face the same way as the surface it stands for
+const NORMAL_WEIGHT: f32 = 4.0;
+// models bigger than this many blocks along a side are almost always a wrong scale
+pub const MAX_GRID_SIZE: usize = 2048;
+
+pub type Triangle = [Vector3<f32>; 3];
+
+// triangles of every face in an OBJ file, polygons are split into fans
+fn read_obj(text: &str) -> Result<Vec<Triangle>, Error> {
+    let mut vertices = Vec::<Vector3<f32>>::new();
+    let mut triangles = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let mut parts = line.split_whitespace();
+        match parts.next() {
+            Some("v") => {
+                let c: Vec<f32> = parts.take(3).map( |p| p.parse::<f32>() ).collect::<Result<_, _>>().map_err( |_| invalid( format!( "line {}: bad vertex", n + 1 ) ) )?;
+                if c.len() != 3 {
+                    return Err( invalid( format!( "line {}: a vertex needs x, y and z", n + 1 ) ) );
+                }
+                vertices.push( Vector3::new( c[0], c[1], c[2] ) );
+            }
+            Some("f") => {
+                let mut face = Vec::new();
+                for p in parts {
+                    // v, v/vt, v//vn or v/vt/vn, negative indices count back from the last vertex
+                    let idx = p.split('/').next().unwrap_or("").parse::<i64>().map_err( |_| invalid( format!( "line {}: bad face", n + 1 ) ) )?;
+                    let i = if idx < 0 { vertices.len() as i64 + idx } else { idx - 1 };
+                    if i < 0 || i as usize >= vertices.len() {
+                        return Err( invalid( format!( "line {}: the face uses a vertex that doesn't exist", n + 1 ) ) );
+                    }
+                    face.push( vertices[ i as usize ] );
+                }
+                for i in 1..face.len().saturating_sub(1) {
+                    triangles.push( [ face[0], face[i], face[i + 1] ] );
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(triangles)
+}
+
+// for buffers embedded in .gltf files as data: URIs
+fn decode_base64(text: &str) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::with_capacity( text.len() * 3 / 4 );
+    let (mut acc, mut bits) = (0_u32, 0);
+    for c in text.bytes() {
+        let v = match c {
+            b'A'..=b'Z' => c - b'A',
+            b'a'..=b'z' => c - b'a' + 26,
+            b'0'..=b'9' => c - b'0' + 52,
+            b'+' | b'-' => 62,
+            b'/' | b'_' => 63,
+            b'=' => break,
+            b' ' | b'\n' | b'\r' | b'\t' => continue,
+            _ => return Err( invalid( "bad base64 in a data URI".to_string() ) ),
+        };
+        acc = ( acc << 6 ) | v as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            out.push( ( acc >> bits ) as u8 );
+            acc &= ( 1 << bits ) - 1;
+        }
+    }
+    Ok(out)
+}
+
+fn read_gltf_node(node: &gltf::Node, parent: Matrix4<f32>, buffers: &[Vec<u8>], triangles: &mut Vec<Triangle>) -> Result<(), Error> {
+    let matrix = parent * Matrix4::from( node.transform().matrix() );
+    // a mirroring transform turns the faces inside out
+    let mirrored = matrix.determinant() < 0.0;
+
+    if let Some(mesh) = node.mesh() {
+        for prim in mesh.primitives() {
+            if prim.mode() != gltf::mesh::Mode::Triangles {
+                continue;
+            }
+            let reader = prim.reader( |b| buffers.get( b.index() ).map( |d| d.as_slice() ) );
+            let positions: Vec<Vector3<f32>> = match reader.read_positions() {
+                Some(p) => p.map( |p| ( matrix * Vector4::new( p[0], p[1], p[2], 1.0 ) ).truncate() ).collect(),
+                None => continue,
+            };
+            let indices: Vec<u32> = match reader.read_indices() {
+                Some(i) => i.into_u32().collect(),
+                None => ( 0..positions.len() as u32 ).collect(),
+            };
+            let get = |i: u32| positions.get( i as usize ).copied().ok_or( invalid( "an index points past the vertices".to_string() ) );
+            for tri in indices.chunks_exact(3) {
+                if mirrored {
+                    triangles.push( [ get(tri[0])?, get(tri[2])?, get(tri[1])? ] );
+                } else {
+                    triangles.push( [ get(tri[0])?, get(tri[1])?, get(tri[2])? ] );
+                }
+            }
+        }
+    }
+
+    for child in node.children() {
+        read_gltf_node( &child, matrix, buffers, triangles )?;
+    }
+    Ok(())
+}
+
+// triangles of every mesh in the default scene, with the node transforms applied
+fn read_gltf(bytes: &[u8], dir: &Path) -> Result<Vec<Triangle>, Error> {
+    let gltf = gltf::Gltf::from_slice(bytes).map_err( |e| invalid( e.to_string() ) )?;
+
+    let mut buffers = Vec::new();
+    for buffer in gltf.buffers() {
+        let data = match buffer.source() {
+            gltf::buffer::Source::Bin => gltf.blob.clone().ok_or( invalid( "the binary chunk is missing".to_string() ) )?,
+            gltf::buffer::Source::Uri(uri) => match uri.strip_prefix("data:") {
+                Some(data) => decode_base64( data.split_once(',').map( |(_, d)| d ).unwrap_or("") )?,
+                None => {
+                    let mut pbuf = dir.to_path_buf();
+                    pbuf.push(uri);
+                    std::fs::read(&pbuf).map_err( |e| Error::new( e.kind(), format!( "{}: {}", pbuf.display(), e ) ) )?
+                }
+            },
+        };
+        buffers.push(data);
+    }
+
+    let scene = gltf.default_scene().or_else( || gltf.scenes().next() ).ok_or( invalid( "the file has no scene".to_string() ) )?;
+    let mut triangles = Vec::new();
+    for node in scene.nodes() {
+        read_gltf_node( &node, Matrix4::identity(), &buffers, &mut triangles )?;
+    }
+    Ok(triangles)
+}
+
+// reads .obj, .gltf or .glb by the file's extension
+pub fn load_triangles(path: &Path) -> Result<Vec<Triangle>, Error> {
+    let bytes = std::fs::read(path).map_err( |e| Error::new( e.kind(), format!( "{}: {}", path.display(), e ) ) )?;
+    let ext = path.extension().and_then( |e| e.to_str() ).map( |e| e.to_lowercase() ).unwrap_or_default();
+    match ext.as_str() {
+        "obj" => read_obj( &String::from_utf8_lossy(&bytes) ),
+        "gltf" | "glb" => read_gltf( &bytes, &path.parent().map( |p| p.to_path_buf() ).unwrap_or_default() ),
+        _ => Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "can't read '{}' files, the formats are obj, gltf and glb", ext ) ) ),
+    }
+}
+
+// which block goes into a cell, with its exparam
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum CellFit {
+    Cube,
+    Slope(u8),
+    Corner(u8),
+}
+
+impl CellFit {
+    // block ID and exparam, the family has to have the shape, which it does for fits made with its ShapeFitter
+    pub fn block(&self, family: &BlockFamily) -> (u16, u8) {
+        match self {
+            CellFit::Cube => ( family.cube, 0 ),
+            CellFit::Slope(exparam) => ( family.slope.unwrap(), *exparam ),
+            CellFit::Corner(exparam) => ( family.corner.unwrap(), *exparam ),
+        }
+    }
+}
+
+struct Candidate {
+    fit: Option<CellFit>,
+    mask: u64,
+    // which way the sloped face points, None for shapes without one
+    normal: Option<Vector3<f32>>,
+}
+
+// where sample s sits along an axis of the grid, in blocks
+fn sample_coord(s: usize, axis: usize) -> f32 {
+    ( s as f32 + 0.5 ) / SAMPLES as f32 + SAMPLE_JITTER[axis]
+}
+
+// the samples whose coordinate lies between lo and hi, clamped to the grid
+fn sample_range(lo: f32, hi: f32, axis: usize, count: usize) -> std::ops::Range<usize> {
+    let first = ( ( lo - SAMPLE_JITTER[axis] ) * SAMPLES as f32 - 0.5 ).ceil().max(0.0) as usize;
+    let end = ( ( ( hi - SAMPLE_JITTER[axis] ) * SAMPLES as f32 - 0.5 ).floor() + 1.0 ).clamp( 0.0, count as f32 ) as usize;
+    first..end.max(first)
+}
+
+fn sample_bit(k: (usize, usize, usize)) -> u64 {
+    1 << ( k.0 + SAMPLES * ( k.1 + SAMPLES * k.2 ) )
+}
+
+// samples of one block that fall inside a shape, positions relative to the block's center
+pub fn shape_mask(inside: impl Fn(Vector3<f32>) -> bool) -> u64 {
+    let mut mask = 0;
+    for kx in 0..SAMPLES {
+        for ky in 0..SAMPLES {
+            for kz in 0..SAMPLES {
+                let p = Vector3::new( sample_coord(kx, 0) - 0.5, sample_coord(ky, 1) - 0.5, sample_coord(kz, 2) - 0.5 );
+                if inside(p) {
+                    mask |= sample_bit( (kx, ky, kz) );
+                }
+            }
+        }
+    }
+    mask
+}
+
+// every shape and rotation the family can build, rotated the same way meshing rotates them
+fn candidates(family: &BlockFamily) -> Vec<Candidate> {
+    let mut out = vec![
+        Candidate{ fit: None, mask: 0, normal: None },
+        Candidate{ fit: Some( CellFit::Cube ), mask: shape_mask( |_| true ), normal: None },
+    ];
+
+    if family.slope.is_some() {
+        for exparam in 0..16 {
+            if let Some(re) = rotation_group::num_to_re(exparam) {
+                let quat = rotation_group::generate_quat_from_re(re);
+                let inv = quat.invert();
+                // unrotated, the slope is solid below the plane y + z = 0
+                out.push( Candidate {
+                    fit: Some( CellFit::Slope(exparam) ),
+                    mask: shape_mask( |p| { let q = inv.rotate_vector(p); q.y + q.z <= 0.0 } ),
+                    normal: Some( quat.rotate_vector( Vector3::new( 0.0, 1.0, 1.0 ).normalize() ) ),
+                } );
+            }
+        }
+    }
+
+    if family.corner.is_some() {
+        for exparam in 0..8 {
+            if let Some(rv) = rotation_group::num_to_rv(exparam) {
+                let quat = rotation_group::generate_quat_from_rv(rv);
+                let inv = quat.invert();
+                // unrotated, the corner is solid below the plane x + y + z = -0.5
+                out.push( Candidate {
+                    fit: Some( CellFit::Corner(exparam) ),
+                    mask: shape_mask( |p| { let q = inv.rotate_vector(p); q.x + q.y + q.z <= -0.5 } ),
+                    normal: Some( quat.rotate_vector( Vector3::new( 1.0, 1.0, 1.0 ).normalize() ) ),
+                } );
+            }
+        }
+    }
+    out
+}
+
+// picks the block for a cell out of what the family can build
+pub struct ShapeFitter {
+    candidates: Vec<Candidate>,
+}
+
+impl ShapeFitter {
+    pub fn new(family: &BlockFamily) -> ShapeFitter {
+        ShapeFitter{ candidates: candidates(family) }
+    }
+
+    // mask is the samples that should be solid, see shape_mask. normal is which way the
+    // surface through the cell faces, None if no surface passes through it.
+    // None means the cell is best left empty
+    pub fn fit(&self, mask: u64, normal: Option<Vector3<f32>>) -> Option<CellFit> {
+        let score = |c: &Candidate| {
+            let wrong = ( mask ^ c.mask ).count_ones() as f32;
+            match ( normal, c.normal ) {
+                ( Some(n), Some(cn) ) => wrong + NORMAL_WEIGHT * ( 1.0 - n.dot(cn) ),
+                // no surface runs through the cell, so nothing to slope along
+                ( None, Some(_) ) => wrong + NORMAL_WEIGHT,
+                _ => wrong,
+            }
+        };
+        self.candidates.iter().min_by( |a, b| score(a).total_cmp( &score(b) ) ).unwrap().fit
+    }
+}
+
+pub struct Voxelized {
+    // blocks along each side of the model
+    pub size: (usize, usize, usize),
+    // cells counted from the model's minimum corner
+    pub cells: Vec<( (usize, usize, usize), CellFit )>,
+}
+
+// scale is blocks per unit of the mesh. the mesh should be closed, whatever is inside
+// it is filled in
+pub fn voxelize(triangles: &[Triangle], scale: f32, family: &BlockFamily) -> Result<Voxelized, Error> {
+    if triangles.is_empty() {
+        return Err( invalid( "the mesh has no triangles".to_string() ) );
+    }
+
+    let mut min = Vector3::new( f32::MAX, f32::MAX, f32::MAX );
+    let mut max = Vector3::new( f32::MIN, f32::MIN, f32::MIN );
+    for v in triangles.iter().flatten() {
+        min = Vector3::new( min.x.min(v.x), min.y.min(v.y), min.z.min(v.z) );
+        max = Vector3::new( max.x.max(v.x), max.y.max(v.y), max.z.max(v.z) );
+    }
+    let extent = ( max - min ) * scale;
+    let size = ( ( extent.x.ceil() as usize ).max(1), ( extent.y.ceil() as usize ).max(1), ( extent.z.ceil() as usize ).max(1) );
+    if size.0 > MAX_GRID_SIZE || size.1 > MAX_GRID_SIZE || size.2 > MAX_GRID_SIZE {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "at this scale the model is {}x{}x{} blocks, more than {} along a side", size.0, size.1, size.2, MAX_GRID_SIZE ) ) );
+    }
+
+    // in blocks, with the model's minimum corner at the origin
+    let tris: Vec<Triangle> = triangles.iter().map( |t| [ ( t[0] - min ) * scale, ( t[1] - min ) * scale, ( t[2] - min ) * scale ] ).collect();
+    let samples = ( size.0 * SAMPLES, size.1 * SAMPLES, size.2 * SAMPLES );
+
+    // where each row of samples along x crosses the surface
+    let mut rows = HashMap::<(usize, usize), Vec<f32>>::new();
+    for [a, b, c] in tris.iter() {
+        let d = ( b.y - a.y ) * ( c.z - a.z ) - ( c.y - a.y ) * ( b.z - a.z );
+        if d.abs() < 1e-12 {
+            // seen edge on from along x, rows never cross it
+            continue;
+        }
+        for sy in sample_range( a.y.min(b.y).min(c.y), a.y.max(b.y).max(c.y), 1, samples.1 ) {
+            let y = sample_coord(sy, 1);
+            for sz in sample_range( a.z.min(b.z).min(c.z), a.z.max(b.z).max(c.z), 2, samples.2 ) {
+                let z = sample_coord(sz, 2);
+                let u = ( ( b.y - y ) * ( c.z - z ) - ( c.y - y ) * ( b.z - z ) ) / d;
+                let v = ( ( c.y - y ) * ( a.z - z ) - ( a.y - y ) * ( c.z - z ) ) / d;
+                let w = 1.0 - u - v;
+                if u < 0.0 || v < 0.0 || w < 0.0 {
+                    continue;
+                }
+                rows.entry( (sy, sz) ).or_default().push( u * a.x + v * b.x + w * c.x );
+            }
+        }
+    }
+
+    // samples between each pair of crossings are inside
+    let mut masks = HashMap::<(usize, usize, usize), u64>::new();
+    for ( (sy, sz), mut xs ) in rows.into_iter() {
+        xs.sort_by( |a, b| a.total_cmp(b) );
+        for span in xs.chunks_exact(2) {
+            for sx in sample_range( span[0], span[1], 0, samples.0 ) {
+                let cell = ( sx / SAMPLES, sy / SAMPLES, sz / SAMPLES );
+                *masks.entry(cell).or_insert(0) |= sample_bit( ( sx % SAMPLES, sy % SAMPLES, sz % SAMPLES ) );
+            }
+        }
+    }
+
+    // which way the surface faces in every cell it passes through, weighted by area
+    let mut normals = HashMap::<(usize, usize, usize), Vector3<f32>>::new();
+    for [a, b, c] in tris.iter() {
+        let n = ( b - a ).cross( c - a );
+        if n.magnitude2() == 0.0 {
+            continue;
+        }
+        let unit = n.normalize();
+        let lo = ( a.x.min(b.x).min(c.x), a.y.min(b.y).min(c.y), a.z.min(b.z).min(c.z) );
+        let hi = ( a.x.max(b.x).max(c.x), a.y.max(b.y).max(c.y), a.z.max(b.z).max(c.z) );
+        let cell_range = |lo: f32, hi: f32, count: usize| ( lo.floor().max(0.0) as usize )..( ( hi.floor() + 1.0 ).clamp( 0.0, count as f32 ) as usize );
+        for x in cell_range( lo.0, hi.0, size.0 ) {
+            for y in cell_range( lo.1, hi.1, size.1 ) {
+                for z in cell_range( lo.2, hi.2, size.2 ) {
+                    let center = Vector3::new( x as f32 + 0.5, y as f32 + 0.5, z as f32 + 0.5 );
+                    // half the diagonal of a block, the plane misses the block beyond that
+                    if ( center - a ).dot(unit).abs() <= 0.87 {
+                        *normals.entry( (x, y, z) ).or_insert( Vector3::new( 0.0, 0.0, 0.0 ) ) += n;
+                    }
+                }
+            }
+        }
+    }
+
+    let fitter = ShapeFitter::new(family);
+    let mut cells = Vec::new();
+    for (cell, mask) in masks.into_iter() {
+        let normal = normals.get(&cell).filter( |n| n.magnitude2() > 0.0 ).map( |n| n.normalize() );
+        if let Some(fit) = fitter.fit( mask, normal ) {
+            cells.push( (cell, fit) );
+        }
+    }
+    cells.sort_by_key( |(cell, _)| ( cell.1, cell.2, cell.0 ) );
+
+    Ok( Voxelized{ size, cells } )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAMILY: BlockFamily = BlockFamily{ cube: 1, slope: Some(2), corner: Some(3) };
+
+    // corner of the unit cube, flipped to the far side along the axes where flip is set
+    fn corner_point(x: f32, y: f32, z: f32, flip: [bool; 3]) -> Vector3<f32> {
+        let f = |c: f32, f: bool| if f { 1.0 - c } else { c };
+        Vector3::new( f( x, flip[0] ), f( y, flip[1] ), f( z, flip[2] ) )
+    }
+
+    fn quad(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>, d: Vector3<f32>) -> [Triangle; 2] {
+        [ [ a, b, c ], [ a, c, d ] ]
+    }
+
+    fn cube(side: f32) -> Vec<Triangle> {
+        let p = |x: f32, y: f32, z: f32| Vector3::new( x, y, z ) * side;
+        [
+            quad( p(0.0, 0.0, 0.0), p(0.0, 1.0, 0.0), p(1.0, 1.0, 0.0), p(1.0, 0.0, 0.0) ),
+            quad( p(0.0, 0.0, 1.0), p(1.0, 0.0, 1.0), p(1.0, 1.0, 1.0), p(0.0, 1.0, 1.0) ),
+            quad( p(0.0, 0.0, 0.0), p(0.0, 0.0, 1.0), p(0.0, 1.0, 1.0), p(0.0, 1.0, 0.0) ),
+            quad( p(1.0, 0.0, 0.0), p(1.0, 1.0, 0.0), p(1.0, 1.0, 1.0), p(1.0, 0.0, 1.0) ),
+            quad( p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(1.0, 0.0, 1.0), p(0.0, 0.0, 1.0) ),
+            quad( p(0.0, 1.0, 0.0), p(0.0, 1.0, 1.0), p(1.0, 1.0, 1.0), p(1.0, 1.0, 0.0) ),
+        ].concat()
+    }
+
+    // a block cut in half along the diagonal between axes a and b, solid towards the edge
+    // where both are lowest before flipping. returns the mesh and the sloped face's normal
+    fn wedge(a: usize, b: usize, flip: [bool; 3]) -> (Vec<Triangle>, Vector3<f32>) {
+        let p = |u: f32, v: f32, w: f32| {
+            let mut c = [w; 3];
+            c[a] = u;
+            c[b] = v;
+            corner_point( c[0], c[1], c[2], flip )
+        };
+        let mut tris = vec![ [ p(0.0, 0.0, 0.0), p(1.0, 0.0, 0.0), p(0.0, 1.0, 0.0) ], [ p(0.0, 0.0, 1.0), p(0.0, 1.0, 1.0), p(1.0, 0.0, 1.0) ] ];
+        tris.extend( quad( p(0.0, 0.0, 0.0), p(0.0, 0.0, 1.0), p(1.0, 0.0, 1.0), p(1.0, 0.0, 0.0) ) );
+        tris.extend( quad( p(0.0, 0.0, 0.0), p(0.0, 1.0, 0.0), p(0.0, 1.0, 1.0), p(0.0, 0.0, 1.0) ) );
+        tris.extend( quad( p(1.0, 0.0, 0.0), p(1.0, 0.0, 1.0), p(0.0, 1.0, 1.0), p(0.0, 1.0, 0.0) ) );
+
+        let mut normal = Vector3::new( 0.0, 0.0, 0.0 );
+        normal[a] = if flip[a] { -1.0 } else { 1.0 };
+        normal[b] = if flip[b] { -1.0 } else { 1.0 };
+        ( tris, normal.normalize() )
+    }
+
+    // the tetrahedron in the corner of a block that is lowest along every axis before flipping
+    fn corner(flip: [bool; 3]) -> (Vec<Triangle>, Vector3<f32>) {
+        let o = corner_point( 0.0, 0.0, 0.0, flip );
+        let x = corner_point( 1.0, 0.0, 0.0, flip );
+        let y = corner_point( 0.0, 1.0, 0.0, flip );
+        let z = corner_point( 0.0, 0.0, 1.0, flip );
+        let s = |f: bool| if f { -1.0 } else { 1.0 };
+        ( vec![ [ o, y, x ], [ o, x, z ], [ o, z, y ], [ x, y, z ] ], Vector3::new( s(flip[0]), s(flip[1]), s(flip[2]) ).normalize() )
+    }
+
+    fn flips() -> Vec<[bool; 3]> {
+        ( 0..8 ).map( |i| [ i & 1 != 0, i & 2 != 0, i & 4 != 0 ] ).collect()
+    }
+
+    fn candidate(fit: CellFit) -> Candidate {
+        candidates(&FAMILY).into_iter().find( |c| c.fit == Some(fit) ).unwrap()
+    }
+
+    #[test]
+    fn candidates_follow_rotation_group() {
+        // the solid edge or corner of the unrotated shape ends up wherever the quaternion
+        // meshing uses moves it, behind the sloped face
+        for c in candidates(&FAMILY) {
+            let (solid, offset) = match c.fit {
+                Some( CellFit::Slope(e) ) => ( rotation_group::generate_quat_from_re( rotation_group::num_to_re(e).unwrap() ).rotate_vector( Vector3::new( 0.0, -1.0, -1.0 ) ), 0.0 ),
+                Some( CellFit::Corner(e) ) => ( rotation_group::generate_quat_from_rv( rotation_group::num_to_rv(e).unwrap() ).rotate_vector( Vector3::new( -1.0, -1.0, -1.0 ) ), -0.5 / 3.0_f32.sqrt() ),
+                _ => continue,
+            };
+            let n = c.normal.unwrap();
+            assert!( n.dot( -solid.normalize() ) > 0.99, "{:?}", c.fit );
+            assert_eq!( shape_mask( |p| p.dot(n) <= offset ), c.mask, "{:?}", c.fit );
+        }
+
+        // every edge of the block has a slope along it
+        let slopes: Vec<u64> = candidates(&FAMILY).into_iter().filter( |c| matches!( c.fit, Some( CellFit::Slope(_) ) ) ).map( |c| c.mask ).collect();
+        assert_eq!( slopes.len(), 12 );
+        for (i, m) in slopes.iter().enumerate() {
+            assert!( !slopes[i + 1..].contains(m) );
+        }
+    }
+
+    #[test]
+    fn fitter_picks_exact_shapes() {
+        let fitter = ShapeFitter::new(&FAMILY);
+        for c in candidates(&FAMILY) {
+            // some exparams build the same shape, any of them will do
+            let fit = fitter.fit( c.mask, c.normal );
+            assert!( candidates(&FAMILY).iter().any( |o| o.fit == fit && o.mask == c.mask ), "{:?} got {:?}", c.fit, fit );
+        }
+        // without the shape, the nearest one the family has is used
+        let cubes = ShapeFitter::new( &BlockFamily{ cube: 1, slope: None, corner: None } );
+        let corner = candidate( CellFit::Corner(0) );
+        assert_eq!( cubes.fit( !corner.mask, corner.normal.map( |n| -n ) ), Some( CellFit::Cube ) );
+        assert_eq!( cubes.fit( corner.mask, corner.normal ), None );
+    }
+
+    #[test]
+    fn unit_cube() {
+        let v = voxelize( &cube(1.0), 1.0, &FAMILY ).unwrap();
+        assert_eq!( v.size, (1, 1, 1) );
+        assert_eq!( v.cells, vec![ ( (0, 0, 0), CellFit::Cube ) ] );
+    }
+
+    #[test]
+    fn fill_reaches_the_middle() {
+        let v = voxelize( &cube(3.0), 1.0, &FAMILY ).unwrap();
+        assert_eq!( v.size, (3, 3, 3) );
+        assert_eq!( v.cells.len(), 27 );
+        assert!( v.cells.iter().all( |(_, fit)| *fit == CellFit::Cube ) );
+
+        // scale counts blocks per unit of the mesh
+        let v = voxelize( &cube(3.0), 2.0, &FAMILY ).unwrap();
+        assert_eq!( v.size, (6, 6, 6) );
+        assert_eq!( v.cells.len(), 216 );
+    }
+
+    #[test]
+    fn wedges_pick_slopes() {
+        for (a, b) in [ (1, 2), (0, 1), (0, 2) ] {
+            for flip in flips() {
+                let (tris, normal) = wedge( a, b, flip );
+                let v = voxelize( &tris, 1.0, &FAMILY ).unwrap();
+                assert_eq!( v.cells.len(), 1 );
+                match v.cells[0] {
+                    ( (0, 0, 0), CellFit::Slope(exparam) ) => {
+                        let quat = rotation_group::generate_quat_from_re( rotation_group::num_to_re(exparam).unwrap() );
+                        let n = quat.rotate_vector( Vector3::new( 0.0, 1.0, 1.0 ).normalize() );
+                        assert!( n.dot(normal) > 0.99, "wedge facing {:?} got slope {}", normal, exparam );
+                    }
+                    other => panic!( "wedge facing {:?} got {:?}", normal, other ),
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn tetrahedra_pick_corners() {
+        for flip in flips() {
+            let (tris, normal) = corner(flip);
+            // not every corner can be built, see generate_quat_from_rv
+            let buildable = candidates(&FAMILY).iter().any( |c| matches!( c.fit, Some( CellFit::Corner(_) ) ) && c.normal.unwrap().dot(normal) > 0.99 );
+            let v = voxelize( &tris, 1.0, &FAMILY ).unwrap();
+            match v.cells.first() {
+                Some( &( (0, 0, 0), CellFit::Corner(exparam) ) ) => {
+                    let quat = rotation_group::generate_quat_from_rv( rotation_group::num_to_rv(exparam).unwrap() );
+                    let n = quat.rotate_vector( Vector3::new( 1.0, 1.0, 1.0 ).normalize() );
+                    assert!( n.dot(normal) > 0.99, "corner facing {:?} got {}", normal, exparam );
+                }
+                other => assert!( !buildable, "corner facing {:?} got {:?}", normal, other ),
+            }
+            assert!( v.cells.len() <= 1 );
+        }
+    }
+
+    #[test]
+    fn empty_mesh() {
+        assert!( voxelize( &[], 1.0, &FAMILY ).is_err() );
+    }
+}
@@ -0,0 +1,119 @@
+use std::io::Error;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use crate::engine::camera;
+use crate::engine::chunk;
+use crate::engine::world_gen;
+
+// state stored when a game world is saved
+#[derive(Serialize, Deserialize)]
+pub struct WorldSavestate {
+    pub chunk_manager: chunk::ChunkManager,
+    pub block_select: u16,
+    pub camera: camera::Camera,
+    // block name for each ID used in this save, so IDs can be remapped when block.toml changes
+    #[serde(default)]
+    pub block_table: Vec<String>,
+    #[serde(skip)]
+    pub unknown_blocks: Vec<String>,
+    // bumped on every save, edit journals record which save they build on
+    #[serde(default)]
+    pub save_id: u64,
+}
+
+impl WorldSavestate {
+    pub fn new(size: usize) -> WorldSavestate {
+        Self::generate( &world_gen::WorldGenSettings::new(size) )
+    }
+
+    pub fn generate(settings: &world_gen::WorldGenSettings) -> WorldSavestate {
+        let chunk_manager = crate::engine::chunk::ChunkManager::generate(settings);
+        let block_select = 1;
+        let camera = camera::Camera::new(settings.spawn_position(), cgmath::Deg(90.0), cgmath::Deg(-20.0));
+
+        Self {
+            chunk_manager,
+            block_select,
+            camera,
+            block_table: Vec::<String>::new(),
+            unknown_blocks: Vec::<String>::new(),
+            save_id: 0,
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.chunk_manager.size
+    }
+
+    // rewrites every stored block ID to match the current name table.
+    // blocks that no longer exist keep an ID past the end of the registry so they survive a re-save,
+    // and their names are collected in unknown_blocks.
+    pub fn remap_blocks(&mut self, current: &[String]) -> Result<(), Error> {
+        self.unknown_blocks.clear();
+
+        // saves from before the name table existed used the registry order directly
+        if self.block_table.is_empty() {
+            self.block_table = current.to_vec();
+        }
+
+        if self.block_table == *current {
+            if self.block_select as usize >= current.len() || self.block_select == 0 {
+                self.block_select = 1;
+            }
+            return Ok(());
+        }
+
+        // every chunk has to be rewritten with the new IDs, including ones not streamed in yet
+        self.chunk_manager.load_all()?;
+
+        let mut counts = vec![ 0_usize; self.block_table.len() ];
+        let mut out_of_table = false;
+        for ch in self.chunk_manager.data.iter() {
+            for bi in ch.data.iter() {
+                match counts.get_mut( bi.blockdef as usize ) {
+                    Some(count) => { *count += 1; }
+                    None => { out_of_table = true; }
+                }
+            }
+        }
+        if out_of_table {
+            self.unknown_blocks.push( "(IDs missing from the save's block table)".to_string() );
+        }
+
+        let mut new_table = current.to_vec();
+        let mut mapping = Vec::<u16>::new();
+        for (old_id, name) in self.block_table.iter().enumerate() {
+            if let Some(new_id) = current.iter().position( |n| n == name ) {
+                mapping.push( new_id as u16 );
+            } else if counts[old_id] > 0 {
+                new_table.push( name.clone() );
+                mapping.push( ( new_table.len() - 1 ) as u16 );
+                self.unknown_blocks.push( name.clone() );
+            } else {
+                mapping.push(0);
+            }
+        }
+
+        let identity = new_table == self.block_table && !out_of_table;
+        if !identity {
+            for ch in self.chunk_manager.data.iter_mut() {
+                for bi in ch.data.iter_mut() {
+                    bi.blockdef = *mapping.get( bi.blockdef as usize ).unwrap_or(&0);
+                }
+                ch.dirty = true;
+                ch.unsaved = true;
+            }
+        }
+
+        self.block_select = *mapping.get( self.block_select as usize ).unwrap_or(&1);
+        if self.block_select as usize >= current.len() || self.block_select == 0 {
+            self.block_select = 1;
+        }
+        self.block_table = new_table;
+        Ok(())
+    }
+}
@@ -0,0 +1,173 @@
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use crate::engine::block::{BlockRegistry, BlockShapeRegistry};
+use crate::engine::block_data;
+use crate::engine::chunk::{BlockInstance, CHUNK_SIZE, WORLD_CHUNKS};
+use crate::engine::journal;
+use crate::engine::saves::{self, WorldSaver};
+use crate::engine::world::WorldSavestate;
+use crate::engine::world_format;
+
+// a box of block positions, both corners included
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockArea {
+    pub min: (usize, usize, usize),
+    pub max: (usize, usize, usize),
+}
+
+impl BlockArea {
+    pub fn new(a: (usize, usize, usize), b: (usize, usize, usize)) -> BlockArea {
+        BlockArea {
+            min: ( a.0.min(b.0), a.1.min(b.1), a.2.min(b.2) ),
+            max: ( a.0.max(b.0), a.1.max(b.1), a.2.max(b.2) ),
+        }
+    }
+
+    pub fn size(&self) -> (usize, usize, usize) {
+        ( self.max.0 - self.min.0 + 1, self.max.1 - self.min.1 + 1, self.max.2 - self.min.2 + 1 )
+    }
+
+    pub fn contains(&self, pos: (usize, usize, usize)) -> bool {
+        pos.0 >= self.min.0 && pos.0 <= self.max.0 && pos.1 >= self.min.1 && pos.1 <= self.max.1 && pos.2 >= self.min.2 && pos.2 <= self.max.2
+    }
+}
+
+// a cube block together with the slope and corner blocks of the same material, which
+// block.toml names after the cube with _45s and _cn30 on the end
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlockFamily {
+    pub cube: u16,
+    pub slope: Option<u16>,
+    pub corner: Option<u16>,
+}
+
+// a saved world opened outside the game, with every chunk loaded and block IDs matching the current registry.
+// the game must not have the same world open while it is edited.
+pub struct WorldEditor {
+    pub world_dir: PathBuf,
+    pub world: WorldSavestate,
+    pub registry: BlockRegistry,
+    pub shape_registry: BlockShapeRegistry,
+    pub config: block_data::Config,
+    changed: usize,
+}
+
+impl WorldEditor {
+    pub fn open(world_dir: &Path) -> Result<WorldEditor, Error> {
+        if let Some(version) = world_format::read_version(world_dir)? {
+            if version > world_format::FORMAT_VERSION {
+                return Err( Error::new( std::io::ErrorKind::InvalidData, format!( "the world was saved in format version {}, which is newer than this tool supports", version ) ) );
+            }
+        }
+
+        let config = block_data::load_block_config( PathBuf::from(block_data::BLOCK_DATA_PATH) )?;
+        let (registry, shape_registry) = block_data::load_block_registry( PathBuf::from(block_data::BLOCK_DATA_PATH) )?;
+
+        let mut world = world_format::read_world_file(world_dir)?;
+        world.remap_blocks( &registry.name_table() )?;
+        journal::replay( world_dir, world.save_id, &mut world.chunk_manager )?;
+        world.chunk_manager.load_all()?;
+
+        Ok( WorldEditor{ world_dir: world_dir.to_path_buf(), world, registry, shape_registry, config, changed: 0 } )
+    }
+
+    // blocks along each side of the world
+    pub fn width(&self) -> usize {
+        CHUNK_SIZE * WORLD_CHUNKS[ self.world.size() ]
+    }
+
+    pub fn whole_world(&self) -> BlockArea {
+        let last = self.width() - 1;
+        BlockArea::new( (0, 0, 0), (last, last, last) )
+    }
+
+    pub fn in_world(&self, pos: (i64, i64, i64)) -> Option<(usize, usize, usize)> {
+        let width = self.width() as i64;
+        if pos.0 < 0 || pos.1 < 0 || pos.2 < 0 || pos.0 >= width || pos.1 >= width || pos.2 >= width {
+            None
+        } else {
+            Some( ( pos.0 as usize, pos.1 as usize, pos.2 as usize ) )
+        }
+    }
+
+    pub fn get(&self, pos: (usize, usize, usize)) -> BlockInstance {
+        *self.world.chunk_manager.get_block(pos)
+    }
+
+    // blocks outside the world are silently dropped, returns whether the block was placed
+    pub fn set(&mut self, pos: (i64, i64, i64), blockdef: u16, exparam: u8) -> bool {
+        match self.in_world(pos) {
+            Some(pos) => {
+                let bi = self.world.chunk_manager.get_mut_block(pos);
+                bi.blockdef = blockdef;
+                bi.exparam = exparam;
+                self.changed += 1;
+                true
+            }
+            None => false,
+        }
+    }
+
+    // how many blocks were placed since the world was opened
+    pub fn changed(&self) -> usize {
+        self.changed
+    }
+
+    // the block with this name, for tools that let the user pick blocks
+    pub fn block_id(&self, name: &str) -> Result<u16, Error> {
+        self.registry.get_id(name).ok_or( Error::new( std::io::ErrorKind::NotFound, format!( "there is no block called '{}' in block.toml", name ) ) )
+    }
+
+    // the block called name with the given shape, None if there is none or its shape differs
+    fn block_with_shape(&self, name: &str, shape_name: &str) -> Option<u16> {
+        let bp = self.config.block.iter().find( |bp| bp.name == name )?;
+        if bp.shape_name == shape_name {
+            self.registry.get_id(name)
+        } else {
+            None
+        }
+    }
+
+    // the family of a cube block, slope and corner can be given by name when they don't follow the naming
+    pub fn block_family(&self, cube: &str, slope: Option<&str>, corner: Option<&str>) -> Result<BlockFamily, Error> {
+        let not_shape = |name: &str, shape_name: &str| Error::new( std::io::ErrorKind::InvalidInput, format!( "'{}' is not a {} block in block.toml", name, shape_name ) );
+        let cube_id = self.block_with_shape( cube, "CubeStatic" ).ok_or( not_shape( cube, "CubeStatic" ) )?;
+        let slope = match slope {
+            Some(name) => Some( self.block_with_shape( name, "Slope" ).ok_or( not_shape( name, "Slope" ) )? ),
+            None => self.block_with_shape( &format!( "{}_45s", cube ), "Slope" ),
+        };
+        let corner = match corner {
+            Some(name) => Some( self.block_with_shape( name, "CornerSlope" ).ok_or( not_shape( name, "CornerSlope" ) )? ),
+            None => self.block_with_shape( &format!( "{}_cn30", cube ), "CornerSlope" ),
+        };
+        Ok( BlockFamily{ cube: cube_id, slope, corner } )
+    }
+
+    // IDs of the full cube blocks, the only ones that can stand in for a voxel on their own
+    pub fn cube_blocks(&self) -> Vec<u16> {
+        ( 1..self.registry.get_num_blocks() ).filter( |id| {
+            let block = self.registry.get(*id).unwrap();
+            self.shape_registry.get(block.shape_id).map( |s| s.is_full_cube() ).unwrap_or(false)
+        } ).collect()
+    }
+
+    // saves like the game does: backs up the old save first, and moves past any edit journal
+    // so its edits aren't replayed over the new save
+    pub fn save(&mut self) -> Result<(), Error> {
+        WorldSaver::make_backup(&self.world_dir)?;
+        self.world.save_id += 1;
+        self.world.chunk_manager.save_unsaved()?;
+
+        let mut savebuf = self.world_dir.clone();
+        savebuf.push(world_format::SAVE_FILE_NAME);
+        saves::write_file_atomic( &savebuf, &world_format::encode_world(&self.world)? )?;
+
+        let mut pklbuf = self.world_dir.clone();
+        pklbuf.push(world_format::LEGACY_SAVE_FILE_NAME);
+        if pklbuf.exists() {
+            std::fs::remove_file(pklbuf)?;
+        }
+        Ok(())
+    }
+}
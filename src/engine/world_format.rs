@@ -0,0 +1,388 @@
+use std::io::{
+    Error,
+    ErrorKind,
+    Read,
+};
+use std::path::Path;
+
+use ndarray::Array3;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use crate::engine::invalid;
+use crate::engine::camera;
+use crate::engine::chunk::{
+    BlockInstance,
+    Chunk,
+    ChunkManager,
+    CHUNK_SIZE,
+    WORLD_CHUNKS
+};
+use crate::engine::region;
+use crate::engine::world::WorldSavestate;
+
+// Save file layout (all integers little endian):
+//   magic "SGRW", format version u16, reserved u16
+//   metadata length u32, metadata (pickled WorldMeta)
+// version 1 files follow this with the chunks themselves:
+//   chunk count u32, then for each stored chunk:
+//     chunk index x, y, z as u16, payload length u32, payload
+// from version 2 the chunks live in region files instead, see region.rs.
+// A chunk payload is a run-length list of ( run length u16, blockdef u16, exparam u8, light u8 ).
+// Chunks that are entirely air are not stored at all.
+
+pub const SAVE_FILE_NAME: &str = "world.sgrw";
+pub const LEGACY_SAVE_FILE_NAME: &str = "world_savestate.pkl";
+pub const FORMAT_VERSION: u16 = 2;
+const MAGIC: &[u8; 4] = b"SGRW";
+
+#[derive(Serialize)]
+struct WorldMetaRef<'a> {
+    size: usize,
+    block_select: u16,
+    camera: &'a camera::Camera,
+    block_table: &'a Vec<String>,
+    save_id: u64,
+}
+
+#[derive(Deserialize)]
+struct WorldMeta {
+    size: usize,
+    block_select: u16,
+    camera: camera::Camera,
+    block_table: Vec<String>,
+    #[serde(default)]
+    save_id: u64,
+}
+
+fn read_u8(rd: &mut &[u8]) -> Result<u8, Error> {
+    let mut buf = [0_u8; 1];
+    rd.read_exact(&mut buf)?;
+    Ok(buf[0])
+}
+
+fn read_u16(rd: &mut &[u8]) -> Result<u16, Error> {
+    let mut buf = [0_u8; 2];
+    rd.read_exact(&mut buf)?;
+    Ok(u16::from_le_bytes(buf))
+}
+
+fn read_u32(rd: &mut &[u8]) -> Result<u32, Error> {
+    let mut buf = [0_u8; 4];
+    rd.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_bytes<'a>(rd: &mut &'a [u8], len: usize) -> Result<&'a [u8], Error> {
+    if rd.len() < len {
+        return Err( Error::from(ErrorKind::UnexpectedEof) );
+    }
+    let (head, tail) = rd.split_at(len);
+    *rd = tail;
+    Ok(head)
+}
+
+pub fn is_empty_chunk(chunk: &Chunk) -> bool {
+    chunk.data.iter().all( |bi| bi.blockdef == 0 )
+}
+
+pub fn encode_chunk(chunk: &Chunk) -> Vec<u8> {
+    let mut out = Vec::<u8>::new();
+    let push_run = |out: &mut Vec<u8>, run: u16, bi: &BlockInstance| {
+        out.extend_from_slice( &run.to_le_bytes() );
+        out.extend_from_slice( &bi.blockdef.to_le_bytes() );
+        out.push( bi.exparam );
+        out.push( bi.light );
+    };
+
+    let mut iter = chunk.data.iter();
+    if let Some(first) = iter.next() {
+        let mut current = *first;
+        let mut run: u16 = 1;
+        for bi in iter {
+            if bi.blockdef == current.blockdef && bi.exparam == current.exparam && bi.light == current.light {
+                run += 1;
+            } else {
+                push_run(&mut out, run, &current);
+                current = *bi;
+                run = 1;
+            }
+        }
+        push_run(&mut out, run, &current);
+    }
+    out
+}
+
+pub fn decode_chunk(payload: &[u8]) -> Result<Chunk, Error> {
+    let mut rd = payload;
+    let total = CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE;
+    let mut blocks = Vec::<BlockInstance>::with_capacity(total);
+    while !rd.is_empty() {
+        let run = read_u16(&mut rd)? as usize;
+        let bi = BlockInstance{
+            blockdef: read_u16(&mut rd)?,
+            exparam: read_u8(&mut rd)?,
+            light: read_u8(&mut rd)?,
+        };
+        // a damaged payload could otherwise claim far more blocks than a chunk holds
+        if blocks.len() + run > total {
+            return Err( invalid( format!("bad chunk payload: more than {} blocks", total) ) );
+        }
+        blocks.extend( std::iter::repeat(bi).take(run) );
+    }
+
+    let data = Array3::from_shape_vec( (CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE), blocks ).map_err( |e| invalid( format!("bad chunk payload: {}", e) ) )?;
+    let mut chunk = Chunk::new();
+    chunk.data = data;
+    Ok(chunk)
+}
+
+// encodes the world metadata, chunks are saved separately with ChunkManager::save_unsaved
+pub fn encode_world(world: &WorldSavestate) -> Result<Vec<u8>, Error> {
+    let mut out = Vec::<u8>::new();
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice( &FORMAT_VERSION.to_le_bytes() );
+    out.extend_from_slice( &0_u16.to_le_bytes() );
+
+    let meta = WorldMetaRef{
+        size: world.size(),
+        block_select: world.block_select,
+        camera: &world.camera,
+        block_table: &world.block_table,
+        save_id: world.save_id,
+    };
+    let meta_bytes = serde_pickle::to_vec( &meta, Default::default() ).map_err( |e| invalid( e.to_string() ) )?;
+    out.extend_from_slice( &(meta_bytes.len() as u32).to_le_bytes() );
+    out.extend_from_slice( &meta_bytes );
+
+    Ok(out)
+}
+
+pub fn decode_world(bytes: &[u8]) -> Result<WorldSavestate, Error> {
+    let mut rd = bytes;
+    if read_bytes(&mut rd, 4)? != MAGIC {
+        return Err( invalid( "not a world save file".to_string() ) );
+    }
+    let version = read_u16(&mut rd)?;
+    if version > FORMAT_VERSION {
+        return Err( invalid( format!("save format version {} is newer than this game supports ({})", version, FORMAT_VERSION) ) );
+    }
+    let _reserved = read_u16(&mut rd)?;
+
+    let meta_len = read_u32(&mut rd)? as usize;
+    let meta: WorldMeta = serde_pickle::from_slice( read_bytes(&mut rd, meta_len)?, Default::default() ).map_err( |e| invalid( e.to_string() ) )?;
+    if meta.size >= WORLD_CHUNKS.len() {
+        return Err( invalid( format!("bad world size {}", meta.size) ) );
+    }
+
+    let world_chunks = WORLD_CHUNKS[meta.size];
+    let mut chunk_manager = ChunkManager{
+        size: meta.size,
+        data: Array3::from_shape_fn( (world_chunks, world_chunks, world_chunks), |_| Chunk::new() ),
+        region_dir: None,
+    };
+
+    if version >= 2 {
+        // placeholders until they are streamed in from the region files
+        for ch in chunk_manager.data.iter_mut() {
+            ch.loaded = false;
+            ch.unsaved = false;
+            ch.dirty = false;
+        }
+        return Ok(WorldSavestate{
+            chunk_manager,
+            block_select: meta.block_select,
+            camera: meta.camera,
+            block_table: meta.block_table,
+            unknown_blocks: Vec::<String>::new(),
+            save_id: meta.save_id,
+        });
+    }
+
+    let count = read_u32(&mut rd)?;
+    for _ in 0..count {
+        let idx = ( read_u16(&mut rd)? as usize, read_u16(&mut rd)? as usize, read_u16(&mut rd)? as usize );
+        let len = read_u32(&mut rd)? as usize;
+        let payload = read_bytes(&mut rd, len)?;
+        let slot = chunk_manager.data.get_mut(idx).ok_or( invalid( format!("chunk index {:?} is outside the world", idx) ) )?;
+        *slot = decode_chunk(payload)?;
+    }
+
+    Ok(WorldSavestate{
+        chunk_manager,
+        block_select: meta.block_select,
+        camera: meta.camera,
+        block_table: meta.block_table,
+        unknown_blocks: Vec::<String>::new(),
+        save_id: meta.save_id,
+    })
+}
+
+// the format version of the save in a world directory, None for legacy or missing saves
+pub fn read_version(world_dir: &Path) -> Result<Option<u16>, Error> {
+    let mut pbuf = world_dir.to_path_buf();
+    pbuf.push(SAVE_FILE_NAME);
+    if !pbuf.exists() {
+        return Ok(None);
+    }
+    let mut header = [0_u8; 6];
+    std::fs::File::open(pbuf)?.read_exact(&mut header)?;
+    if &header[0..4] != MAGIC {
+        return Err( invalid( "not a world save file".to_string() ) );
+    }
+    Ok( Some( u16::from_le_bytes( [header[4], header[5]] ) ) )
+}
+
+// reads the world save in a world directory, falling back to the old pickled savestate.
+// worlds from older formats come back fully loaded and marked unsaved, so the next save moves them into region files.
+pub fn read_world_file(world_dir: &Path) -> Result<WorldSavestate, Error> {
+    let mut pbuf = world_dir.to_path_buf();
+    pbuf.push(SAVE_FILE_NAME);
+    let mut world: WorldSavestate = if pbuf.exists() {
+        let bytes = std::fs::read(pbuf)?;
+        decode_world(&bytes)?
+    } else {
+        let mut legacy = world_dir.to_path_buf();
+        legacy.push(LEGACY_SAVE_FILE_NAME);
+        let bytes = std::fs::read(legacy)?;
+        serde_pickle::from_slice(&bytes, Default::default()).map_err( |e| invalid( e.to_string() ) )?
+    };
+
+    world.chunk_manager.region_dir = Some( region::region_dir(world_dir) );
+    Ok(world)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::engine::test_dir;
+
+    fn same_blocks(a: &Chunk, b: &Chunk) -> bool {
+        a.data.iter().zip( b.data.iter() ).all( |(x, y)| x.blockdef == y.blockdef && x.exparam == y.exparam && x.light == y.light )
+    }
+
+    fn mixed_chunk() -> Chunk {
+        let mut chunk = Chunk::new();
+        for (i, bi) in chunk.data.iter_mut().enumerate() {
+            if i % 7 < 3 {
+                bi.blockdef = ( i % 5 ) as u16 + 1;
+                bi.exparam = ( i % 12 ) as u8;
+                bi.light = ( i % 256 ) as u8;
+            }
+        }
+        chunk
+    }
+
+    #[test]
+    fn chunk_round_trip_air() {
+        let chunk = Chunk::new();
+        assert!( is_empty_chunk(&chunk) );
+        let payload = encode_chunk(&chunk);
+        // one run covers the whole chunk
+        assert_eq!( payload.len(), 6 );
+        assert!( same_blocks( &chunk, &decode_chunk(&payload).unwrap() ) );
+    }
+
+    #[test]
+    fn chunk_round_trip_mixed() {
+        let chunk = mixed_chunk();
+        assert!( !is_empty_chunk(&chunk) );
+        assert!( same_blocks( &chunk, &decode_chunk( &encode_chunk(&chunk) ).unwrap() ) );
+    }
+
+    #[test]
+    fn chunk_payload_too_long() {
+        let mut payload = encode_chunk( &Chunk::new() );
+        payload.extend_from_slice( &encode_chunk( &Chunk::new() ) );
+        assert!( decode_chunk(&payload).is_err() );
+
+        // a run past the end of the chunk is refused before it is expanded
+        let mut huge = Vec::new();
+        for _ in 0..1000 {
+            huge.extend_from_slice( &[ 0xff, 0xff, 1, 0, 0, 255 ] );
+        }
+        assert!( decode_chunk(&huge).is_err() );
+    }
+
+    #[test]
+    fn chunk_payload_too_short() {
+        let payload = encode_chunk( &mixed_chunk() );
+        assert!( decode_chunk( &payload[ ..payload.len() - 6 ] ).is_err() );
+        assert!( decode_chunk( &payload[ ..payload.len() - 1 ] ).is_err() );
+    }
+
+    #[test]
+    fn region_cut_short_reads_as_air() {
+        let dir = test_dir("region_cut_short");
+        let rdir = region::region_dir(&dir);
+        std::fs::create_dir_all(&rdir).unwrap();
+        // a save that crashed while creating the file
+        std::fs::write( region::region_path( &rdir, (0, 0, 0) ), [ 0_u8; 100 ] ).unwrap();
+        assert!( region::read_chunk( &rdir, (1, 2, 3) ).unwrap().is_none() );
+
+        let chunk = mixed_chunk();
+        region::write_chunks( &rdir, &vec![ ( (1, 2, 3), &chunk ) ] ).unwrap();
+        assert!( same_blocks( &chunk, &region::read_chunk( &rdir, (1, 2, 3) ).unwrap().unwrap() ) );
+        assert!( region::read_chunk( &rdir, (1, 2, 4) ).unwrap().is_none() );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn world_round_trip() {
+        let dir = test_dir("world_round_trip");
+        let mut world = WorldSavestate::new(0);
+        world.block_table = vec![ "air".to_string(), "st_100".to_string() ];
+        world.block_select = 1;
+        world.save_id = 7;
+        world.chunk_manager.data[ (1, 2, 3) ] = mixed_chunk();
+        world.chunk_manager.region_dir = Some( region::region_dir(&dir) );
+        world.chunk_manager.save_unsaved().unwrap();
+
+        let mut pbuf = dir.clone();
+        pbuf.push(SAVE_FILE_NAME);
+        std::fs::write( &pbuf, encode_world(&world).unwrap() ).unwrap();
+        assert_eq!( read_version(&dir).unwrap(), Some(FORMAT_VERSION) );
+
+        let mut read = read_world_file(&dir).unwrap();
+        assert_eq!( read.size(), world.size() );
+        assert_eq!( read.block_select, 1 );
+        assert_eq!( read.save_id, 7 );
+        assert_eq!( read.block_table, world.block_table );
+        assert_eq!( read.camera.position, world.camera.position );
+
+        read.chunk_manager.load_all().unwrap();
+        for (a, b) in world.chunk_manager.data.iter().zip( read.chunk_manager.data.iter() ) {
+            assert!( same_blocks(a, b) );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn legacy_save_migrates() {
+        let dir = test_dir("legacy_save_migrates");
+        let mut world = WorldSavestate::new(0);
+        world.chunk_manager.data[ (0, 1, 0) ] = mixed_chunk();
+        let mut legacy = dir.clone();
+        legacy.push(LEGACY_SAVE_FILE_NAME);
+        std::fs::write( &legacy, serde_pickle::to_vec( &world, Default::default() ).unwrap() ).unwrap();
+
+        // read directly it comes back fully loaded and waiting to be saved
+        assert_eq!( read_version(&dir).unwrap(), None );
+        let read = read_world_file(&dir).unwrap();
+        assert!( read.chunk_manager.data.iter().all( |ch| ch.loaded && ch.unsaved ) );
+
+        crate::engine::saves::WorldSaver::migrate_world(&dir).unwrap();
+        assert!( !legacy.exists() );
+        assert_eq!( read_version(&dir).unwrap(), Some(FORMAT_VERSION) );
+
+        let mut migrated = read_world_file(&dir).unwrap();
+        migrated.chunk_manager.load_all().unwrap();
+        for (a, b) in world.chunk_manager.data.iter().zip( migrated.chunk_manager.data.iter() ) {
+            assert!( same_blocks(a, b) );
+        }
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,151 @@
+use ndarray::Array3;
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+use crate::engine::chunk::{
+    BlockInstance,
+    Chunk,
+    CHUNK_SIZE,
+    WORLD_CHUNKS
+};
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorldGenerator {
+    #[default]
+    Flat,
+    Empty,
+    Hills,
+}
+
+impl WorldGenerator {
+    pub const ALL: [WorldGenerator; 3] = [ WorldGenerator::Flat, WorldGenerator::Empty, WorldGenerator::Hills ];
+
+    pub fn pretty_name(&self) -> &'static str {
+        match self {
+            WorldGenerator::Flat => "Flat",
+            WorldGenerator::Empty => "Empty",
+            WorldGenerator::Hills => "Hills",
+        }
+    }
+}
+
+pub const SIZE_NAMES: [&str; 3] = [ "Small", "Medium", "Large" ];
+
+#[derive(Clone, Debug)]
+pub struct WorldGenSettings {
+    pub size: usize,
+    pub seed: u32,
+    pub generator: WorldGenerator,
+}
+
+impl WorldGenSettings {
+    pub fn new(size: usize) -> WorldGenSettings {
+        Self {
+            size,
+            seed: 0,
+            generator: WorldGenerator::Flat,
+        }
+    }
+
+    // height of the terrain surface in blocks, i.e. the first air block of the column
+    pub fn surface_height(&self, x: usize, z: usize) -> usize {
+        let world_height = CHUNK_SIZE * WORLD_CHUNKS[self.size];
+        match self.generator {
+            WorldGenerator::Flat => 2 * CHUNK_SIZE,
+            WorldGenerator::Empty => 0,
+            WorldGenerator::Hills => {
+                let mut h = 0.0;
+                let mut amplitude = 0.5;
+                let mut scale = 1.0 / 48.0;
+                for octave in 0..4 {
+                    h += amplitude * value_noise( x as f32 * scale, z as f32 * scale, self.seed.wrapping_add(octave) );
+                    amplitude *= 0.5;
+                    scale *= 2.0;
+                }
+                let base = 1.5 * CHUNK_SIZE as f32;
+                let height = base + h * 2.5 * CHUNK_SIZE as f32;
+                std::cmp::min( height as usize, world_height - 1 )
+            }
+        }
+    }
+
+    pub fn generate_chunk(&self, loc: (usize, usize, usize)) -> Chunk {
+        let air = BlockInstance{ blockdef: 0, exparam: 0, light: 255 };
+        let ground = BlockInstance{ blockdef: 1, exparam: 0, light: 255 };
+
+        let mut heights = [[0_usize; CHUNK_SIZE]; CHUNK_SIZE];
+        for x in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                heights[x][z] = self.surface_height( loc.0 * CHUNK_SIZE + x, loc.2 * CHUNK_SIZE + z );
+            }
+        }
+
+        let mut chunk = Chunk::from_blockinstance(air);
+        chunk.data = Array3::from_shape_fn( (CHUNK_SIZE, CHUNK_SIZE, CHUNK_SIZE), | pos: (usize, usize, usize) | -> BlockInstance {
+            if loc.1 * CHUNK_SIZE + pos.1 < heights[pos.0][pos.2] {
+                ground
+            } else {
+                air
+            }
+        });
+        chunk
+    }
+
+    // a camera position a little way above the ground near the middle of the world
+    pub fn spawn_position(&self) -> (f32, f32, f32) {
+        let world_width = CHUNK_SIZE * WORLD_CHUNKS[self.size];
+        let (x, z) = ( world_width / 2 - 1, world_width / 2 - 2 );
+        let y = std::cmp::max( self.surface_height(x, z) + 3, 3 );
+        ( x as f32, y as f32, z as f32 )
+    }
+}
+
+// seed text entered by the player: numbers are used as-is, anything else gets hashed
+pub fn seed_from_string(text: &str) -> u32 {
+    let trimmed = text.trim();
+    if trimmed.is_empty() {
+        let now = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap_or_default();
+        return now.subsec_nanos() ^ ( now.as_secs() as u32 );
+    }
+    if let Ok(num) = trimmed.parse::<u32>() {
+        return num;
+    }
+
+    // FNV-1a
+    let mut hash: u32 = 0x811c9dc5;
+    for byte in trimmed.bytes() {
+        hash ^= byte as u32;
+        hash = hash.wrapping_mul(0x01000193);
+    }
+    hash
+}
+
+fn lattice_value(x: i32, z: i32, seed: u32) -> f32 {
+    let mut h = seed ^ (x as u32).wrapping_mul(0x27d4eb2d) ^ (z as u32).wrapping_mul(0x165667b1);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x85ebca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2ae35);
+    h ^= h >> 16;
+    h as f32 / u32::MAX as f32
+}
+
+fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    let (x0, z0) = ( x.floor(), z.floor() );
+    let (fx, fz) = ( x - x0, z - z0 );
+    let (sx, sz) = ( fx * fx * (3.0 - 2.0 * fx), fz * fz * (3.0 - 2.0 * fz) );
+    let (ix, iz) = ( x0 as i32, z0 as i32 );
+
+    let a = lattice_value(ix, iz, seed);
+    let b = lattice_value(ix + 1, iz, seed);
+    let c = lattice_value(ix, iz + 1, seed);
+    let d = lattice_value(ix + 1, iz + 1, seed);
+
+    let top = a + (b - a) * sx;
+    let bottom = c + (d - c) * sx;
+    top + (bottom - top) * sz
+}
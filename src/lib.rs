@@ -0,0 +1,5 @@
+pub mod engine;
+pub mod tool;
+
+#[cfg(feature = "client")]
+pub mod wctx;
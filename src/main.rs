@@ -1,6 +1,5 @@
 
-mod wctx;
-use wctx::run;
+use sgr_cubes::wctx::run;
 
 fn main() {
     pollster::block_on(run());
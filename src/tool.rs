@@ -0,0 +1,828 @@
+use std::collections::HashMap;
+use std::io::Error;
+use std::path::{Path, PathBuf};
+
+use crate::engine::block::{BlockRegistry, BlockShapeRegistry};
+use crate::engine::chunk::CHUNK_SIZE;
+use crate::engine::block_data;
+use crate::engine::region;
+use crate::engine::world::WorldSavestate;
+use crate::engine::world_format;
+use crate::engine::saves::{self, WorldSaver};
+use crate::engine::blueprint::{Blueprint, BlueprintBlock};
+use crate::engine::heightmap;
+use crate::engine::mesh_export;
+use crate::engine::mural;
+use crate::engine::palette::{self, BlockColors};
+use crate::engine::schematic::{self, BlockMapping};
+use crate::engine::vox;
+use crate::engine::voxelize;
+use crate::engine::world_edit::{BlockArea, WorldEditor};
+
+const USAGE: &str = "usage: sgr_cubes-tool <command> <world name or folder> [options]
+
+works on world saves without starting the game. run it from the game folder,
+block data is read from res/.
+
+commands:
+  stats <world>                   block counts, non-empty chunks and file sizes
+  validate <world>                look for unknown block IDs and bad rotations
+  convert <world>                 move an old save to the current format, backing it up first
+  backups list <world>            show the world's backups, newest first
+  backups prune <world> [--keep <count>]
+                                  delete all but the newest backups, default keeps 1
+  vox export <world> [--from <x,y,z> --to <x,y,z>] [--out <file>]
+                                  write blocks to a MagicaVoxel .vox file, default is every
+                                  block in the world into <world name>.vox
+  vox import <world> <file> [--at <x,y,z>]
+                                  build a MagicaVoxel model into the world out of the cube
+                                  blocks closest in colour, backing the world up first
+  mesh export <world> [--from <x,y,z> --to <x,y,z>] [--format glb|obj] [--out <file>]
+                                  write blocks as a textured mesh for other 3D programs, default
+                                  is every block in the world into <world name>.glb. OBJ files
+                                  get a .mtl and .png of the same name next to them
+  mesh import <world> <file> --block <name> [--slope <name>] [--corner <name>]
+              [--scale <blocks per unit>] [--at <x,y,z>]
+                                  fill a closed .obj, .gltf or .glb mesh with blocks, its lowest
+                                  corner at --at. sloped surfaces use the block's _45s and _cn30
+                                  variants unless others are given, the world is backed up first
+  heightmap import <world> <file> --layers <block>:<depth>,...,<block> [--at <x,y,z>]
+              [--base <blocks>] [--height <blocks>] [--columns <count>]
+                                  build terrain from a grayscale picture, one column per pixel
+                                  with its corner at --at. black is --base blocks tall (default 1),
+                                  white --base + --height (default 32). --columns resizes the
+                                  picture first. layers go from the surface down, the last one
+                                  fills the rest, and sloped ground uses each layer's _45s and
+                                  _cn30 blocks. the world is backed up first
+  mural import <world> <image> [--at <x,y,z>] [--plane wall-x|wall-z|floor]
+              [--blocks <name>,...] [--columns <count>] [--dither] [--blueprint]
+                                  build a picture out of the blocks closest to each pixel's colour,
+                                  its lowest corner at --at. --blocks limits which blocks are used,
+                                  default is every cube block. --dither spreads out what each block
+                                  misses by. --blueprint only plans it, the game shows it as ghost
+                                  blocks to build over; otherwise the world is backed up first
+  schematic import <world> <file> [--at <x,y,z>] [--mapping <file>] [--blueprint]
+                                  build a WorldEdit .schem or Litematica .litematic file, its lowest
+                                  corner at --at. Minecraft blocks are swapped for ours by the
+                                  table in res/data/minecraft_blocks.toml unless --mapping gives
+                                  another, and blocks it doesn't cover are listed and left out.
+                                  --blueprint only plans it; otherwise the world is backed up first
+  blueprint clear <world>         remove every planned block from the world's blueprint";
+
+// how many problem positions validate prints per kind of problem
+const MAX_LISTED: usize = 10;
+
+fn load_registries() -> Result<(BlockRegistry, BlockShapeRegistry), Error> {
+    block_data::load_block_registry( PathBuf::from(block_data::BLOCK_DATA_PATH) )
+}
+
+// the save exactly as stored, IDs still in the save's own block table
+fn read_raw_world(world_dir: &Path) -> Result<WorldSavestate, Error> {
+    if let Some(version) = world_format::read_version(world_dir)? {
+        if version > world_format::FORMAT_VERSION {
+            return Err( Error::new( std::io::ErrorKind::InvalidData, format!( "the world was saved in format version {}, this tool supports up to {}", version, world_format::FORMAT_VERSION ) ) );
+        }
+    }
+    let mut world = world_format::read_world_file(world_dir)?;
+    world.chunk_manager.load_all()?;
+    Ok(world)
+}
+
+// the save's block table, falling back to the current registry order for saves from before it existed
+fn block_table(world: &WorldSavestate, registry: &BlockRegistry) -> Vec<String> {
+    if world.block_table.is_empty() {
+        registry.name_table()
+    } else {
+        world.block_table.clone()
+    }
+}
+
+fn dir_size(path: &Path) -> u64 {
+    if path.is_dir() {
+        fs_extra::dir::get_size(path).unwrap_or(0)
+    } else {
+        std::fs::metadata(path).map( |m| m.len() ).unwrap_or(0)
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    if bytes >= 1024 * 1024 {
+        format!( "{:.1} MiB", bytes as f64 / ( 1024.0 * 1024.0 ) )
+    } else if bytes >= 1024 {
+        format!( "{:.1} KiB", bytes as f64 / 1024.0 )
+    } else {
+        format!( "{} B", bytes )
+    }
+}
+
+fn stats(world_dir: &Path) -> Result<i32, Error> {
+    let info = saves::WorldInfo::read(world_dir)?;
+    let world = read_raw_world(world_dir)?;
+    let (registry, _) = load_registries()?;
+    let table = block_table(&world, &registry);
+
+    let mut counts = HashMap::<u16, usize>::new();
+    let mut non_empty = 0;
+    for ch in world.chunk_manager.data.iter() {
+        if !world_format::is_empty_chunk(ch) {
+            non_empty += 1;
+        }
+        for bi in ch.data.iter() {
+            *counts.entry(bi.blockdef).or_insert(0) += 1;
+        }
+    }
+
+    println!( "{} ({})", info.name, world_dir.display() );
+    println!( "size {}, {} chunks of which {} are not empty", world.size(), world.chunk_manager.data.len(), non_empty );
+
+    let mut sorted: Vec<(u16, usize)> = counts.into_iter().filter( |(id, _)| *id != 0 ).collect();
+    sorted.sort_by( |a, b| b.1.cmp(&a.1).then( a.0.cmp(&b.0) ) );
+    println!();
+    println!("blocks:");
+    for (id, count) in sorted {
+        let label = match table.get(id as usize) {
+            Some(name) => match registry.get_id(name).and_then( |rid| registry.get(rid) ) {
+                Some(block) => block.pretty_name.clone(),
+                None => format!( "{} (not in block.toml)", name ),
+            },
+            None => format!( "ID {} (not in the save's block table)", id ),
+        };
+        println!( "  {:>10}  {}", count, label );
+    }
+
+    println!();
+    println!("files:");
+    let mut total = 0;
+    for name in [ "info.toml", world_format::SAVE_FILE_NAME, world_format::LEGACY_SAVE_FILE_NAME, saves::PREVIEW_FILE_NAME, region::REGION_DIR_NAME, saves::BACKUP_DIR_NAME ] {
+        let mut pbuf = world_dir.to_path_buf();
+        pbuf.push(name);
+        if pbuf.exists() {
+            let size = dir_size(&pbuf);
+            total += size;
+            println!( "  {:>10}  {}", format_bytes(size), name );
+        }
+    }
+    println!( "  {:>10}  total", format_bytes(total) );
+    Ok(0)
+}
+
+fn validate(world_dir: &Path) -> Result<i32, Error> {
+    let world = read_raw_world(world_dir)?;
+    let (registry, shape_registry) = load_registries()?;
+    let table = block_table(&world, &registry);
+
+    // save ID to current block, None for names block.toml no longer has
+    let resolved: Vec<Option<u16>> = table.iter().map( |name| registry.get_id(name) ).collect();
+
+    let mut bad_ids = Vec::<((usize, usize, usize), u16)>::new();
+    let mut unknown = HashMap::<String, usize>::new();
+    let mut bad_exparams = Vec::<((usize, usize, usize), String, u8)>::new();
+    let (mut bad_id_count, mut bad_exparam_count) = (0, 0);
+
+    for ( (cx, cy, cz), ch ) in world.chunk_manager.data.indexed_iter() {
+        for ( (bx, by, bz), bi ) in ch.data.indexed_iter() {
+            let pos = ( cx * CHUNK_SIZE + bx, cy * CHUNK_SIZE + by, cz * CHUNK_SIZE + bz );
+            match resolved.get( bi.blockdef as usize ) {
+                None => {
+                    bad_id_count += 1;
+                    if bad_ids.len() < MAX_LISTED {
+                        bad_ids.push( (pos, bi.blockdef) );
+                    }
+                }
+                Some(None) => {
+                    *unknown.entry( table[ bi.blockdef as usize ].clone() ).or_insert(0) += 1;
+                }
+                Some( Some(id) ) => {
+                    let block = registry.get(*id).unwrap();
+                    let shape = shape_registry.get(block.shape_id).unwrap();
+                    if !shape.valid_exparam(bi.exparam) {
+                        bad_exparam_count += 1;
+                        if bad_exparams.len() < MAX_LISTED {
+                            bad_exparams.push( (pos, block.pretty_name.clone(), bi.exparam) );
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    if bad_id_count > 0 {
+        println!( "{} blocks have IDs missing from the save's block table:", bad_id_count );
+        for (pos, id) in bad_ids.iter() {
+            println!( "  ID {} at {:?}", id, pos );
+        }
+    }
+    if !unknown.is_empty() {
+        // not an error, the game keeps these blocks around until block.toml has them again
+        println!("blocks that block.toml doesn't define:");
+        for (name, count) in unknown.iter() {
+            println!( "  {} x{}", name, count );
+        }
+    }
+    if bad_exparam_count > 0 {
+        println!( "{} blocks have a rotation their shape doesn't support:", bad_exparam_count );
+        for (pos, name, exparam) in bad_exparams.iter() {
+            println!( "  {} with exparam {} at {:?}", name, exparam, pos );
+        }
+    }
+
+    if bad_id_count == 0 && bad_exparam_count == 0 {
+        println!("no problems found");
+        Ok(0)
+    } else {
+        Ok(1)
+    }
+}
+
+fn convert(world_dir: &Path) -> Result<i32, Error> {
+    match world_format::read_version(world_dir)? {
+        Some(version) if version == world_format::FORMAT_VERSION => {
+            println!( "already in format version {}", version );
+            return Ok(0);
+        }
+        Some(version) => println!( "converting from format version {}", version ),
+        None => println!("converting from the pickled savestate"),
+    }
+
+    WorldSaver::make_backup(world_dir)?;
+    WorldSaver::migrate_world(world_dir)?;
+    println!( "now in format version {}, the old save is in the newest backup", world_format::FORMAT_VERSION );
+    Ok(0)
+}
+
+fn list_backups(world_dir: &Path) -> Result<i32, Error> {
+    let backups = WorldSaver::list_backups(world_dir);
+    if backups.is_empty() {
+        println!("no backups");
+    }
+    for backup in backups.iter() {
+        println!( "  {:>10}  {}  {}", format_bytes( dir_size(&backup.path) ), backup.label(), backup.path.display() );
+    }
+    Ok(0)
+}
+
+fn prune_backups(world_dir: &Path, keep: usize) -> Result<i32, Error> {
+    let backups = WorldSaver::list_backups(world_dir);
+    let mut removed = 0;
+    for old in backups.iter().skip(keep) {
+        std::fs::remove_dir_all(&old.path)?;
+        removed += 1;
+    }
+    println!( "removed {} backups, kept {}", removed, backups.len() - removed );
+    Ok(0)
+}
+
+// the smallest box holding every block that isn't air, None for an empty area
+fn used_area(editor: &WorldEditor, area: BlockArea) -> Option<BlockArea> {
+    let mut bounds: Option<BlockArea> = None;
+    for x in area.min.0..=area.max.0 {
+        for y in area.min.1..=area.max.1 {
+            for z in area.min.2..=area.max.2 {
+                if editor.get( (x, y, z) ).blockdef == 0 {
+                    continue;
+                }
+                bounds = Some( match bounds {
+                    Some(b) => BlockArea::new( ( b.min.0.min(x), b.min.1.min(y), b.min.2.min(z) ), ( b.max.0.max(x), b.max.1.max(y), b.max.2.max(z) ) ),
+                    None => BlockArea::new( (x, y, z), (x, y, z) ),
+                } );
+            }
+        }
+    }
+    bounds
+}
+
+fn vox_export(world_dir: &Path, from: Option<(i64, i64, i64)>, to: Option<(i64, i64, i64)>, out: Option<PathBuf>) -> Result<i32, Error> {
+    let info = saves::WorldInfo::read(world_dir)?;
+    let editor = WorldEditor::open(world_dir)?;
+    let colors = BlockColors::load(&editor.config)?;
+
+    let area = match export_area( &editor, from, to )? {
+        Some(area) => area,
+        None => {
+            println!("the world has no blocks to export");
+            return Ok(1);
+        }
+    };
+
+    let size = area.size();
+    if size.0 > vox::MAX_MODEL_SIZE || size.1 > vox::MAX_MODEL_SIZE || size.2 > vox::MAX_MODEL_SIZE {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "the area is {}x{}x{} blocks, MagicaVoxel models can be at most {} along each side", size.0, size.1, size.2, vox::MAX_MODEL_SIZE ) ) );
+    }
+
+    // one colour index per kind of block, coloured like the block looks in game
+    let mut palette = [[0_u8; 4]; 256];
+    let mut indices = HashMap::<u16, u8>::new();
+    let mut voxels = Vec::new();
+    for x in area.min.0..=area.max.0 {
+        for y in area.min.1..=area.max.1 {
+            for z in area.min.2..=area.max.2 {
+                let blockdef = editor.get( (x, y, z) ).blockdef;
+                if blockdef == 0 {
+                    continue;
+                }
+                let index = match indices.get(&blockdef) {
+                    Some(index) => *index,
+                    None => {
+                        if indices.len() == 255 {
+                            return Err( Error::new( std::io::ErrorKind::InvalidInput, "the area uses more than 255 kinds of blocks, which is more than a MagicaVoxel palette holds" ) );
+                        }
+                        let index = indices.len() as u8 + 1;
+                        let c = colors.get(blockdef).unwrap_or( [128, 128, 128] );
+                        palette[index as usize] = [c[0], c[1], c[2], 255];
+                        indices.insert(blockdef, index);
+                        index
+                    }
+                };
+                // the world is y up, MagicaVoxel is z up
+                voxels.push( ( ( x - area.min.0 ) as u8, ( area.max.2 - z ) as u8, ( y - area.min.1 ) as u8, index ) );
+            }
+        }
+    }
+
+    let model = vox::VoxModel{ size: ( size.0, size.2, size.1 ), voxels, palette };
+    let out = out.unwrap_or( PathBuf::from( format!( "{}.vox", info.name ) ) );
+    std::fs::write( &out, vox::write_vox(&model)? )?;
+    println!( "exported {} blocks from {:?} to {:?} as {} colours into {}", model.voxels.len(), area.min, area.max, indices.len(), out.display() );
+    Ok(0)
+}
+
+fn vox_import(world_dir: &Path, file: &Path, at: (i64, i64, i64)) -> Result<i32, Error> {
+    let bytes = std::fs::read(file).map_err( |e| Error::new( e.kind(), format!( "{}: {}", file.display(), e ) ) )?;
+    let models = vox::read_vox(&bytes)?;
+    let model = models.first().ok_or( Error::new( std::io::ErrorKind::InvalidData, "the file has no models" ) )?;
+    if models.len() > 1 {
+        println!( "the file has {} models, only the first one is imported", models.len() );
+    }
+
+    let mut editor = WorldEditor::open(world_dir)?;
+    let colors = BlockColors::load(&editor.config)?;
+    let candidates = editor.cube_blocks();
+
+    // nearest block for each colour index, worked out once per colour
+    let mut blocks = HashMap::<u8, u16>::new();
+    let mut outside = 0;
+    for (vx, vy, vz, index) in model.voxels.iter() {
+        let blockdef = match blocks.get(index) {
+            Some(blockdef) => *blockdef,
+            None => {
+                let c = model.palette[*index as usize];
+                let blockdef = colors.nearest_block( [c[0], c[1], c[2]], &candidates ).ok_or( Error::new( std::io::ErrorKind::NotFound, "block.toml has no full cube blocks to build with" ) )?;
+                blocks.insert(*index, blockdef);
+                blockdef
+            }
+        };
+        // MagicaVoxel is z up, the world is y up
+        let pos = ( at.0 + *vx as i64, at.1 + *vz as i64, at.2 + ( model.size.1 as i64 - 1 - *vy as i64 ) );
+        if !editor.set( pos, blockdef, 0 ) {
+            outside += 1;
+        }
+    }
+
+    if editor.changed() > 0 {
+        editor.save()?;
+    }
+    println!( "placed {} blocks using {} kinds of block, {} were outside the world", editor.changed(), blocks.len(), outside );
+    Ok(0)
+}
+
+fn mesh_import(world_dir: &Path, file: &Path, at: (i64, i64, i64), scale: f32, block: &str, slope: Option<&str>, corner: Option<&str>) -> Result<i32, Error> {
+    if scale <= 0.0 || !scale.is_finite() {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, "--scale has to be more than 0" ) );
+    }
+
+    let triangles = voxelize::load_triangles(file)?;
+    let mut editor = WorldEditor::open(world_dir)?;
+    let family = editor.block_family( block, slope, corner )?;
+    let model = voxelize::voxelize( &triangles, scale, &family )?;
+
+    let mut outside = 0;
+    let mut counts = [0; 3];
+    for ( (x, y, z), fit ) in model.cells.iter() {
+        let (blockdef, exparam) = fit.block(&family);
+        let kind = match fit {
+            voxelize::CellFit::Cube => 0,
+            voxelize::CellFit::Slope(_) => 1,
+            voxelize::CellFit::Corner(_) => 2,
+        };
+        if editor.set( ( at.0 + *x as i64, at.1 + *y as i64, at.2 + *z as i64 ), blockdef, exparam ) {
+            counts[kind] += 1;
+        } else {
+            outside += 1;
+        }
+    }
+
+    if editor.changed() > 0 {
+        editor.save()?;
+    }
+    println!( "{} triangles make a model of {}x{}x{} blocks", triangles.len(), model.size.0, model.size.1, model.size.2 );
+    println!( "placed {} cubes, {} slopes and {} corners, {} were outside the world", counts[0], counts[1], counts[2], outside );
+    if family.slope.is_none() || family.corner.is_none() {
+        println!( "no slope or corner block was found for '{}', give them with --slope and --corner to smooth the surface", block );
+    }
+    Ok(0)
+}
+
+// --layers is block:depth,block:depth,...,block from the top down, the last one fills the rest
+fn parse_layers(editor: &WorldEditor, text: &str) -> Result<Vec<heightmap::Layer>, Error> {
+    let parts: Vec<&str> = text.split(',').map( |p| p.trim() ).collect();
+    let mut layers = Vec::new();
+    for (i, part) in parts.iter().enumerate() {
+        let (name, depth) = match part.split_once(':') {
+            Some( (name, depth) ) => ( name, Some( depth.parse::<usize>().map_err( |_| Error::new( std::io::ErrorKind::InvalidInput, format!( "'{}' is not a layer depth", depth ) ) )? ) ),
+            None => ( *part, None ),
+        };
+        if depth.is_none() && i + 1 < parts.len() {
+            return Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "layer '{}' needs a depth, only the last layer goes all the way down", name ) ) );
+        }
+        let depth = if i + 1 == parts.len() { None } else { depth };
+        layers.push( heightmap::Layer{ family: editor.block_family( name, None, None )?, depth } );
+    }
+    Ok(layers)
+}
+
+fn heightmap_import(world_dir: &Path, file: &Path, at: (i64, i64, i64), layers: &str, base: f32, height: f32, columns: Option<u32>) -> Result<i32, Error> {
+    if base < 0.0 || height < 0.0 {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, "--base and --height can't be negative" ) );
+    }
+
+    let map = heightmap::Heightmap::load( file, base, height, columns )?;
+    let mut editor = WorldEditor::open(world_dir)?;
+    let layers = parse_layers( &editor, layers )?;
+
+    let mut outside = 0;
+    // the terrain stops at the top of the world
+    let max_height = ( editor.width() as i64 - at.1 ).max(0) as usize;
+    heightmap::build_terrain( &map, &layers, max_height, |(x, y, z), blockdef, exparam| {
+        if !editor.set( ( at.0 + x as i64, at.1 + y as i64, at.2 + z as i64 ), blockdef, exparam ) {
+            outside += 1;
+        }
+    } );
+
+    if editor.changed() > 0 {
+        editor.save()?;
+    }
+    println!( "the terrain is {}x{} blocks and up to {} tall", map.width, map.depth, ( base + height ).ceil() );
+    println!( "placed {} blocks, {} were outside the world", editor.changed(), outside );
+    Ok(0)
+}
+
+struct MuralOptions {
+    at: (i64, i64, i64),
+    plane: mural::MuralPlane,
+    // block names to build with, every cube block when None
+    blocks: Option<String>,
+    columns: Option<u32>,
+    dither: bool,
+    blueprint: bool,
+}
+
+fn mural_import(world_dir: &Path, file: &Path, options: &MuralOptions) -> Result<i32, Error> {
+    let img = image::open(file).map_err( |e| Error::new( std::io::ErrorKind::InvalidData, format!( "{}: {}", file.display(), e ) ) )?;
+    let img = palette::resize_to_columns( img, options.columns );
+    let img = img.to_rgba8();
+
+    let mut editor = WorldEditor::open(world_dir)?;
+    let colors = BlockColors::load(&editor.config)?;
+    let candidates = match &options.blocks {
+        Some(names) => names.split(',').map( |n| editor.block_id( n.trim() ) ).collect::<Result<Vec<u16>, Error>>()?,
+        None => editor.cube_blocks(),
+    };
+    let pixels = mural::build_mural( &img, &colors, &candidates, options.dither );
+
+    let at = options.at;
+    let positions = pixels.iter().map( |( (px, py), id )| {
+        let o = options.plane.offset( *px, *py, img.height() );
+        ( ( at.0 + o.0, at.1 + o.1, at.2 + o.2 ), *id )
+    } );
+
+    let mut kinds = HashMap::<u16, usize>::new();
+    for (_, id) in pixels.iter() {
+        *kinds.entry(*id).or_insert(0) += 1;
+    }
+    println!( "the mural is {}x{} blocks using {} kinds of block", img.width(), img.height(), kinds.len() );
+
+    if options.blueprint {
+        // planned only, the game shows it as ghost blocks to build over
+        let mut blueprint = Blueprint::read(world_dir)?;
+        let mut planned = Vec::new();
+        let mut outside = 0;
+        for (pos, id) in positions {
+            if editor.in_world(pos).is_none() {
+                outside += 1;
+                continue;
+            }
+            let name = editor.registry.get(id).unwrap().name.clone();
+            planned.push( BlueprintBlock{ pos: [ pos.0, pos.1, pos.2 ], name, exparam: 0 } );
+        }
+        let count = planned.len();
+        blueprint.add(planned);
+        blueprint.write(world_dir)?;
+        println!( "added {} blocks to the world's blueprint, {} were outside the world", count, outside );
+    } else {
+        let mut outside = 0;
+        for (pos, id) in positions {
+            if !editor.set( pos, id, 0 ) {
+                outside += 1;
+            }
+        }
+        if editor.changed() > 0 {
+            editor.save()?;
+        }
+        println!( "placed {} blocks, {} were outside the world", editor.changed(), outside );
+    }
+    Ok(0)
+}
+
+fn schematic_import(world_dir: &Path, file: &Path, at: (i64, i64, i64), mapping: Option<PathBuf>, blueprint: bool) -> Result<i32, Error> {
+    let schem = schematic::read_schematic(file)?;
+    let mut editor = WorldEditor::open(world_dir)?;
+    let mapping = BlockMapping::load( &mapping.unwrap_or( PathBuf::from(schematic::MAPPING_PATH) ), &editor.registry )?;
+
+    // each palette entry is looked up once
+    let mapped: Vec<Option<(u16, u8)>> = schem.palette.iter().map( |state| mapping.map( state, &editor.registry, &editor.shape_registry ) ).collect();
+    let mut unmapped = HashMap::<&str, usize>::new();
+    let mut placed = Vec::new();
+    for ( (x, y, z), idx ) in schem.blocks.iter() {
+        match mapped[*idx] {
+            Some(block) => placed.push( ( ( at.0 + *x as i64, at.1 + *y as i64, at.2 + *z as i64 ), block ) ),
+            None => *unmapped.entry( &schem.palette[*idx].name ).or_insert(0) += 1,
+        }
+    }
+    println!( "the schematic is {}x{}x{} blocks with {} that aren't air", schem.size.0, schem.size.1, schem.size.2, schem.blocks.len() );
+
+    let mut outside = 0;
+    if blueprint {
+        let mut plan = Blueprint::read(world_dir)?;
+        let mut planned = Vec::new();
+        for (pos, (id, exparam)) in placed {
+            if editor.in_world(pos).is_none() {
+                outside += 1;
+                continue;
+            }
+            let name = editor.registry.get(id).unwrap().name.clone();
+            planned.push( BlueprintBlock{ pos: [ pos.0, pos.1, pos.2 ], name, exparam } );
+        }
+        let count = planned.len();
+        plan.add(planned);
+        plan.write(world_dir)?;
+        println!( "added {} blocks to the world's blueprint, {} were outside the world", count, outside );
+    } else {
+        for (pos, (id, exparam)) in placed {
+            if !editor.set( pos, id, exparam ) {
+                outside += 1;
+            }
+        }
+        if editor.changed() > 0 {
+            editor.save()?;
+        }
+        println!( "placed {} blocks, {} were outside the world", editor.changed(), outside );
+    }
+
+    if !unmapped.is_empty() {
+        let mut names: Vec<(&str, usize)> = unmapped.into_iter().collect();
+        names.sort_by( |a, b| b.1.cmp(&a.1).then( a.0.cmp(b.0) ) );
+        println!( "left out {} blocks the mapping has no rule for:", names.iter().map( |n| n.1 ).sum::<usize>() );
+        for (name, count) in names {
+            println!( "  {:>8}  {}", count, name );
+        }
+    }
+    Ok(0)
+}
+
+fn clear_blueprint(world_dir: &Path) -> Result<i32, Error> {
+    let blueprint = Blueprint::read(world_dir)?;
+    Blueprint::default().write(world_dir)?;
+    println!( "removed {} planned blocks", blueprint.block.len() );
+    Ok(0)
+}
+
+// the area given with --from and --to, otherwise every block in the world
+fn export_area(editor: &WorldEditor, from: Option<(i64, i64, i64)>, to: Option<(i64, i64, i64)>) -> Result<Option<BlockArea>, Error> {
+    match (from, to) {
+        ( Some(a), Some(b) ) => {
+            let outside = |p: (i64, i64, i64)| Error::new( std::io::ErrorKind::InvalidInput, format!( "{:?} is outside the world", p ) );
+            Ok( Some( BlockArea::new( editor.in_world(a).ok_or( outside(a) )?, editor.in_world(b).ok_or( outside(b) )? ) ) )
+        }
+        ( None, None ) => Ok( used_area( editor, editor.whole_world() ) ),
+        _ => Err( Error::new( std::io::ErrorKind::InvalidInput, "--from and --to have to be given together" ) ),
+    }
+}
+
+fn export_mesh(world_dir: &Path, from: Option<(i64, i64, i64)>, to: Option<(i64, i64, i64)>, format: Option<&str>, out: Option<PathBuf>) -> Result<i32, Error> {
+    let info = saves::WorldInfo::read(world_dir)?;
+    let editor = WorldEditor::open(world_dir)?;
+    let area = match export_area( &editor, from, to )? {
+        Some(area) => area,
+        None => {
+            println!("the world has no blocks to export");
+            return Ok(1);
+        }
+    };
+
+    // the format follows the output file's extension unless it is given
+    let format = match ( format, out.as_ref().and_then( |o| o.extension() ).and_then( |e| e.to_str() ) ) {
+        ( Some(f), _ ) => f.to_lowercase(),
+        ( None, Some(ext) ) => ext.to_lowercase(),
+        ( None, None ) => "glb".to_string(),
+    };
+    if format != "glb" && format != "obj" {
+        return Err( Error::new( std::io::ErrorKind::InvalidInput, format!( "can't export to '{}', the formats are glb and obj", format ) ) );
+    }
+    let out = out.unwrap_or( PathBuf::from( format!( "{}.{}", info.name, format ) ) );
+
+    let atlas = mesh_export::TextureAtlas::bake(&editor.config)?;
+    let mesh = mesh_export::build_mesh( &editor, &area, &atlas );
+    if mesh.indices.is_empty() {
+        println!("the area has no visible block faces to export");
+        return Ok(1);
+    }
+
+    let mut png = std::io::Cursor::new( Vec::<u8>::new() );
+    atlas.image.write_to( &mut png, image::ImageOutputFormat::Png ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+
+    let mut written = vec![ out.clone() ];
+    if format == "glb" {
+        std::fs::write( &out, mesh_export::write_glb( &mesh, png.get_ref() )? )?;
+    } else {
+        // OBJ keeps its material and texture in files next to it
+        let mtl = out.with_extension("mtl");
+        let texture = out.with_extension("png");
+        let file_name = |p: &PathBuf| p.file_name().unwrap_or_default().to_string_lossy().to_string();
+        std::fs::write( &out, mesh_export::write_obj( &mesh, &file_name(&mtl) ) )?;
+        std::fs::write( &mtl, mesh_export::write_mtl( &file_name(&texture) ) )?;
+        std::fs::write( &texture, png.get_ref() )?;
+        written.push(mtl);
+        written.push(texture);
+    }
+
+    println!( "exported {} triangles from {:?} to {:?}", mesh.triangle_count(), area.min, area.max );
+    for path in written {
+        println!( "  {}", path.display() );
+    }
+    Ok(0)
+}
+
+// command line split into plain arguments and --name value options
+struct ToolArgs {
+    positional: Vec<String>,
+    options: HashMap<String, String>,
+}
+
+impl ToolArgs {
+    fn parse(args: Vec<String>, flags: &[&str]) -> Result<ToolArgs, String> {
+        let mut positional = Vec::new();
+        let mut options = HashMap::new();
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            if flags.contains( &arg.as_str() ) {
+                options.insert( arg, String::new() );
+            } else if arg.starts_with("--") {
+                let value = iter.next().ok_or( format!( "{} needs a value", arg ) )?;
+                options.insert( arg, value );
+            } else {
+                positional.push(arg);
+            }
+        }
+        Ok( ToolArgs{ positional, options } )
+    }
+
+    fn positional(&self, idx: usize) -> Result<&str, String> {
+        self.positional.get(idx).map( |p| p.as_str() ).ok_or( USAGE.to_string() )
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.options.get(name).map( |o| o.as_str() )
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn number<T: std::str::FromStr>(&self, name: &str, default: T) -> Result<T, String> {
+        match self.get(name) {
+            Some(text) => text.parse::<T>().map_err( |_| format!( "{} expects a number, got '{}'", name, text ) ),
+            None => Ok(default),
+        }
+    }
+
+    // x,y,z block coordinates
+    fn position(&self, name: &str) -> Result<Option<(i64, i64, i64)>, String> {
+        let text = match self.get(name) {
+            Some(text) => text,
+            None => return Ok(None),
+        };
+        let nums: Vec<i64> = text.split(',').map( |n| n.trim().parse::<i64>() ).collect::<Result<_, _>>().map_err( |_| format!( "{} expects x,y,z, got '{}'", name, text ) )?;
+        if nums.len() != 3 {
+            return Err( format!( "{} expects x,y,z, got '{}'", name, text ) );
+        }
+        Ok( Some( (nums[0], nums[1], nums[2]) ) )
+    }
+
+    fn check_known(&self, known: &[&str]) -> Result<(), String> {
+        for name in self.options.keys() {
+            if !known.contains( &name.as_str() ) {
+                return Err( format!( "unknown option '{}'\n\n{}", name, USAGE ) );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn world_dir_arg(args: &ToolArgs, idx: usize) -> Result<PathBuf, String> {
+    let world_dir = WorldSaver::find_world_dir( args.positional(idx)? );
+    if !world_dir.is_dir() {
+        return Err( format!( "no world folder at {}", world_dir.display() ) );
+    }
+    Ok(world_dir)
+}
+
+fn run_command(args: Vec<String>) -> Result<i32, String> {
+    let args = ToolArgs::parse( args, &["--dither", "--blueprint"] )?;
+    let command = args.positional(0)?.to_string();
+    let sub = match command.as_str() {
+        "backups" | "vox" | "mesh" | "heightmap" | "mural" | "schematic" | "blueprint" => Some( args.positional(1)?.to_string() ),
+        _ => None,
+    };
+    let first = if sub.is_some() { 2 } else { 1 };
+    let world_dir = world_dir_arg(&args, first)?;
+
+    let result = match ( command.as_str(), sub.as_deref() ) {
+        ( "stats", None ) => { args.check_known(&[])?; stats(&world_dir) }
+        ( "validate", None ) => { args.check_known(&[])?; validate(&world_dir) }
+        ( "convert", None ) => { args.check_known(&[])?; convert(&world_dir) }
+        ( "backups", Some("list") ) => { args.check_known(&[])?; list_backups(&world_dir) }
+        ( "backups", Some("prune") ) => {
+            args.check_known(&["--keep"])?;
+            prune_backups( &world_dir, args.number("--keep", 1)? )
+        }
+        ( "vox", Some("export") ) => {
+            args.check_known(&["--from", "--to", "--out"])?;
+            vox_export( &world_dir, args.position("--from")?, args.position("--to")?, args.get("--out").map( PathBuf::from ) )
+        }
+        ( "mesh", Some("export") ) => {
+            args.check_known(&["--from", "--to", "--format", "--out"])?;
+            export_mesh( &world_dir, args.position("--from")?, args.position("--to")?, args.get("--format"), args.get("--out").map( PathBuf::from ) )
+        }
+        ( "mesh", Some("import") ) => {
+            args.check_known(&["--at", "--scale", "--block", "--slope", "--corner"])?;
+            let file = PathBuf::from( args.positional(first + 1)? );
+            let block = args.get("--block").ok_or( format!( "--block is needed to know what to build with\n\n{}", USAGE ) )?;
+            mesh_import( &world_dir, &file, args.position("--at")?.unwrap_or( (0, 0, 0) ), args.number("--scale", 1.0)?, block, args.get("--slope"), args.get("--corner") )
+        }
+        ( "heightmap", Some("import") ) => {
+            args.check_known(&["--at", "--layers", "--base", "--height", "--columns"])?;
+            let file = PathBuf::from( args.positional(first + 1)? );
+            let layers = args.get("--layers").ok_or( format!( "--layers is needed to know what to build with\n\n{}", USAGE ) )?;
+            let columns = match args.get("--columns") {
+                Some(_) => Some( args.number("--columns", 0)? ),
+                None => None,
+            };
+            heightmap_import( &world_dir, &file, args.position("--at")?.unwrap_or( (0, 0, 0) ), layers, args.number("--base", 1.0)?, args.number("--height", 32.0)?, columns )
+        }
+        ( "mural", Some("import") ) => {
+            args.check_known(&["--at", "--plane", "--blocks", "--columns", "--dither", "--blueprint"])?;
+            let file = PathBuf::from( args.positional(first + 1)? );
+            let plane = args.get("--plane").unwrap_or("wall-x");
+            let options = MuralOptions {
+                at: args.position("--at")?.unwrap_or( (0, 0, 0) ),
+                plane: mural::MuralPlane::parse(plane).ok_or( format!( "unknown plane '{}', the planes are wall-x, wall-z and floor", plane ) )?,
+                blocks: args.get("--blocks").map( |b| b.to_string() ),
+                columns: match args.get("--columns") {
+                    Some(_) => Some( args.number("--columns", 0)? ),
+                    None => None,
+                },
+                dither: args.flag("--dither"),
+                blueprint: args.flag("--blueprint"),
+            };
+            mural_import( &world_dir, &file, &options )
+        }
+        ( "schematic", Some("import") ) => {
+            args.check_known(&["--at", "--mapping", "--blueprint"])?;
+            let file = PathBuf::from( args.positional(first + 1)? );
+            schematic_import( &world_dir, &file, args.position("--at")?.unwrap_or( (0, 0, 0) ), args.get("--mapping").map( PathBuf::from ), args.flag("--blueprint") )
+        }
+        ( "blueprint", Some("clear") ) => { args.check_known(&[])?; clear_blueprint(&world_dir) }
+        ( "vox", Some("import") ) => {
+            args.check_known(&["--at"])?;
+            let file = PathBuf::from( args.positional(first + 1)? );
+            vox_import( &world_dir, &file, args.position("--at")?.unwrap_or( (0, 0, 0) ) )
+        }
+        _ => return Err( format!( "unknown command\n\n{}", USAGE ) ),
+    };
+    result.map_err( |e| format!( "{}: {}", world_dir.display(), e ) )
+}
+
+// entry point of the sgr_cubes-tool binary, returns the process exit code
+pub fn run_tool(args: Vec<String>) -> i32 {
+    if args.iter().any( |a| a == "--help" || a == "-h" ) {
+        println!("{}", USAGE);
+        return 0;
+    }
+    match run_command(args) {
+        Ok(code) => code,
+        Err(message) => {
+            eprintln!("{}", message);
+            2
+        }
+    }
+}
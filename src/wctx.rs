@@ -3,6 +3,7 @@
 use std::collections::HashMap;
 use std::time::{Instant, Duration};
 use std::path::PathBuf;
+use std::sync::Arc;
 use std::io::{
     Write,
     Read
@@ -20,18 +21,19 @@ use winit::{
 
 use wgpu::util::DeviceExt;
 
-mod camera;
+use crate::engine::world_gen;
 
-mod chunk;
+mod camera;
 
 mod texture;
 mod atlas_tex;
 
-mod block;
-mod rotation_group;
-
 mod data_loader;
 
+pub mod headless;
+
+mod screenshot;
+mod settings;
 mod ui;
 mod world;
 mod world_loader;
@@ -47,8 +49,9 @@ struct MouseOps {
 
 struct State<'a> {
     surface: wgpu::Surface<'a>,
-    device: wgpu::Device,
-    queue: wgpu::Queue,
+    // shared with the world loading thread
+    device: Arc<wgpu::Device>,
+    queue: Arc<wgpu::Queue>,
     config: wgpu::SurfaceConfiguration,
     size: winit::dpi::PhysicalSize<u32>,
     window: &'a Window, // The window must be declared after the surface so it gets dropped after it as the surface contains unsafe references to the window's resources.
@@ -57,6 +60,10 @@ struct State<'a> {
     ui_mode: ui::UIMode,
     world_render: Option<world::WorldRender>,
     world_loader: world_loader::WorldLoader,
+    settings: settings::GameSettings,
+    autosaver: world_saver::Autosaver,
+    load_task: Option<world_loader::LoadTask>,
+    screenshot_request: Option<screenshot::ScreenshotRequest>,
 }
 
 impl<'a> State<'a> {
@@ -106,6 +113,8 @@ impl<'a> State<'a> {
             },
             None, // Trace path
         ).await.unwrap();
+        let device = Arc::new(device);
+        let queue = Arc::new(queue);
 
         let surface_caps = surface.get_capabilities(&adapter);
         // Shader code in this tutorial assumes an sRGB surface texture. Using a different
@@ -115,8 +124,13 @@ impl<'a> State<'a> {
             .find(|f| f.is_srgb())
             .copied()
             .unwrap_or(surface_caps.formats[0]);
+        // reading the frame back for screenshots needs COPY_SRC, without it they are rendered off screen
+        let mut usage = wgpu::TextureUsages::RENDER_ATTACHMENT;
+        if surface_caps.usages.contains( wgpu::TextureUsages::COPY_SRC ) {
+            usage |= wgpu::TextureUsages::COPY_SRC;
+        }
         let config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            usage,
             format: surface_format,
             width: size.width,
             height: size.height,
@@ -131,16 +145,21 @@ impl<'a> State<'a> {
         let mouse_pressed = MouseOps{left: false, right: false, left_just_now: false, right_just_now: false };
 
 
+        let settings = settings::GameSettings::load();
+        let autosaver = world_saver::Autosaver::new(settings.autosave_seconds);
+
         let mut ui_core = ui::UICore::new(&config.format, &config, &device, &queue,);
         let ui_mode = ui::UIMode::MainTitle;
 
         let mut wl = world_loader::WorldLoader {
             previews: Vec::<world_loader::WorldPreview>::new(),
             name_map: HashMap::<String, usize>::new(),
+            broken: Vec::new(),
         };
         let pvs = wl.load_previews( &ui_core.get_gfx(&device, &queue) );
 
-        ui_core.update_world_list( pvs, &config, &device, &queue );
+        let broken = wl.broken_dirs();
+        ui_core.update_world_list( pvs, broken, "".to_string(), &config, &device, &queue );
 
         //let wss = Self::load_world().unwrap_or( world::WorldSavestate::new(0) );
         //let wr = world::WorldRender::new(&device, &queue, &config, wss);
@@ -157,6 +176,10 @@ impl<'a> State<'a> {
             ui_mode,
             world_render: None,
             world_loader: wl,
+            settings,
+            autosaver,
+            load_task: None,
+            screenshot_request: None,
         }
     }
 
@@ -278,13 +301,18 @@ impl<'a> State<'a> {
                 .set_cursor_grab(winit::window::CursorGrabMode::Confined)
                 .or_else(|_e| self.window.set_cursor_grab(winit::window::CursorGrabMode::Locked));
                 self.window.set_cursor_visible(false);
+                self.ui_core.show_queued_hud_message();
                 self.ui_mode = ui::UIMode::Gameplay;
             }
             ui::UIMode::WorldSelection => {
                 let pvs = self.world_loader.load_previews( &self.ui_core.get_gfx(&self.device, &self.queue) );
-                self.ui_core.update_world_list( pvs, &self.config, &self.device, &self.queue );
+                self.ui_core.update_world_list( pvs, self.world_loader.broken_dirs(), "".to_string(), &self.config, &self.device, &self.queue );
                 self.ui_mode = ui::UIMode::WorldSelection;
             }
+            ui::UIMode::CreateWorldMenu => {
+                self.ui_core.open_world_creation( &world_saver::WorldSaver::list_templates(), &self.config, &self.device, &self.queue );
+                self.ui_mode = ui::UIMode::CreateWorldMenu;
+            }
             _ => {
                 let _ = self.window.set_cursor_grab(winit::window::CursorGrabMode::None);
                 self.window.set_cursor_visible(true);
@@ -295,11 +323,253 @@ impl<'a> State<'a> {
 
     }
 
+    // save directory of a world listed in the world selection screen
+    fn world_dir_for(&self, name: &str) -> PathBuf {
+        match self.world_loader.name_map.get(name) {
+            Some(idx) => PathBuf::from( &self.world_loader.previews[*idx].path_name ),
+            None => world_saver::WorldSaver::world_dir(name),
+        }
+    }
+
+    fn open_world_options(&mut self, name: &str, status: String) {
+        self.open_world_options_confirm( name, status, false );
+    }
+
+    fn open_world_options_confirm(&mut self, name: &str, status: String, confirm_delete: bool) {
+        let world_dir = self.world_dir_for(name);
+        let backups = world_saver::WorldSaver::list_backups(&world_dir);
+        let is_template = world_saver::WorldInfo::read(&world_dir).map( |info| info.template ).unwrap_or(false);
+        self.ui_core.open_world_options( name.to_string(), &backups, status, confirm_delete, is_template, &self.config, &self.device, &self.queue );
+        self.ui_mode = ui::UIMode::WorldOptions;
+    }
+
+    fn refresh_world_list(&mut self) {
+        let pvs = self.world_loader.load_previews( &self.ui_core.get_gfx(&self.device, &self.queue) );
+        self.ui_core.update_world_list( pvs, self.world_loader.broken_dirs(), "".to_string(), &self.config, &self.device, &self.queue );
+    }
+
+    // puts up the error dialog for a world that failed to load; name is None if the world isn't listed
+    fn show_load_error(&mut self, name: Option<String>, world_dir: PathBuf, error: &world_loader::WorldLoadError) {
+        log::error!("failed to load world {}: {}", world_dir.display(), error);
+        let title = name.clone().unwrap_or( world_dir.file_name().map( |n| n.to_string_lossy().to_string() ).unwrap_or_default() );
+        let can_restore = name.is_some() && error.save_problem() && !world_saver::WorldSaver::list_backups(&world_dir).is_empty();
+        self.ui_core.show_load_error( title, error.to_string(), can_restore, &self.config, &self.device, &self.queue );
+        self.ui_core.load_error_name = name;
+        self.ui_core.load_error_dir = Some(world_dir);
+        self.update_ui_mode( ui::UIMode::LoadError );
+    }
+
+    fn load_selected_world(&mut self, name: String) {
+        let pv = match self.world_loader.name_map.get(&name) {
+            Some(idx) => self.world_loader.previews[*idx].clone(),
+            None => {
+                let error = world_loader::WorldLoadError::NotFound( name.clone() );
+                self.show_load_error( None, world_saver::WorldSaver::world_dir(&name), &error );
+                return;
+            }
+        };
+
+        self.load_task = Some( world_loader::LoadTask::load( pv, name, self.device.clone(), self.queue.clone(), self.config.clone() ) );
+        self.update_ui_mode( ui::UIMode::Loading );
+    }
+
+    // loads a world in the background only to render its missing preview.png
+    fn generate_preview(&mut self, name: String) {
+        match self.world_loader.name_map.get(&name) {
+            Some(idx) => {
+                let pv = self.world_loader.previews[*idx].clone();
+                self.load_task = Some( world_loader::LoadTask::load_for_preview( pv, name, self.device.clone(), self.queue.clone(), self.config.clone() ) );
+                self.update_ui_mode( ui::UIMode::Loading );
+            }
+            None => self.update_ui_mode( ui::UIMode::WorldSelection ),
+        }
+    }
+
+    // checks on the world loading in the background, switching to the world once it's ready
+    fn poll_load_task(&mut self) {
+        let result = match &mut self.load_task {
+            Some(task) => {
+                let result = task.poll();
+                self.ui_core.set_loading_progress( format!( "{}: {}", task.world_name, task.status() ), task.fraction() );
+                result
+            }
+            None => {
+                self.update_ui_mode( ui::UIMode::WorldSelection );
+                return;
+            }
+        };
+
+        if let Some(result) = result {
+            let task = self.load_task.take().unwrap();
+            match result {
+                Ok(wr) if task.preview_only => {
+                    if let Err(e) = world_saver::WorldSaver::write_preview( &wr, &task.world_dir, &self.settings.preview, &self.device, &self.queue ) {
+                        log::error!("failed to render the preview for {}: {}", task.world_name, e);
+                    }
+                    self.update_ui_mode( ui::UIMode::WorldSelection );
+                }
+                Ok(mut wr) => {
+                    // the window may have changed size while loading
+                    wr.resize_window(&self.device, &self.config);
+                    if task.generated {
+                        if let Err(e) = world_saver::WorldSaver::write_first_save( &mut wr.world, &task.world_dir ) {
+                            log::error!("failed to write the first save of {}, edits are lost if the game stops before it is saved: {}", task.world_name, e);
+                        }
+                    }
+                    if !wr.world.unknown_blocks.is_empty() {
+                        self.ui_core.queue_hud_message( unknown_blocks_message( &wr.world.unknown_blocks ) );
+                    }
+                    self.world_render = Some(wr);
+                    self.autosaver.reset();
+                    self.ui_mode = ui::UIMode::PauseMenu;
+                }
+                Err(e) => {
+                    self.show_load_error( Some(task.world_name), task.world_dir, &e );
+                }
+            }
+        }
+    }
+
+    // carries out a button press from the load error dialog
+    fn do_load_error_action(&mut self, action: ui::world_ui::LoadErrorAction) {
+        let name = self.ui_core.load_error_name.clone();
+        let world_dir = self.ui_core.load_error_dir.clone();
+        match action {
+            ui::world_ui::LoadErrorAction::Retry => {
+                self.refresh_world_list();
+                if let Some(name) = name {
+                    self.load_selected_world(name);
+                    return;
+                }
+                // an unreadable info.toml, see if it has been fixed
+                let still_broken = self.world_loader.broken.iter().position( |b| Some( &b.0 ) == world_dir.as_ref() );
+                match still_broken {
+                    Some(idx) => {
+                        let (dir, error) = self.world_loader.broken.remove(idx);
+                        self.show_load_error( None, dir, &error );
+                    }
+                    None => self.update_ui_mode( ui::UIMode::WorldSelection ),
+                }
+            }
+            ui::world_ui::LoadErrorAction::Restore => {
+                self.ui_core.world_options_name = name;
+                self.update_ui_mode( ui::UIMode::OpenWorldOptions );
+            }
+            ui::world_ui::LoadErrorAction::OpenFolder => {
+                if let Some(dir) = world_dir {
+                    if let Err(e) = world_loader::open_folder(&dir) {
+                        log::error!("failed to open {}: {}", dir.display(), e);
+                    }
+                }
+                self.ui_mode = ui::UIMode::LoadError;
+            }
+            ui::world_ui::LoadErrorAction::Back | ui::world_ui::LoadErrorAction::None => {
+                self.update_ui_mode( ui::UIMode::WorldSelection );
+            }
+        }
+    }
+
+    // carries out a button press from the world options screen
+    fn do_world_action(&mut self, name: &str, action: ui::world_ui::WorldOptionsAction) {
+        let world_dir = self.world_dir_for(name);
+        match action {
+            ui::world_ui::WorldOptionsAction::None => {
+                self.open_world_options( name, "".to_string() );
+            }
+            ui::world_ui::WorldOptionsAction::Rename(new_name) => {
+                match world_saver::WorldSaver::rename_world( &world_dir, &new_name ) {
+                    Ok(_) => {
+                        let new_name = new_name.trim().to_string();
+                        self.refresh_world_list();
+                        self.ui_core.world_options_name = Some( new_name.clone() );
+                        self.open_world_options( &new_name, format!("Renamed to {}.", new_name) );
+                    }
+                    Err(e) => {
+                        self.open_world_options( name, format!("Couldn't rename: {}", e) );
+                    }
+                }
+            }
+            ui::world_ui::WorldOptionsAction::Duplicate => {
+                let status = match world_saver::WorldSaver::duplicate_world(&world_dir) {
+                    Ok(new_name) => {
+                        self.refresh_world_list();
+                        format!("Copied to {}.", new_name)
+                    }
+                    Err(e) => {
+                        log::error!("failed to duplicate world {}: {}", name, e);
+                        format!("Couldn't duplicate: {}", e)
+                    }
+                };
+                self.open_world_options( name, status );
+            }
+            ui::world_ui::WorldOptionsAction::AskDelete => {
+                self.open_world_options_confirm( name, "".to_string(), true );
+            }
+            ui::world_ui::WorldOptionsAction::CancelDelete => {
+                self.open_world_options( name, "".to_string() );
+            }
+            ui::world_ui::WorldOptionsAction::Delete => {
+                match world_saver::WorldSaver::delete_world(&world_dir) {
+                    Ok(()) => {
+                        self.ui_core.world_options_name = None;
+                        self.update_ui_mode( ui::UIMode::WorldSelection );
+                    }
+                    Err(e) => {
+                        log::error!("failed to delete world {}: {}", name, e);
+                        self.open_world_options( name, format!("Couldn't delete: {}", e) );
+                    }
+                }
+            }
+            ui::world_ui::WorldOptionsAction::Restore(backup_path) => {
+                let backup = world_saver::WorldSaver::list_backups(&world_dir).into_iter().find( |b| b.path.to_string_lossy() == backup_path );
+                let status = match backup.map( |b| world_saver::WorldSaver::restore_backup(&world_dir, &b) ) {
+                    Some(Ok(())) => "Backup restored.".to_string(),
+                    Some(Err(e)) => {
+                        log::error!("failed to restore backup {}: {}", backup_path, e);
+                        format!("Failed to restore backup: {}", e)
+                    }
+                    None => "That backup no longer exists.".to_string(),
+                };
+                self.open_world_options( name, status );
+            }
+            ui::world_ui::WorldOptionsAction::Export => {
+                let status = match world_saver::WorldSaver::export_world(&world_dir) {
+                    Ok(path) => format!("Exported to {}", path.display()),
+                    Err(e) => {
+                        log::error!("failed to export world {}: {}", name, e);
+                        format!("Couldn't export: {}", e)
+                    }
+                };
+                self.open_world_options( name, status );
+            }
+            ui::world_ui::WorldOptionsAction::SetTemplate(template) => {
+                let status = match world_saver::WorldSaver::set_template(&world_dir, template) {
+                    Ok(_) if template => "New worlds can now be started from this one.".to_string(),
+                    Ok(_) => "No longer a template.".to_string(),
+                    Err(e) => {
+                        log::error!("failed to update world {}: {}", name, e);
+                        format!("Couldn't change the template setting: {}", e)
+                    }
+                };
+                self.refresh_world_list();
+                self.open_world_options( name, status );
+            }
+        }
+    }
+
     fn update(&mut self, dt: std::time::Duration) {
         if let Some(new) = self.ui_core.update(self.ui_mode) {
             self.update_ui_mode( new );
         }
 
+        if self.ui_mode == ui::UIMode::Gameplay || self.ui_mode == ui::UIMode::PauseMenu {
+            if let Some(ref mut wr) = &mut self.world_render {
+                if self.autosaver.update( dt, wr ) {
+                    self.ui_core.show_saved_indicator();
+                }
+            }
+        }
+
         match self.ui_mode {
             ui::UIMode::Gameplay => {
                 if let Some(ref mut wr) = &mut self.world_render {
@@ -307,30 +577,121 @@ impl<'a> State<'a> {
                 }
             }
             ui::UIMode::LoadWorld => {
-                if let Some(name) = self.ui_core.world_selected_name.clone() {
-                    self.ui_core.world_selected_name = None;
-                    let pv = &self.world_loader.previews[ *self.world_loader.name_map.get(&name).unwrap() ];
-                    let world_load = pv.load_world();
-
-                    if let Ok(wss) = world_load {
-                        self.world_render = Some( world::WorldRender::new(&self.device, &self.queue, &self.config, wss, name) );
-                        self.ui_mode = ui::UIMode::PauseMenu;
+                if let Some(name) = self.ui_core.world_selected_name.take() {
+                    self.load_selected_world(name);
+                } else {
+                    self.update_ui_mode( ui::UIMode::WorldSelection );
+                }
+            }
+            ui::UIMode::Loading => {
+                self.poll_load_task();
+            }
+            ui::UIMode::GeneratePreview => {
+                match self.ui_core.world_selected_name.take() {
+                    Some(name) => self.generate_preview(name),
+                    None => self.update_ui_mode( ui::UIMode::WorldSelection ),
+                }
+            }
+            ui::UIMode::ExportMap => {
+                if let Some(ref mut wr) = &mut self.world_render {
+                    match world_saver::WorldSaver::export_map( wr, &self.settings.map, &self.device, &self.queue ) {
+                        Ok(dir) => {
+                            log::info!("exported map to {}", dir.display());
+                            self.ui_core.show_hud_message( format!( "Map exported to {}", dir.display() ) );
+                        }
+                        Err(e) => {
+                            log::error!("failed to export map: {}", e);
+                            self.ui_core.show_hud_message( format!( "Map export failed: {}", e ) );
+                        }
                     }
                 }
+                self.ui_mode = ui::UIMode::PauseMenu;
             }
             ui::UIMode::QuitGameplay => {
                 let mut worldsaver = world_saver::WorldSaver{};
-                worldsaver.save_world( &self.world_render.as_ref().unwrap(), &self.device, &self.queue );
+                self.autosaver.wait( self.world_render.as_mut().unwrap() );
+                if let Err(e) = worldsaver.save_world( self.world_render.as_mut().unwrap(), &self.settings.preview, &self.device, &self.queue ) {
+                    log::error!("failed to save world: {}", e);
+                }
                 self.world_render = None;
                 self.ui_mode = ui::UIMode::MainTitle;
             }
             ui::UIMode::CreateWorld => {
                 let name = self.ui_core.world_selected_name.clone().expect("missing world name!");
-                let wss = world::WorldSavestate::new(0);
-                let mut wr = world::WorldRender::new(&self.device, &self.queue, &self.config, wss, name.clone() );
-                wr.update_chunks();
-                self.world_render = Some(wr);
-                self.ui_mode = ui::UIMode::PauseMenu;
+                if let Some(template) = self.ui_core.world_create_template.take() {
+                    self.ui_core.world_create_settings = None;
+                    match world_saver::WorldSaver::create_from_template( &template, &name ) {
+                        Ok(_) => {
+                            self.ui_core.world_selected_name = None;
+                            self.refresh_world_list();
+                            self.load_selected_world( name.trim().to_string() );
+                        }
+                        Err(e) => {
+                            log::error!("failed to create world {} from template {}: {}", name, template.display(), e);
+                            self.ui_core.show_create_error( format!( "Couldn't create the world: {}", e ) );
+                            self.ui_mode = ui::UIMode::CreateWorldMenu;
+                        }
+                    }
+                } else {
+                    let settings = self.ui_core.world_create_settings.take().unwrap_or( world_gen::WorldGenSettings::new(0) );
+                    // an existing world's files would be overwritten, and a name like ../x would land outside the worlds folder
+                    match world_saver::WorldSaver::validate_name(&name) {
+                        Ok(()) => {
+                            let name = name.trim().to_string();
+                            let world_dir = world_saver::WorldSaver::create_world_dir( &world_saver::WorldInfo::new( name.clone(), &settings ) );
+                            self.load_task = Some( world_loader::LoadTask::generate( name, world_dir, settings, self.device.clone(), self.queue.clone(), self.config.clone() ) );
+                            self.update_ui_mode( ui::UIMode::Loading );
+                        }
+                        Err(e) => {
+                            self.ui_core.show_create_error( format!( "Couldn't create the world: {}", e ) );
+                            self.ui_mode = ui::UIMode::CreateWorldMenu;
+                        }
+                    }
+                }
+            }
+            ui::UIMode::ImportWorld => {
+                let status = match self.ui_core.import_path.take() {
+                    Some(path) => match world_saver::WorldSaver::import_world( &PathBuf::from(path.trim_matches('"')) ) {
+                        Ok(name) => format!("Imported {}.", name),
+                        Err(e) => {
+                            log::error!("failed to import world from {}: {}", path, e);
+                            format!("Import failed: {}", e)
+                        }
+                    },
+                    None => "".to_string(),
+                };
+                let pvs = self.world_loader.load_previews( &self.ui_core.get_gfx(&self.device, &self.queue) );
+                self.ui_core.update_world_list( pvs, self.world_loader.broken_dirs(), status, &self.config, &self.device, &self.queue );
+                self.ui_mode = ui::UIMode::WorldSelection;
+            }
+            ui::UIMode::OpenLoadError => {
+                let dir = self.ui_core.load_error_dir.clone();
+                let broken = self.world_loader.broken.iter().position( |b| Some( &b.0 ) == dir.as_ref() );
+                match broken {
+                    Some(idx) => {
+                        let (dir, error) = self.world_loader.broken.remove(idx);
+                        self.show_load_error( None, dir, &error );
+                    }
+                    None => self.update_ui_mode( ui::UIMode::WorldSelection ),
+                }
+            }
+            ui::UIMode::LoadErrorAction => {
+                let action = self.ui_core.load_error_action.take().unwrap_or( ui::world_ui::LoadErrorAction::Back );
+                self.do_load_error_action(action);
+            }
+            ui::UIMode::OpenWorldOptions => {
+                if let Some(name) = self.ui_core.world_options_name.clone() {
+                    self.open_world_options( &name, "".to_string() );
+                } else {
+                    self.update_ui_mode( ui::UIMode::WorldSelection );
+                }
+            }
+            ui::UIMode::WorldOptionsAction => {
+                if let ( Some(name), Some(action) ) = ( self.ui_core.world_options_name.clone(), self.ui_core.world_action.take() ) {
+                    self.do_world_action( &name, action );
+                } else {
+                    self.update_ui_mode( ui::UIMode::WorldSelection );
+                }
             }
             _ => {
 
@@ -342,6 +703,37 @@ impl<'a> State<'a> {
         self.mouse_pressed.right_just_now = false;
     }
 
+    fn take_screenshot(&mut self, request: screenshot::ScreenshotRequest, frame: &wgpu::Texture) {
+        let can_copy = self.config.usage.contains( wgpu::TextureUsages::COPY_SRC );
+        let img = match ( request, &self.world_render ) {
+            ( screenshot::ScreenshotRequest::Frame, _ ) if can_copy => screenshot::capture_frame( frame, &self.device, &self.queue ),
+            ( screenshot::ScreenshotRequest::Frame, Some(wr) ) => {
+                let window = ( self.config.width, self.config.height );
+                let plain = settings::ScreenshotSettings { scale: 1, supersample: 1 };
+                screenshot::capture_high_res( wr, window, &plain, &self.device, &self.queue )
+            }
+            ( screenshot::ScreenshotRequest::HighRes, Some(wr) ) => {
+                let window = ( self.config.width, self.config.height );
+                screenshot::capture_high_res( wr, window, &self.settings.screenshot, &self.device, &self.queue )
+            }
+            _ => {
+                self.ui_core.show_hud_message( "Nothing to take a screenshot of".to_string() );
+                return;
+            }
+        };
+
+        match img.and_then( |img| screenshot::save( &img, self.world_render.as_ref() ) ) {
+            Ok(path) => {
+                log::info!("saved screenshot {}", path.display());
+                self.ui_core.show_hud_message( format!( "Screenshot saved to {}", path.display() ) );
+            }
+            Err(e) => {
+                log::error!("failed to take a screenshot: {}", e);
+                self.ui_core.show_hud_message( format!( "Screenshot failed: {}", e ) );
+            }
+        }
+    }
+
     fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
 
         let output = self.surface.get_current_texture()?;
@@ -360,6 +752,11 @@ impl<'a> State<'a> {
         // submit will accept anything that implements IntoIter
         let draw_vec = vec![ world_encoder.finish(), ui_encoder.expect("Error rendering the UI").finish() ];
         self.queue.submit( draw_vec.into_iter() );
+
+        if let Some(request) = self.screenshot_request.take() {
+            self.take_screenshot( request, &output.texture );
+        }
+
         output.present();
 
         Ok(())
@@ -368,8 +765,16 @@ impl<'a> State<'a> {
 
 }
 
+// how many missing block names fit in the HUD before the rest are only counted
+const MAX_LISTED_BLOCKS: usize = 3;
 
-
+fn unknown_blocks_message(names: &[String]) -> String {
+    let mut listed = names.iter().take(MAX_LISTED_BLOCKS).cloned().collect::<Vec<String>>().join(", ");
+    if names.len() > MAX_LISTED_BLOCKS {
+        listed = format!( "{} and {} more", listed, names.len() - MAX_LISTED_BLOCKS );
+    }
+    format!( "Blocks missing from block.toml won't be drawn: {}", listed )
+}
 
 
 
@@ -418,6 +823,30 @@ pub async fn run() {
                             state.update_ui_mode( ui::UIMode::MainTitle );
                         }
                     }
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                state: ElementState::Pressed,
+                                physical_key: PhysicalKey::Code(KeyCode::F2),
+                                repeat: false,
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.screenshot_request = Some( screenshot::ScreenshotRequest::Frame );
+                    }
+                    WindowEvent::KeyboardInput {
+                        event:
+                            KeyEvent {
+                                state: ElementState::Pressed,
+                                physical_key: PhysicalKey::Code(KeyCode::F3),
+                                repeat: false,
+                                ..
+                            },
+                        ..
+                    } => {
+                        state.screenshot_request = Some( screenshot::ScreenshotRequest::HighRes );
+                    }
                     WindowEvent::Resized(physical_size) => {
                         state.resize(*physical_size);
                     }
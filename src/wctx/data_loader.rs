@@ -10,27 +10,19 @@ use std::path::PathBuf;
 use figment::Figment;
 use figment::providers::{Format, Toml};
 
-use serde::Deserialize;
-
-#[derive(Debug, PartialEq, Clone, Deserialize)]
-pub struct Config {
-    block: Vec<BlockPlan>,
-}
-
-#[derive(Debug, PartialEq, Clone, Deserialize)]
-pub struct BlockPlan {
-    pretty_name: String,
-    textures: Vec<String>,
-    shape_name: String,
-    transparent: Option<bool>,
-}
+pub use crate::engine::block_data::{
+    Config,
+    BLOCK_DATA_PATH,
+    load_block_names,
+    load_block_registry,
+};
 
 pub struct BlockLoader {
-    pub block_registry: crate::wctx::block::BlockRegistry,
+    pub block_registry: crate::engine::block::BlockRegistry,
     block_names: HashMap<String, u32>,
     pub texture_atlas: crate::wctx::atlas_tex::AtlasTexture,
     texture_names: HashMap<String, u32>,
-    pub shape_registry: crate::wctx::block::BlockShapeRegistry,
+    pub shape_registry: crate::engine::block::BlockShapeRegistry,
     shape_names: HashMap<String, u32>,
     figment: Figment,
     config: Option<Config>,
@@ -38,11 +30,11 @@ pub struct BlockLoader {
 
 impl BlockLoader {
     pub fn create(device: &wgpu::Device, queue: &wgpu::Queue) -> BlockLoader {
-        let block_registry = crate::wctx::block::BlockRegistry::new();
+        let block_registry = crate::engine::block::BlockRegistry::new();
         let block_names = HashMap::<String, u32>::new();
         let texture_atlas = crate::wctx::atlas_tex::AtlasTexture::new(&device, &queue, wgpu::TextureFormat::R8Uint, (16, 16));
         let texture_names = HashMap::<String, u32>::new();
-        let shape_registry = crate::wctx::block::BlockShapeRegistry::new();
+        let shape_registry = crate::engine::block::BlockShapeRegistry::new();
         let shape_names = HashMap::<String, u32>::new();
         let figment = Figment::new();
         let config = None;
@@ -59,7 +51,7 @@ impl BlockLoader {
         }
     }
 
-    pub fn submit_blockshape_direct(&mut self, bs: crate::wctx::block::BlockShape, name: &String ) -> u32 {
+    pub fn submit_blockshape_direct(&mut self, bs: crate::engine::block::BlockShape, name: &String ) -> u32 {
         let idx = self.shape_registry.add(bs);
         self.shape_names.insert( name.clone(), idx );
         idx
@@ -95,7 +87,7 @@ impl BlockLoader {
                 None => false
             };
 
-            self.block_registry.add( *shape_idx, pretty_name, tex_indices, transparent );
+            self.block_registry.add( bp.name.clone(), *shape_idx, pretty_name, tex_indices, transparent ).ok_or(Error::new::<String>(std::io::ErrorKind::Other, format!("Duplicate block name {}!", bp.name) ))?;
         }
 
         Ok(())
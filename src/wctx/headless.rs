@@ -0,0 +1,254 @@
+use std::io::Error;
+use std::path::PathBuf;
+
+use crate::wctx::camera;
+use crate::wctx::world;
+use crate::wctx::world_loader::WorldPreview;
+use crate::wctx::world_saver::{self, WorldSaver};
+
+const USAGE: &str = "usage: sgr_cubes_render <world name or folder> [options]
+
+renders a world save to PNG files without opening a window.
+run it from the game folder, block data is read from res/.
+
+options:
+  --out <folder>              where the pictures go, default is the current folder
+  --size <width>x<height>     picture size in pixels, default 1280x720
+  --view <view>               can be given several times, default is player
+                                player        the camera stored in the save
+                                top, isometric, front, side
+                                              orthographic view of the whole world
+                                angle:<yaw>:<pitch>
+                                              orthographic view from any angle, in degrees
+                                camera:<x>,<y>,<z>,<yaw>,<pitch>
+                                              perspective view from a given camera
+  --software                  use the fallback software adapter even if there is a GPU";
+
+#[derive(Clone, Debug, PartialEq)]
+enum RenderView {
+    Player,
+    Ortho{ label: String, yaw: f32, pitch: f32 },
+    Camera{ position: [f32; 3], yaw: f32, pitch: f32 },
+}
+
+impl RenderView {
+    fn parse(text: &str) -> Result<RenderView, String> {
+        match text {
+            "player" => return Ok( RenderView::Player ),
+            "top" => return Ok( RenderView::Ortho{ label: text.to_string(), yaw: 0.0, pitch: 90.0 } ),
+            "isometric" => return Ok( RenderView::Ortho{ label: text.to_string(), yaw: -45.0, pitch: 35.264 } ),
+            "front" => return Ok( RenderView::Ortho{ label: text.to_string(), yaw: 0.0, pitch: 0.0 } ),
+            "side" => return Ok( RenderView::Ortho{ label: text.to_string(), yaw: -90.0, pitch: 0.0 } ),
+            _ => {}
+        }
+
+        if let Some(angles) = text.strip_prefix("angle:") {
+            let nums = parse_numbers( angles, ':' )?;
+            if nums.len() != 2 {
+                return Err( format!( "'{}' needs a yaw and a pitch", text ) );
+            }
+            return Ok( RenderView::Ortho{ label: format!( "angle_{}_{}", nums[0], nums[1] ), yaw: nums[0], pitch: nums[1] } );
+        }
+
+        if let Some(cam) = text.strip_prefix("camera:") {
+            let nums = parse_numbers( cam, ',' )?;
+            if nums.len() != 5 {
+                return Err( format!( "'{}' needs x, y, z, yaw and pitch", text ) );
+            }
+            return Ok( RenderView::Camera{ position: [nums[0], nums[1], nums[2]], yaw: nums[3], pitch: nums[4] } );
+        }
+
+        Err( format!( "unknown view '{}'", text ) )
+    }
+
+    fn label(&self) -> String {
+        match self {
+            RenderView::Player => "player".to_string(),
+            RenderView::Ortho{ label, .. } => label.clone(),
+            RenderView::Camera{ position, yaw, pitch } => format!( "camera_{}_{}_{}_{}_{}", position[0], position[1], position[2], yaw, pitch ),
+        }
+    }
+}
+
+fn parse_numbers(text: &str, sep: char) -> Result<Vec<f32>, String> {
+    text.split(sep)
+        .map( |n| n.trim().parse::<f32>().map_err( |_| format!( "'{}' is not a number", n ) ) )
+        .collect()
+}
+
+struct RenderArgs {
+    world: String,
+    out: PathBuf,
+    width: u32,
+    height: u32,
+    views: Vec<RenderView>,
+    software: bool,
+}
+
+impl RenderArgs {
+    fn parse(args: Vec<String>) -> Result<RenderArgs, String> {
+        let mut world = None;
+        let mut out = PathBuf::from(".");
+        let (mut width, mut height) = (1280, 720);
+        let mut views = Vec::new();
+        let mut software = false;
+
+        let mut iter = args.into_iter();
+        while let Some(arg) = iter.next() {
+            match arg.as_str() {
+                "--out" => out = PathBuf::from( iter.next().ok_or("--out needs a folder")? ),
+                "--size" => {
+                    let size = iter.next().ok_or("--size needs <width>x<height>")?;
+                    let (w, h) = size.split_once('x').ok_or( format!( "'{}' is not <width>x<height>", size ) )?;
+                    width = w.parse::<u32>().map_err( |_| format!( "'{}' is not a width", w ) )?;
+                    height = h.parse::<u32>().map_err( |_| format!( "'{}' is not a height", h ) )?;
+                    if width == 0 || height == 0 {
+                        return Err( "the picture can't be empty".to_string() );
+                    }
+                }
+                "--view" => views.push( RenderView::parse( &iter.next().ok_or("--view needs a view")? )? ),
+                "--software" => software = true,
+                "--help" | "-h" => return Err( USAGE.to_string() ),
+                _ if arg.starts_with("--") => return Err( format!( "unknown option '{}'\n\n{}", arg, USAGE ) ),
+                _ if world.is_none() => world = Some(arg),
+                _ => return Err( format!( "only one world can be rendered at a time, got '{}'\n\n{}", arg, USAGE ) ),
+            }
+        }
+
+        if views.is_empty() {
+            views.push( RenderView::Player );
+        }
+
+        Ok( RenderArgs{ world: world.ok_or( USAGE.to_string() )?, out, width, height, views, software } )
+    }
+}
+
+// no surface to present to, so any adapter will do. the fallback adapter is a software
+// renderer, used when there is no GPU at all or when it is asked for
+async fn request_device(software: bool) -> Result<(wgpu::Device, wgpu::Queue), Error> {
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: wgpu::Backends::all(),
+        ..Default::default()
+    });
+
+    let mut adapter = None;
+    if !software {
+        adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: false,
+            },
+        ).await;
+    }
+    if adapter.is_none() {
+        adapter = instance.request_adapter(
+            &wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                compatible_surface: None,
+                force_fallback_adapter: true,
+            },
+        ).await;
+    }
+    let adapter = adapter.ok_or( Error::new( std::io::ErrorKind::NotFound, "no graphics adapter, not even a software one" ) )?;
+    log::info!("rendering with {:?}", adapter.get_info());
+
+    let mut feature_list = wgpu::Features::empty();
+    feature_list.insert( wgpu::Features::CLEAR_TEXTURE );
+    feature_list.insert( wgpu::Features::PUSH_CONSTANTS );
+
+    let mut lim = wgpu::Limits::default();
+    lim.max_push_constant_size = 128;
+
+    adapter.request_device(
+        &wgpu::DeviceDescriptor {
+            required_features: feature_list,
+            required_limits: lim,
+            label: None,
+            memory_hints: wgpu::MemoryHints::Performance
+        },
+        None,
+    ).await.map_err( |e| Error::new( std::io::ErrorKind::Unsupported, e.to_string() ) )
+}
+
+fn render_world(args: &RenderArgs) -> Result<Vec<PathBuf>, Error> {
+    let world_dir = WorldSaver::find_world_dir(&args.world);
+    let info = world_saver::WorldInfo::read(&world_dir).map_err( |e| Error::new( e.kind(), format!( "can't read {}: {}", world_dir.display(), e ) ) )?;
+    let preview = WorldPreview {
+        path_name: world_dir.to_string_lossy().to_string(),
+        info: info.clone(),
+        has_preview: false,
+    };
+    let mut wss = preview.load_world().map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+    wss.chunk_manager.load_all()?;
+
+    let (device, queue) = pollster::block_on( request_device(args.software) )?;
+
+    // stands in for the window surface, the pipelines are built for this format
+    let config = wgpu::SurfaceConfiguration {
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+        format: wgpu::TextureFormat::Rgba8UnormSrgb,
+        width: args.width,
+        height: args.height,
+        present_mode: wgpu::PresentMode::AutoNoVsync,
+        alpha_mode: wgpu::CompositeAlphaMode::Auto,
+        view_formats: vec![],
+        desired_maximum_frame_latency: 2,
+    };
+    // only reads the world, so no edit journal
+    let mut wr = world::WorldRender::new( &device, &queue, &config, wss, info.name.clone(), &world_dir, false );
+    wr.update_chunks();
+
+    std::fs::create_dir_all(&args.out)?;
+
+    let mut written = Vec::new();
+    for view in &args.views {
+        let matrix = match view {
+            RenderView::Player => wr.view_proj(),
+            RenderView::Camera{ position, yaw, pitch } => {
+                let cam = camera::Camera::new( *position, cgmath::Deg(*yaw), cgmath::Deg(*pitch) );
+                let projection = camera::Projection::new( args.width, args.height, cgmath::Deg(45.0), 0.1, 100.0 );
+                projection.calc_matrix() * cam.calc_matrix()
+            }
+            RenderView::Ortho{ yaw, pitch, .. } => {
+                // squeeze the fitted map into the picture without stretching it
+                let (matrix, aspect) = WorldSaver::map_matrix( wr.world.size(), *yaw, *pitch );
+                let picture_aspect = args.width as f32 / args.height as f32;
+                let (sx, sy) = if aspect > picture_aspect { ( 1.0, picture_aspect / aspect ) } else { ( aspect / picture_aspect, 1.0 ) };
+                cgmath::Matrix4::from_nonuniform_scale( sx, sy, 1.0 ) * matrix
+            }
+        };
+
+        let img = WorldSaver::render_view_image( &wr, matrix, args.width, args.height, &device, &queue )?;
+        let mut path = args.out.clone();
+        path.push( format!( "{}_{}.png", info.name, view.label() ) );
+        std::fs::write( &path, WorldSaver::encode_png(&img)? )?;
+        written.push(path);
+    }
+
+    Ok(written)
+}
+
+// entry point of the sgr_cubes_render binary, returns the process exit code
+pub fn run_render(args: Vec<String>) -> i32 {
+    let args = match RenderArgs::parse(args) {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            return 2;
+        }
+    };
+
+    match render_world(&args) {
+        Ok(written) => {
+            for path in written {
+                println!("{}", path.display());
+            }
+            0
+        }
+        Err(e) => {
+            eprintln!("render failed: {}", e);
+            1
+        }
+    }
+}
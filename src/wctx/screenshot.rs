@@ -0,0 +1,127 @@
+use std::io::Error;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use fs_extra::dir::create_all;
+
+use crate::wctx::settings::ScreenshotSettings;
+use crate::wctx::world::WorldRender;
+use crate::wctx::world_saver::WorldSaver;
+
+// what was asked for with the screenshot keys, handled on the next rendered frame
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub enum ScreenshotRequest {
+    // exactly what is on screen, UI included
+    Frame,
+    // the world alone, rendered bigger and scaled down
+    HighRes,
+}
+
+pub fn screenshot_dir() -> PathBuf {
+    let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
+    let mut pbuf = pdirs.data_dir().to_path_buf();
+    pbuf.push( "screenshots/" );
+    pbuf
+}
+
+// copies the swapchain texture, the surface has to be configured with COPY_SRC
+pub fn capture_frame(texture: &wgpu::Texture, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<image::RgbaImage, Error> {
+    let mut pixels = WorldSaver::read_texture( texture, device, queue )?;
+
+    match texture.format() {
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb => {
+            for px in pixels.chunks_exact_mut(4) {
+                px.swap(0, 2);
+            }
+        }
+        wgpu::TextureFormat::Rgba8Unorm | wgpu::TextureFormat::Rgba8UnormSrgb => {}
+        f => return Err( Error::new( std::io::ErrorKind::Unsupported, format!( "can't take screenshots of a {:?} surface", f ) ) ),
+    }
+
+    // the window is opaque, whatever alpha ended up in the frame is meaningless
+    for px in pixels.chunks_exact_mut(4) {
+        px[3] = 255;
+    }
+
+    image::RgbaImage::from_raw( texture.width(), texture.height(), pixels ).ok_or( Error::new( std::io::ErrorKind::Other, "readback buffer has the wrong size" ) )
+}
+
+// renders the world from the player's camera at scale * supersample times the window size,
+// then scales it down to scale times the window size
+pub fn capture_high_res(world_render: &WorldRender, window: (u32, u32), settings: &ScreenshotSettings, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<image::RgbaImage, Error> {
+    let scale = settings.scale.max(1);
+    let max_side = device.limits().max_texture_dimension_2d;
+    let factor = ( scale * settings.supersample.max(1) ).min( max_side / window.0.max(window.1).max(1) ).max(1);
+
+    let mut img = WorldSaver::render_view_image( world_render, world_render.view_proj(), window.0 * factor, window.1 * factor, device, queue )?;
+    for px in img.pixels_mut() {
+        px[3] = 255;
+    }
+
+    let (width, height) = ( window.0 * scale.min(factor), window.1 * scale.min(factor) );
+    if (width, height) == img.dimensions() {
+        Ok(img)
+    } else {
+        Ok( image::imageops::resize( &img, width, height, image::imageops::FilterType::Lanczos3 ) )
+    }
+}
+
+// writes the picture into the screenshots folder, tagged with where it was taken
+pub fn save(img: &image::RgbaImage, world_render: Option<&WorldRender>) -> Result<PathBuf, Error> {
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+
+    let mut text = vec![
+        ( "Software".to_string(), format!( "SGR Cubes {}", env!("CARGO_PKG_VERSION") ) ),
+        ( "Timestamp".to_string(), now.as_secs().to_string() ),
+    ];
+    if let Some(wr) = world_render {
+        let pos = wr.world.camera.position;
+        text.push( ( "World".to_string(), wr.world_name.clone() ) );
+        text.push( ( "Camera".to_string(), format!( "{:.2} {:.2} {:.2}", pos.x, pos.y, pos.z ) ) );
+    }
+
+    let dir = screenshot_dir();
+    create_all( &dir, false ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+
+    let mut path = dir.clone();
+    path.push( format!( "screenshot_{}_{:03}.png", now.as_secs(), now.subsec_millis() ) );
+
+    let png = encode_png_with_text( img, &text )?;
+    std::fs::write( &path, png )?;
+    Ok(path)
+}
+
+fn encode_png_with_text(img: &image::RgbaImage, text: &[(String, String)]) -> Result<Vec<u8>, Error> {
+    let to_io = |e: png::EncodingError| Error::new( std::io::ErrorKind::Other, e.to_string() );
+
+    let mut png = Vec::<u8>::new();
+    {
+        let mut encoder = png::Encoder::new( &mut png, img.width(), img.height() );
+        encoder.set_color( png::ColorType::Rgba );
+        encoder.set_depth( png::BitDepth::Eight );
+        // tEXt only holds Latin-1, world names can be any text
+        for (key, value) in text {
+            encoder.add_itxt_chunk( key.clone(), value.clone() ).map_err(to_io)?;
+        }
+        let mut writer = encoder.write_header().map_err(to_io)?;
+        writer.write_image_data( img.as_raw() ).map_err(to_io)?;
+        writer.finish().map_err(to_io)?;
+    }
+    Ok(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn text_outside_latin1() {
+        let img = image::RgbaImage::from_pixel( 2, 2, image::Rgba( [ 10, 20, 30, 255 ] ) );
+        let text = vec![ ( "World".to_string(), "城 🌍".to_string() ) ];
+        let png = encode_png_with_text( &img, &text ).unwrap();
+
+        let reader = png::Decoder::new( std::io::Cursor::new(png) ).read_info().unwrap();
+        let chunk = reader.info().utf8_text.iter().find( |c| c.keyword == "World" ).unwrap();
+        assert_eq!( chunk.get_text().unwrap(), "城 🌍" );
+    }
+}
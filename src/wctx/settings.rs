@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+
+use figment::Figment;
+use figment::providers::{Format, Toml, Serialized};
+
+use serde::{
+    Serialize,
+    Deserialize
+};
+
+// player settings, read from settings.toml in the config directory.
+// anything missing from the file keeps its default.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct GameSettings {
+    // seconds between autosaves while a world is open, 0 turns autosaving off
+    pub autosave_seconds: u64,
+    #[serde(default)]
+    pub preview: PreviewSettings,
+    #[serde(default)]
+    pub map: MapSettings,
+    #[serde(default)]
+    pub screenshot: ScreenshotSettings,
+}
+
+impl Default for GameSettings {
+    fn default() -> Self {
+        Self {
+            autosave_seconds: 120,
+            preview: PreviewSettings::default(),
+            map: MapSettings::default(),
+            screenshot: ScreenshotSettings::default(),
+        }
+    }
+}
+
+// how the world thumbnails in the world list are rendered, the [preview] table of settings.toml
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct PreviewSettings {
+    // width and height of preview.png in pixels
+    pub resolution: u32,
+    // camera angles in degrees: yaw turns around the vertical axis, pitch tilts down towards the ground
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl Default for PreviewSettings {
+    fn default() -> Self {
+        Self {
+            resolution: 512,
+            yaw: -45.0,
+            // the classic isometric angle, atan(1/sqrt(2))
+            pitch: 35.264,
+        }
+    }
+}
+
+// the high resolution screenshot key, the [screenshot] table of settings.toml
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ScreenshotSettings {
+    // size of the picture as a multiple of the window size
+    pub scale: u32,
+    // how many rendered pixels go into one saved pixel along each side, 1 turns supersampling off
+    pub supersample: u32,
+}
+
+impl Default for ScreenshotSettings {
+    fn default() -> Self {
+        Self {
+            scale: 2,
+            supersample: 2,
+        }
+    }
+}
+
+impl GameSettings {
+    pub fn settings_path() -> PathBuf {
+        let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
+        let mut pbuf = pdirs.config_dir().to_path_buf();
+        pbuf.push("settings.toml");
+        pbuf
+    }
+
+    pub fn load() -> GameSettings {
+        let figment = Figment::from( Serialized::defaults( GameSettings::default() ) ).merge( Toml::file( Self::settings_path() ) );
+        match figment.extract() {
+            Ok(settings) => settings,
+            Err(e) => {
+                log::warn!("failed to read settings, using defaults: {}", e);
+                GameSettings::default()
+            }
+        }
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MapView {
+    Top,
+    Isometric,
+    Front,
+    Side,
+    // uses the yaw and pitch from MapSettings
+    Custom,
+}
+
+// exported world maps, the [map] table of settings.toml
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MapSettings {
+    pub view: MapView,
+    // degrees, only used by the custom view
+    pub yaw: f32,
+    pub pitch: f32,
+    // length of the longer side of the whole map in pixels, at most 16384
+    pub size: u32,
+    // maps bigger than this are split into several PNG tiles, raised so there are no more than 4 tiles along a side
+    pub tile_size: u32,
+}
+
+impl Default for MapSettings {
+    fn default() -> Self {
+        Self {
+            view: MapView::Top,
+            yaw: -45.0,
+            pitch: 35.264,
+            size: 4096,
+            tile_size: 2048,
+        }
+    }
+}
+
+impl MapSettings {
+    // ( yaw, pitch ) in degrees
+    pub fn angles(&self) -> (f32, f32) {
+        match self.view {
+            MapView::Top => (0.0, 90.0),
+            MapView::Isometric => (-45.0, 35.264),
+            MapView::Front => (0.0, 0.0),
+            MapView::Side => (-90.0, 0.0),
+            MapView::Custom => (self.yaw, self.pitch),
+        }
+    }
+}
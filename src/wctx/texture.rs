@@ -1,7 +1,6 @@
  
 use image::GenericImageView;
 use anyhow::*;
-use image::Luma;
 
 pub struct Texture {
     #[allow(unused)]
@@ -208,24 +207,7 @@ impl Texture {
         let size = wgpu::Extent3d{ width: dimensions.0, height: dimensions.1, depth_or_array_layers: 1 };
 
         let pal = palette.to_rgba8();
-        let pal_dim = pal.dimensions();
-
-        let convert_fn = | x: u32, y: u32 | -> Luma<u8> {
-            let ic = rgba.get_pixel(x, y);
-            let mut d = 100000.0;
-            let mut out_index: u8 = 0;
-            for i in 0..( pal_dim.0 * pal_dim.1 ) {
-                let pc = pal.get_pixel(i % pal_dim.0, i / pal_dim.0);
-                let nd = num::pow( ic.0[0] as f32 - pc.0[0] as f32, 2 ) + num::pow( ic.0[1] as f32 - pc.0[1] as f32, 2 ) + num::pow( ic.0[2] as f32 - pc.0[2] as f32, 2 );
-                if nd < d {
-                    d = nd;
-                    out_index = i as u8;
-                }
-            }
-            image::Luma::<u8>{ 0: [out_index] }
-        };
-
-        let out_img = image::GrayImage::from_fn(dimensions.0, dimensions.1, convert_fn);
+        let out_img = crate::engine::palette::palettize(&rgba, &pal);
 
         let texture = device.create_texture(
             &wgpu::TextureDescriptor {
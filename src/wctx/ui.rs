@@ -34,12 +34,22 @@ pub enum UIMode {
     Gameplay,
     PauseMenu,
     QuitGameplay,
+    ExportMap,
     LoadWorld,
     MainTitle,
     WorldSelection,
     Quit,
     CreateWorldMenu,
     CreateWorld,
+    OpenWorldOptions,
+    WorldOptions,
+    WorldOptionsAction,
+    ImportWorld,
+    GeneratePreview,
+    Loading,
+    OpenLoadError,
+    LoadError,
+    LoadErrorAction,
 }
 
 pub struct UICore {
@@ -58,6 +68,7 @@ pub struct UICore {
     menu_copy_tex: crate::wctx::texture::Texture,
     menu_copy_bind_group: wgpu::BindGroup,
     ret: Dynamic<bool>,
+    export_map: Dynamic<bool>,
     quit: Dynamic<bool>,
     main_title: cushy::window::VirtualWindow,
     enter: Dynamic<bool>,
@@ -68,8 +79,30 @@ pub struct UICore {
     world_select_ui: world_ui::WorldSelectUI,
     pub world_selected_name: Option<String>,
     world_create_ui: world_ui::WorldCreationUI,
+    pub world_create_settings: Option<crate::engine::world_gen::WorldGenSettings>,
+    pub world_create_template: Option<std::path::PathBuf>,
+    world_options_ui: Option<world_ui::WorldOptionsUI>,
+    pub world_options_name: Option<String>,
+    pub world_action: Option<world_ui::WorldOptionsAction>,
+    load_error_ui: Option<world_ui::LoadErrorUI>,
+    loading_ui: world_ui::LoadingUI,
+    // the world the error dialog is about: its name if it is listed, and its directory
+    pub load_error_name: Option<String>,
+    pub load_error_dir: Option<std::path::PathBuf>,
+    pub load_error_action: Option<world_ui::LoadErrorAction>,
+    pub import_path: Option<String>,
+    hud_screen: cushy::window::VirtualWindow,
+    hud_text: Dynamic<String>,
+    hud_until: Option<std::time::Instant>,
+    // shown once play starts, for news from loading that the pause menu would hide
+    hud_queued: Option<String>,
 }
 
+// how long the "saved" indicator stays up after an autosave
+const SAVED_INDICATOR_TIME: std::time::Duration = std::time::Duration::from_secs(2);
+// other HUD messages stay up longer, they may be shown while the game is paused
+const HUD_MESSAGE_TIME: std::time::Duration = std::time::Duration::from_secs(6);
+
 impl UICore {
     pub fn get_gfx<'a>(&'a mut self, device: &'a wgpu::Device, queue: &'a wgpu::Queue) -> cushy::kludgine::Graphics<'a> {
         self.pause_buttonmenu.graphics(device, queue)
@@ -309,7 +342,9 @@ impl UICore {
 
         let do_return = Dynamic::new(false);
         let do_quit = Dynamic::new(false);
+        let do_export_map = Dynamic::new(false);
         let ret = do_return.clone();
+        let export_map = do_export_map.clone();
         let quit = do_quit.clone();
 
         menub_list.push( widgets::Expand::weighted(8, widgets::Space::clear() ) );
@@ -324,6 +359,11 @@ impl UICore {
         //options_button = options_button.on_click( |click| {  } );
         //menub_list.push(widgets::Expand::new(options_button));
 
+        let mut map_button = widgets::Button::new( widgets::Label::<&str>::new("EXPORT MAP") );
+        map_button = map_button.kind( widgets::button::ButtonKind::Solid );
+        map_button = map_button.on_click( move |_click| { do_export_map.set(true); } );
+        menub_list.push( widgets::Expand::weighted(4, map_button) );
+
         let mut quit_button = widgets::Button::new( widgets::Label::<&str>::new("QUIT") );
         quit_button = quit_button.kind( widgets::button::ButtonKind::Solid );
         quit_button = quit_button.on_click( move |_click| { do_quit.set(true); } );
@@ -568,8 +608,8 @@ impl UICore {
 
         let test_preview = crate::wctx::world_loader::WorldPreview {
             path_name: "test".to_string(),
-            name: "Test World".to_string(),
-            size: 0
+            info: crate::wctx::world_saver::WorldInfo::new( "Test World".to_string(), &crate::engine::world_gen::WorldGenSettings::new(0) ),
+            has_preview: true,
         };
         let world_select_ui = world_ui::WorldSelectUI::new(vec![ (test_preview, cushy::kludgine::Texture::new(
             &pause_buttonmenu.graphics(device, queue),
@@ -577,9 +617,27 @@ impl UICore {
             wgpu::TextureFormat::Rgba8UnormSrgb,
             wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
             wgpu::FilterMode::Linear
-        )) ], config, device, queue);
+        )) ], Vec::new(), "".to_string(), config, device, queue);
 
-        let world_create_ui = world_ui::WorldCreationUI::new(config, device, queue);
+        let world_create_ui = world_ui::WorldCreationUI::new(&Vec::new(), config, device, queue);
+        let loading_ui = world_ui::LoadingUI::new(config, device, queue);
+
+        // small gameplay overlay in the top right corner
+        let hud_text = Dynamic::new( "Saved".to_string() );
+        let saved_label = widgets::Container::new( widgets::Label::new( hud_text.clone() ) )
+        .background_color( styles::Color::new(0, 0, 0, 160) );
+        let hud_align = widgets::Align::new(
+            styles::Edges {
+                top: styles::FlexibleDimension::Dimension( Dimension::Px( Px::new(16))),
+                bottom: styles::FlexibleDimension::Auto,
+                left: styles::FlexibleDimension::Auto,
+                right: styles::FlexibleDimension::Dimension( Dimension::Px( Px::new(16))),
+            },
+            saved_label
+        );
+        let mut hudbuilder = cushy::window::StandaloneWindowBuilder::new( hud_align ).transparent();
+        hudbuilder = hudbuilder.size( figures::Size { width: config.width, height: config.height } );
+        let hud_screen = hudbuilder.finish_virtual(device, queue);
 
         Self{
             crosshair_tex,
@@ -597,6 +655,7 @@ impl UICore {
             menu_copy_tex,
             menu_copy_bind_group,
             ret,
+            export_map,
             quit,
             main_title,
             enter,
@@ -607,11 +666,69 @@ impl UICore {
             world_select_ui,
             world_selected_name: None,
             world_create_ui,
+            world_create_settings: None,
+            world_create_template: None,
+            world_options_ui: None,
+            world_options_name: None,
+            world_action: None,
+            load_error_ui: None,
+            loading_ui,
+            load_error_name: None,
+            load_error_dir: None,
+            load_error_action: None,
+            import_path: None,
+            hud_screen,
+            hud_text,
+            hud_until: None,
+            hud_queued: None,
+        }
+    }
+
+    pub fn show_saved_indicator(&mut self) {
+        self.hud_text.set( "Saved".to_string() );
+        self.hud_until = Some( std::time::Instant::now() + SAVED_INDICATOR_TIME );
+    }
+
+    pub fn show_hud_message(&mut self, text: String) {
+        self.hud_text.set(text);
+        self.hud_until = Some( std::time::Instant::now() + HUD_MESSAGE_TIME );
+    }
+
+    pub fn queue_hud_message(&mut self, text: String) {
+        self.hud_queued = Some(text);
+    }
+
+    pub fn show_queued_hud_message(&mut self) {
+        if let Some(text) = self.hud_queued.take() {
+            self.show_hud_message(text);
         }
     }
 
-    pub fn update_world_list(&mut self, worlds: Vec<(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)>, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) {
-        self.world_select_ui = world_ui::WorldSelectUI::new(worlds, config, device, queue);
+    pub fn update_world_list(&mut self, worlds: Vec<(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)>, broken: Vec<std::path::PathBuf>, status: String, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.world_select_ui = world_ui::WorldSelectUI::new(worlds, broken, status, config, device, queue);
+    }
+
+    // the template list can change between visits, so the screen is rebuilt each time
+    pub fn open_world_creation(&mut self, templates: &[crate::wctx::world_saver::TemplateInfo], config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.world_create_ui = world_ui::WorldCreationUI::new(templates, config, device, queue);
+    }
+
+    // back on the creation screen after a failed attempt, with the reason
+    pub fn show_create_error(&self, message: String) {
+        self.world_create_ui.status.set(message);
+    }
+
+    pub fn set_loading_progress(&self, status: String, fraction: f32) {
+        self.loading_ui.status.set(status);
+        self.loading_ui.progress.set(fraction);
+    }
+
+    pub fn show_load_error(&mut self, name: String, message: String, can_restore: bool, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.load_error_ui = Some( world_ui::LoadErrorUI::new(name, message, can_restore, config, device, queue) );
+    }
+
+    pub fn open_world_options(&mut self, name: String, backups: &[crate::wctx::world_saver::BackupInfo], status: String, confirm_delete: bool, is_template: bool, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) {
+        self.world_options_ui = Some( world_ui::WorldOptionsUI::new(name, backups, status, confirm_delete, is_template, config, device, queue) );
     }
 
     pub fn resize(&mut self, new_size: winit::dpi::PhysicalSize<u32>, device: &wgpu::Device, queue: &wgpu::Queue) {
@@ -666,6 +783,14 @@ impl UICore {
         self.main_title.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
         self.world_select_ui.screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
         self.world_create_ui.screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
+        if let Some(options_ui) = &mut self.world_options_ui {
+            options_ui.screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
+        }
+        self.loading_ui.screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
+        if let Some(error_ui) = &mut self.load_error_ui {
+            error_ui.screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
+        }
+        self.hud_screen.resize( figures::Size { width: UPx::new(new_size.width), height: UPx::new(new_size.height) }, 1.0, queue );
 
     }
 
@@ -675,6 +800,16 @@ impl UICore {
             UIMode::MainTitle => { self.main_title.cursor_moved(cushy::window::DeviceId::Virtual(0), figures::Point::new( figures::units::Px::new(position.x as i32), figures::units::Px::new(position.y as i32) ) ); }
             UIMode::WorldSelection => { self.world_select_ui.screen.cursor_moved(cushy::window::DeviceId::Virtual(0), figures::Point::new( figures::units::Px::new(position.x as i32), figures::units::Px::new(position.y as i32) ) ); }
             UIMode::CreateWorldMenu => { self.world_create_ui.screen.cursor_moved(cushy::window::DeviceId::Virtual(0), figures::Point::new( figures::units::Px::new(position.x as i32), figures::units::Px::new(position.y as i32) ) ); }
+            UIMode::WorldOptions => {
+                if let Some(options_ui) = &mut self.world_options_ui {
+                    options_ui.screen.cursor_moved(cushy::window::DeviceId::Virtual(0), figures::Point::new( figures::units::Px::new(position.x as i32), figures::units::Px::new(position.y as i32) ) );
+                }
+            }
+            UIMode::LoadError => {
+                if let Some(error_ui) = &mut self.load_error_ui {
+                    error_ui.screen.cursor_moved(cushy::window::DeviceId::Virtual(0), figures::Point::new( figures::units::Px::new(position.x as i32), figures::units::Px::new(position.y as i32) ) );
+                }
+            }
             _ => {}
         }
     }
@@ -698,6 +833,16 @@ impl UICore {
             UIMode::MainTitle => { self.main_title.mouse_input(cushy::window::DeviceId::Virtual(0), kstate, kbutton); }
             UIMode::WorldSelection => { self.world_select_ui.screen.mouse_input(cushy::window::DeviceId::Virtual(0), kstate, kbutton); }
             UIMode::CreateWorldMenu => { self.world_create_ui.screen.mouse_input(cushy::window::DeviceId::Virtual(0), kstate, kbutton); }
+            UIMode::WorldOptions => {
+                if let Some(options_ui) = &mut self.world_options_ui {
+                    options_ui.screen.mouse_input(cushy::window::DeviceId::Virtual(0), kstate, kbutton);
+                }
+            }
+            UIMode::LoadError => {
+                if let Some(error_ui) = &mut self.load_error_ui {
+                    error_ui.screen.mouse_input(cushy::window::DeviceId::Virtual(0), kstate, kbutton);
+                }
+            }
             _ => {}
         }
     }
@@ -723,6 +868,20 @@ impl UICore {
         };
 
         match mode {
+            UIMode::WorldSelection => {
+                self.world_select_ui.screen.keyboard_input(
+                    cushy::window::DeviceId::Virtual(0),
+                    cushy::window::KeyEvent {
+                        physical_key: k_pkey,
+                        logical_key: k_key,
+                        text: ktex,
+                        state: kstate,
+                        repeat: event.repeat,
+                        location: cushy::kludgine::app::winit::keyboard::KeyLocation::Standard,
+                    },
+                    true
+                ) == std::ops::ControlFlow::Break(cushy::widget::EventHandled)
+            }
             UIMode::CreateWorldMenu => {
                 self.world_create_ui.screen.keyboard_input(
                     cushy::window::DeviceId::Virtual(0),
@@ -737,6 +896,24 @@ impl UICore {
                     true
                 ) == std::ops::ControlFlow::Break(cushy::widget::EventHandled)
             }
+            UIMode::WorldOptions => {
+                if let Some(options_ui) = &mut self.world_options_ui {
+                    options_ui.screen.keyboard_input(
+                        cushy::window::DeviceId::Virtual(0),
+                        cushy::window::KeyEvent {
+                            physical_key: k_pkey,
+                            logical_key: k_key,
+                            text: ktex,
+                            state: kstate,
+                            repeat: event.repeat,
+                            location: cushy::kludgine::app::winit::keyboard::KeyLocation::Standard,
+                        },
+                        true
+                    ) == std::ops::ControlFlow::Break(cushy::widget::EventHandled)
+                } else {
+                    false
+                }
+            }
             _ => {false}
         }
     }
@@ -747,6 +924,9 @@ impl UICore {
                 if self.ret.get() {
                     self.ret.set(false);
                     Some(UIMode::Gameplay)
+                } else if self.export_map.get() {
+                    self.export_map.set(false);
+                    Some(UIMode::ExportMap)
                 } else if self.quit.get() {
                     self.quit.set(false);
                     Some(UIMode::QuitGameplay)
@@ -776,22 +956,76 @@ impl UICore {
                 } else if self.world_select_ui.create_world.get() {
                     self.world_select_ui.create_world.set(false);
                     Some(UIMode::CreateWorldMenu)
+                } else if self.world_select_ui.opt_world.get() != "".to_string() {
+                    self.world_options_name = Some( self.world_select_ui.opt_world.get().clone() );
+                    self.world_select_ui.opt_world.set("".to_string());
+                    Some( UIMode::OpenWorldOptions )
+                } else if self.world_select_ui.broken_world.get() != "".to_string() {
+                    self.load_error_name = None;
+                    self.load_error_dir = Some( std::path::PathBuf::from( self.world_select_ui.broken_world.get() ) );
+                    self.world_select_ui.broken_world.set("".to_string());
+                    Some( UIMode::OpenLoadError )
+                } else if self.world_select_ui.preview_world.get() != "".to_string() {
+                    self.world_selected_name = Some( self.world_select_ui.preview_world.get().clone() );
+                    self.world_select_ui.preview_world.set("".to_string());
+                    Some( UIMode::GeneratePreview )
+                } else if self.world_select_ui.import_world.get() {
+                    self.world_select_ui.import_world.set(false);
+                    let path = self.world_select_ui.import_path.get().trim().to_string();
+                    if path != "" {
+                        self.import_path = Some(path);
+                        Some( UIMode::ImportWorld )
+                    } else {
+                        None
+                    }
                 } else {
                     None
                 }
             }
+            UIMode::WorldOptions => {
+                if let Some(options_ui) = &self.world_options_ui {
+                    if options_ui.back.get() {
+                        options_ui.back.set(false);
+                        Some( UIMode::WorldSelection )
+                    } else if options_ui.action.get() != world_ui::WorldOptionsAction::None {
+                        self.world_action = Some( options_ui.action.get() );
+                        options_ui.action.set( world_ui::WorldOptionsAction::None );
+                        Some( UIMode::WorldOptionsAction )
+                    } else {
+                        None
+                    }
+                } else {
+                    Some( UIMode::WorldSelection )
+                }
+            }
+            UIMode::LoadError => {
+                if let Some(error_ui) = &self.load_error_ui {
+                    if error_ui.action.get() != world_ui::LoadErrorAction::None {
+                        self.load_error_action = Some( error_ui.action.get() );
+                        error_ui.action.set( world_ui::LoadErrorAction::None );
+                        Some( UIMode::LoadErrorAction )
+                    } else {
+                        None
+                    }
+                } else {
+                    Some( UIMode::WorldSelection )
+                }
+            }
             UIMode::CreateWorldMenu => {
                 if self.world_create_ui.create_world.get() && self.world_create_ui.world_name.get() != "".to_string() {
                     self.world_create_ui.create_world.set(false);
                     self.world_selected_name = Some(self.world_create_ui.world_name.get().clone());
-                    self.world_create_ui.world_name.set("".to_string());
+                    self.world_create_settings = Some(self.world_create_ui.get_settings());
+                    self.world_create_template = self.world_create_ui.get_template();
+                    // the inputs stay filled in, creating can still fail and come back here
+                    self.world_create_ui.status.set("".to_string());
                     Some( UIMode::CreateWorld )
                 } else if self.world_create_ui.create_world.get() {
                     self.world_create_ui.create_world.set(false);
                     None
                 } else if self.world_create_ui.cancel.get() {
                     self.world_create_ui.cancel.set(false);
-                    self.world_create_ui.world_name.set("".to_string());
+                    self.world_create_ui.reset();
                     Some( UIMode::WorldSelection )
                 } else {
                     None
@@ -924,6 +1158,81 @@ impl UICore {
                     render_pass.draw_indexed(0..num_indices, 0, 0..1);
                 }
 
+                // draw the saved indicator or other message for a little while
+                if self.hud_until.map_or( false, |until| std::time::Instant::now() < until ) {
+                    self.hud_screen.prepare(device, queue);
+                    self.hud_screen.render_into(
+                        &self.menu_draw_tex,
+                        wgpu::LoadOp::Clear( cushy::styles::Color::new(0, 0, 0, 0) ),
+                        device,
+                        queue
+                    );
+
+                    encoder.copy_texture_to_texture(
+                        wgpu::ImageCopyTexture{
+                            texture: self.menu_draw_tex.wgpu(),
+                            mip_level: 0,
+                            origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                            aspect: wgpu::TextureAspect::All
+                        },
+                        wgpu::ImageCopyTexture{
+                            texture: &self.menu_copy_tex.texture,
+                            mip_level: 0,
+                            origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                            aspect: wgpu::TextureAspect::All
+                        },
+                        self.menu_copy_tex.texture.size()
+                    );
+
+                    let hud_vertices = vec![
+                        UIVertex{ position: [ -1.0, 1.0 ], uv: [0.0, 0.0] },
+                        UIVertex{ position: [ 1.0, 1.0 ], uv: [1.0, 0.0] },
+                        UIVertex{ position: [ -1.0, -1.0 ], uv: [0.0, 1.0] },
+                        UIVertex{ position: [ 1.0, -1.0 ], uv: [1.0, 1.0] },
+                    ];
+                    let hud_indices: Vec<u16> = vec![
+                        0, 2, 1,
+                        1, 2, 3
+                    ];
+
+                    let vertex_buffer = device.create_buffer_init(
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("Vertex Buffer"),
+                            contents: bytemuck::cast_slice(&hud_vertices),
+                            usage: wgpu::BufferUsages::VERTEX,
+                        }
+                    );
+                    let index_buffer = device.create_buffer_init(
+                        &wgpu::util::BufferInitDescriptor {
+                            label: Some("Index Buffer"),
+                            contents: bytemuck::cast_slice(&hud_indices),
+                            usage: wgpu::BufferUsages::INDEX,
+                        }
+                    );
+                    let num_indices = hud_indices.len() as u32;
+
+                    let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                        label: Some("HUD Draw Pass"),
+                        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                            view: &target_view,
+                            resolve_target: None,
+                            ops: wgpu::Operations {
+                                load: wgpu::LoadOp::Load,
+                                store: wgpu::StoreOp::Store,
+                            },
+                        })],
+                        depth_stencil_attachment: None,
+                        occlusion_query_set: None,
+                        timestamp_writes: None,
+                    });
+
+                    render_pass.set_pipeline(&self.generic_pipeline);
+                    render_pass.set_bind_group(0, &self.menu_copy_bind_group, &[]);
+                    render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+                    render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+                    render_pass.draw_indexed(0..num_indices, 0, 0..1);
+                }
+
                 Ok(encoder)
             }
             UIMode::PauseMenu => {
@@ -1310,6 +1619,43 @@ impl UICore {
 
                 Ok(encoder)
             }
+            UIMode::WorldOptions => {
+                if let Some(options_ui) = &mut self.world_options_ui {
+                    options_ui.screen.prepare(device, queue);
+                    options_ui.screen.render_into(
+                        &self.menu_draw_tex,
+                        wgpu::LoadOp::Clear( cushy::styles::Color::new(0, 0, 0, 0) ),
+                        device,
+                        queue
+                    );
+                }
+
+                Ok( self.overlay_generic_menu(target_size, target_view, device) )
+            }
+            UIMode::Loading => {
+                self.loading_ui.screen.prepare(device, queue);
+                self.loading_ui.screen.render_into(
+                    &self.menu_draw_tex,
+                    wgpu::LoadOp::Clear( cushy::styles::Color::new(0, 0, 0, 0) ),
+                    device,
+                    queue
+                );
+
+                Ok( self.overlay_generic_menu(target_size, target_view, device) )
+            }
+            UIMode::LoadError => {
+                if let Some(error_ui) = &mut self.load_error_ui {
+                    error_ui.screen.prepare(device, queue);
+                    error_ui.screen.render_into(
+                        &self.menu_draw_tex,
+                        wgpu::LoadOp::Clear( cushy::styles::Color::new(0, 0, 0, 0) ),
+                        device,
+                        queue
+                    );
+                }
+
+                Ok( self.overlay_generic_menu(target_size, target_view, device) )
+            }
             _ => {
                 // setup stuff
                 let rw = self.main_menu_tex.texture.width() as f32 / target_size.0 as f32;
@@ -1384,7 +1730,96 @@ impl UICore {
 
     }
 
-    pub fn update_wield_item( &mut self, wi: WieldItem, device: &wgpu::Device, queue: &wgpu::Queue, br: &crate::wctx::block::BlockRegistry, sr: &crate::wctx::block::BlockShapeRegistry, block_render_setup: Option< (&wgpu::RenderPipeline, &wgpu::BindGroupLayout, &wgpu::BindGroup, &wgpu::BindGroup) > ) {
+    // copies a menu already rendered into menu_draw_tex onto the generic menu background
+    fn overlay_generic_menu(&self, target_size: (u32, u32), target_view: &wgpu::TextureView, device: &wgpu::Device) -> wgpu::CommandEncoder {
+        // setup stuff
+        let rw = self.main_menu_tex.texture.width() as f32 / target_size.0 as f32;
+        let rh = self.main_menu_tex.texture.height() as f32 / target_size.1 as f32;
+        let mut bgx = 0.5;
+        let mut bgy = 0.5;
+        if rw < rh {
+            bgy = rw / rh / 2.0;
+        } else {
+            bgx = rh / rw / 2.0;
+        }
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("UI Render Encoder"),
+        });
+
+        // copy the button menu over
+        encoder.copy_texture_to_texture(
+            wgpu::ImageCopyTexture{
+                texture: self.menu_draw_tex.wgpu(),
+                mip_level: 0,
+                origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                aspect: wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyTexture{
+                texture: &self.menu_copy_tex.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                aspect: wgpu::TextureAspect::All
+            },
+            self.menu_copy_tex.texture.size()
+        );
+
+        // draw the menu bg
+        {
+            let bg_vertices = vec![
+                BMVertex{ position: [ -1.0, 1.0 ], uv: [0.5 - bgx, 0.5 - bgy], uv2: [0.0, 0.0] },
+                BMVertex{ position: [ 1.0, 1.0 ], uv: [0.5 + bgx, 0.5 - bgy], uv2: [1.0, 0.0] },
+                BMVertex{ position: [ -1.0, -1.0 ], uv: [0.5 - bgx, 0.5 + bgy], uv2: [0.0, 1.0] },
+                BMVertex{ position: [ 1.0, -1.0 ], uv: [0.5 + bgx, 0.5 + bgy], uv2: [1.0, 1.0] },
+            ];
+            let bg_indices: Vec<u16> = vec![
+                0, 2, 3,
+                0, 3, 1
+            ];
+
+            let vertex_buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&bg_vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }
+            );
+            let index_buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Index Buffer"),
+                    contents: bytemuck::cast_slice(&bg_indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }
+            );
+            let num_indices = bg_indices.len() as u32;
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Menu Draw Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: &target_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: None,
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+
+            render_pass.set_pipeline(&self.overlaid_pipeline);
+            render_pass.set_bind_group(0, &self.gen_menu_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.menu_copy_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..num_indices, 0, 0..1);
+        }
+
+        encoder
+    }
+
+    pub fn update_wield_item( &mut self, wi: WieldItem, device: &wgpu::Device, queue: &wgpu::Queue, br: &crate::engine::block::BlockRegistry, sr: &crate::engine::block::BlockShapeRegistry, block_render_setup: Option< (&wgpu::RenderPipeline, &wgpu::BindGroupLayout, &wgpu::BindGroup, &wgpu::BindGroup) > ) {
         match wi {
             WieldItem::Block(block_id) => {
                 let setup = block_render_setup.expect("Some(Block render pipeline) is REQUIRED for drawing block to wielditem texture, found None");
@@ -1401,7 +1836,7 @@ impl UICore {
                     &mut tinds,
                     blockdef,
                     0,
-                    crate::wctx::chunk::BlockDrawContext::default(),
+                    crate::engine::chunk::BlockDrawContext::default(),
                     (0,0,0),
                     (0,0,0)
                 );
@@ -25,18 +25,61 @@ use cushy::value::{
     Destination
 };
 
+use crate::engine::world_gen::{
+    WorldGenerator,
+    WorldGenSettings,
+    SIZE_NAMES,
+    seed_from_string
+};
+
+
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum WorldSort {
+    LastPlayed,
+    Name,
+    Created,
+    PlayTime,
+}
+
+impl WorldSort {
+    pub const ALL: [WorldSort; 4] = [ WorldSort::LastPlayed, WorldSort::Name, WorldSort::Created, WorldSort::PlayTime ];
+
+    pub fn pretty_name(&self) -> &'static str {
+        match self {
+            WorldSort::LastPlayed => "Last played",
+            WorldSort::Name => "Name",
+            WorldSort::Created => "Newest",
+            WorldSort::PlayTime => "Play time",
+        }
+    }
 
+    pub fn sort(&self, worlds: &mut [&(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)]) {
+        match self {
+            WorldSort::LastPlayed => worlds.sort_by( |a, b| b.0.info.last_played.cmp(&a.0.info.last_played) ),
+            WorldSort::Name => worlds.sort_by( |a, b| a.0.info.name.to_lowercase().cmp(&b.0.info.name.to_lowercase()) ),
+            WorldSort::Created => worlds.sort_by( |a, b| b.0.info.created.cmp(&a.0.info.created) ),
+            WorldSort::PlayTime => worlds.sort_by( |a, b| b.0.info.play_time.cmp(&a.0.info.play_time) ),
+        }
+    }
+}
 
 pub struct WorldSelectUI {
     pub screen: cushy::window::VirtualWindow,
     pub create_world: cushy::value::Dynamic<bool>,
     pub load_world: cushy::value::Dynamic<String>,
     pub opt_world: cushy::value::Dynamic<String>,
+    pub preview_world: cushy::value::Dynamic<String>,
+    pub broken_world: cushy::value::Dynamic<String>,
+    pub import_path: cushy::value::Dynamic<String>,
+    pub import_world: cushy::value::Dynamic<bool>,
     pub back_to_title: cushy::value::Dynamic<bool>,
+    pub sort: cushy::value::Dynamic<WorldSort>,
+    pub filter: cushy::value::Dynamic<String>,
 }
 
 impl WorldSelectUI {
-    pub fn new(worlds: Vec<(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)>, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> WorldSelectUI {
+    pub fn new(worlds: Vec<(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)>, broken: Vec<std::path::PathBuf>, status: String, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> WorldSelectUI {
 
         let create_world = cushy::value::Dynamic::new(false);
 
@@ -58,14 +101,94 @@ impl WorldSelectUI {
 
         let load_world = cushy::value::Dynamic::new( "".to_string() );
         let opt_world = cushy::value::Dynamic::new( "".to_string() );
+        let preview_world = cushy::value::Dynamic::new( "".to_string() );
+        let broken_world = cushy::value::Dynamic::new( "".to_string() );
+        let import_path = cushy::value::Dynamic::new( "".to_string() );
+        let import_world = cushy::value::Dynamic::new(false);
 
-        // list of world options
-        let mut worlds_list = cushy::widget::WidgetList::new();
+        // importing a .sgrworld archive
+        let import_input = import_path.clone().into_input().placeholder("Path to a .sgrworld file")
+        .with( &styles::components::CornerRadius, styles::CornerRadii{ top_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), top_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)) } )
+        .with(&styles::components::OutlineColor, styles::Color::new(224,173,83,255))
+        .with(&styles::components::HighlightColor, styles::Color::new(245,204,25,255));
+        let mut import_button = widgets::Button::new( widgets::Label::<&str>::new("IMPORT") );
+        import_button = import_button.kind( widgets::button::ButtonKind::Solid );
+        import_button = import_button.on_click({
+            let import_world = import_world.clone();
+            move |click| { import_world.set(true); }
+        });
+        let mut import_row = cushy::widget::WidgetList::new();
+        import_row.push( import_input.expand_weighted(3) );
+        import_row.push( import_button.with_styles(Self::make_buttonstyles()).expand_weighted(1) );
+        let sort = cushy::value::Dynamic::new( WorldSort::LastPlayed );
+        let filter = cushy::value::Dynamic::new( "".to_string() );
+
+        // sort and filter controls
+        let filter_input = filter.clone().into_input().placeholder("Search worlds")
+        .with( &styles::components::CornerRadius, styles::CornerRadii{ top_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), top_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)) } )
+        .with(&styles::components::OutlineColor, styles::Color::new(224,173,83,255))
+        .with(&styles::components::HighlightColor, styles::Color::new(245,204,25,255));
 
-        worlds_list.push( create_button.with_styles(Self::make_buttonstyles()) );
+        let sort_label = widgets::Label::new( sort.map_each( |sort| format!( "Sort: {}", sort.pretty_name() ) ) );
+        let mut sort_list = cushy::widget::WidgetList::new();
+        sort_list.push( sort_label.expand_weighted(2) );
+        for sort_mode in WorldSort::ALL {
+            let mut sort_button = widgets::Button::new( widgets::Label::<&str>::new( sort_mode.pretty_name() ) );
+            sort_button = sort_button.kind( widgets::button::ButtonKind::Solid );
+            sort_button = sort_button.on_click({
+                let sort = sort.clone();
+                move |click| { sort.set(sort_mode); }
+            });
+            sort_list.push( sort_button.with_styles(Self::make_buttonstyles()).expand_weighted(1) );
+        }
 
-        for wp in worlds {
-            worlds_list.push( Self::make_world_selector( wp.0.name, wp.1, load_world.clone(), opt_world.clone() ) );
+        // list of world options, rebuilt whenever the sort or filter changes
+        let shown_worlds = (&sort, &filter).map_each({
+            let load_world = load_world.clone();
+            let opt_world = opt_world.clone();
+            let preview_world = preview_world.clone();
+            move |(sort, filter)| {
+                let needle = filter.trim().to_lowercase();
+                let mut matching: Vec<&(crate::wctx::world_loader::WorldPreview, cushy::kludgine::Texture)> = worlds.iter().filter( |wp| {
+                    needle.is_empty() || wp.0.info.name.to_lowercase().contains(&needle)
+                } ).collect();
+                sort.sort(&mut matching);
+
+                let mut worlds_list = cushy::widget::WidgetList::new();
+                for wp in matching {
+                    worlds_list.push( Self::make_world_selector( &wp.0.info, wp.0.has_preview, wp.1.clone(), load_world.clone(), opt_world.clone(), preview_world.clone() ) );
+                }
+                if worlds_list.is_empty() && !needle.is_empty() {
+                    worlds_list.push( widgets::Label::<&str>::new("No worlds match the search.") );
+                }
+                worlds_list
+            }
+        });
+
+        let mut worlds_list = cushy::widget::WidgetList::new();
+        worlds_list.push( create_button.with_styles(Self::make_buttonstyles()) );
+        worlds_list.push( import_row.into_columns() );
+        if status != "" {
+            worlds_list.push( widgets::Label::new(status) );
+        }
+        worlds_list.push( filter_input );
+        worlds_list.push( sort_list.into_columns() );
+        worlds_list.push( shown_worlds.into_rows() );
+
+        // worlds whose info couldn't be read, so they can't be listed normally
+        for path in broken.iter() {
+            let dir_name = path.file_name().map( |n| n.to_string_lossy().to_string() ).unwrap_or_default();
+            let mut details_button = widgets::Button::new( widgets::Label::<&str>::new("DETAILS") );
+            details_button = details_button.kind( widgets::button::ButtonKind::Solid );
+            details_button = details_button.on_click({
+                let broken_world = broken_world.clone();
+                let path = path.to_string_lossy().to_string();
+                move |click| { broken_world.set( path.clone() ); }
+            });
+            let mut row = cushy::widget::WidgetList::new();
+            row.push( widgets::Label::new( format!("{} can't be read", dir_name) ).expand_weighted(3) );
+            row.push( details_button.with_styles(Self::make_buttonstyles()).expand_weighted(1) );
+            worlds_list.push( row.into_columns() );
         }
 
         // scroll to contain the world list
@@ -95,17 +218,35 @@ impl WorldSelectUI {
             create_world,
             load_world,
             opt_world,
+            preview_world,
+            broken_world,
+            import_path,
+            import_world,
             back_to_title,
+            sort,
+            filter,
         }
     }
 
 
 
-    pub fn make_world_selector( name: String, kl_texture: cushy::kludgine::Texture, load_clone: cushy::value::Dynamic<String>, opt_clone: cushy::value::Dynamic<String> ) -> widgets::Container {
+    pub fn make_world_selector( info: &crate::wctx::world_saver::WorldInfo, has_preview: bool, kl_texture: cushy::kludgine::Texture, load_clone: cushy::value::Dynamic<String>, opt_clone: cushy::value::Dynamic<String>, preview_clone: cushy::value::Dynamic<String> ) -> widgets::Container {
+        let name = info.name.clone();
 
         let preview_image = widgets::Image::new( cushy::kludgine::AnyTexture::Texture(kl_texture) ).scaled(0.25);
 
         let label = widgets::Label::new( name.clone() );
+        let details = widgets::Label::new( format!(
+            "{} {} {}, seed {}\nLast played {}, played for {}\nCreated {}{}",
+            SIZE_NAMES.get(info.size).unwrap_or(&"?"),
+            info.generator.pretty_name(),
+            if info.template { "template" } else { "world" },
+            info.seed,
+            crate::wctx::world_saver::format_age(info.last_played),
+            crate::wctx::world_saver::format_duration(info.play_time),
+            crate::wctx::world_saver::format_age(info.created),
+            if info.game_version.is_empty() { "".to_string() } else { format!(", saved with version {}", info.game_version) }
+        ) );
 
         let mut buttonlist = cushy::widget::WidgetList::new();
         let mut buttonstyles = Self::make_buttonstyles();
@@ -126,8 +267,19 @@ impl WorldSelectUI {
         });
         buttonlist.push( options_button.with_styles(buttonstyles.clone()).expand_weighted(5) );
 
+        if !has_preview {
+            let mut preview_button = widgets::Button::new( widgets::Label::<&str>::new("MAKE PREVIEW") );
+            preview_button = preview_button.kind( widgets::button::ButtonKind::Solid );
+            preview_button = preview_button.on_click({
+                let nm = name.clone();
+                move |click| { preview_clone.set(nm.clone()) }
+            });
+            buttonlist.push( preview_button.with_styles(buttonstyles.clone()).expand_weighted(5) );
+        }
+
         let mut right_list = cushy::widget::WidgetList::new();
         right_list.push(label);
+        right_list.push(details);
         right_list.push(buttonlist.into_columns());
 
         let mut full_list = cushy::widget::WidgetList::new();
@@ -170,24 +322,328 @@ impl WorldSelectUI {
 
 }
 
+#[derive(Clone, PartialEq, Debug)]
+pub enum WorldOptionsAction {
+    None,
+    Rename(String),
+    Duplicate,
+    AskDelete,
+    CancelDelete,
+    Delete,
+    Restore(String),
+    Export,
+    SetTemplate(bool),
+}
+
+pub struct WorldOptionsUI {
+    pub screen: cushy::window::VirtualWindow,
+    pub action: cushy::value::Dynamic<WorldOptionsAction>,
+    pub back: cushy::value::Dynamic<bool>,
+}
+
+impl WorldOptionsUI {
+    pub fn new(name: String, backups: &[crate::wctx::world_saver::BackupInfo], status: String, confirm_delete: bool, is_template: bool, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> WorldOptionsUI {
+        let action = cushy::value::Dynamic::new( WorldOptionsAction::None );
+        let back = cushy::value::Dynamic::new(false);
+        let new_name = cushy::value::Dynamic::new( name.clone() );
+
+        let action_button = |text: &'static str, on_click: WorldOptionsAction| {
+            let mut button = widgets::Button::new( widgets::Label::<&str>::new(text) );
+            button = button.kind( widgets::button::ButtonKind::Solid );
+            button = button.on_click({
+                let action = action.clone();
+                move |click| { action.set(on_click.clone()); }
+            });
+            button.with_styles(WorldSelectUI::make_buttonstyles())
+        };
+
+        let mut list = cushy::widget::WidgetList::new();
+        list.push( widgets::Label::new( format!("World options: {}", name) ) );
+        if status != "" {
+            list.push( widgets::Label::new(status) );
+        }
+
+        // rename
+        let name_input = new_name.clone().into_input().placeholder("New name")
+        .with( &styles::components::CornerRadius, styles::CornerRadii{ top_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), top_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)) } )
+        .with(&styles::components::OutlineColor, styles::Color::new(224,173,83,255))
+        .with(&styles::components::HighlightColor, styles::Color::new(245,204,25,255));
+        let mut rename_button = widgets::Button::new( widgets::Label::<&str>::new("RENAME") );
+        rename_button = rename_button.kind( widgets::button::ButtonKind::Solid );
+        rename_button = rename_button.on_click({
+            let action = action.clone();
+            let new_name = new_name.clone();
+            move |click| { action.set( WorldOptionsAction::Rename( new_name.get() ) ); }
+        });
+        let mut rename_row = cushy::widget::WidgetList::new();
+        rename_row.push( name_input.expand_weighted(3) );
+        rename_row.push( rename_button.with_styles(WorldSelectUI::make_buttonstyles()).expand_weighted(1) );
+        list.push( rename_row.into_columns() );
+
+        let mut file_row = cushy::widget::WidgetList::new();
+        file_row.push( action_button("DUPLICATE", WorldOptionsAction::Duplicate).expand() );
+        file_row.push( action_button("EXPORT", WorldOptionsAction::Export).expand() );
+        if !confirm_delete {
+            file_row.push( action_button("DELETE", WorldOptionsAction::AskDelete).expand() );
+        }
+        list.push( file_row.into_columns() );
+
+        if is_template {
+            list.push( widgets::Label::<&str>::new("This world is a template, new worlds can be started from it.") );
+            list.push( action_button("STOP USING AS TEMPLATE", WorldOptionsAction::SetTemplate(false)) );
+        } else {
+            list.push( action_button("USE AS TEMPLATE", WorldOptionsAction::SetTemplate(true)) );
+        }
+
+        // deleting takes a second click
+        if confirm_delete {
+            list.push( widgets::Label::new( format!("Really delete {}? This also deletes its backups and can't be undone.", name) ) );
+            let mut confirm_row = cushy::widget::WidgetList::new();
+            confirm_row.push( action_button("YES, DELETE", WorldOptionsAction::Delete).expand() );
+            confirm_row.push( action_button("NO", WorldOptionsAction::CancelDelete).expand() );
+            list.push( confirm_row.into_columns() );
+        }
+
+        // backup list, newest first
+        list.push( widgets::Label::<&str>::new("Backups") );
+        let mut backup_list = cushy::widget::WidgetList::new();
+        if backups.is_empty() {
+            backup_list.push( widgets::Label::<&str>::new("No backups yet, one is made every time the world is saved.") );
+        }
+        for backup in backups.iter() {
+            let mut row = cushy::widget::WidgetList::new();
+            row.push( widgets::Label::new( backup.label() ).expand_weighted(3) );
+            row.push( action_button( "RESTORE", WorldOptionsAction::Restore( backup.path.to_string_lossy().to_string() ) ).expand_weighted(1) );
+            backup_list.push( row.into_columns() );
+        }
+        list.push( widgets::Scroll::vertical( backup_list.into_rows() ) );
+
+        let mut back_button = widgets::Button::new( widgets::Label::<&str>::new("Back") );
+        back_button = back_button.kind( widgets::button::ButtonKind::Solid );
+        back_button = back_button.on_click({
+            let back = back.clone();
+            move |click| { back.set(true); }
+        });
+        list.push( back_button.with_styles(WorldSelectUI::make_buttonstyles()) );
+
+        let outer = widgets::Container::new( list.into_rows() ).pad_by(
+            styles::Edges {
+                top: Dimension::Px( Px::new(54)),
+                bottom: Dimension::Px( Px::new(54)),
+                left: Dimension::Px( Px::new(54)),
+                right: Dimension::Px( Px::new(54))
+            }
+        ).background_color(
+            styles::Color::new(0,0,0,240)
+        );
+
+        let mut builder = cushy::window::StandaloneWindowBuilder::new( outer.centered() ).transparent();
+        builder = builder.size( figures::Size { width: config.width, height: config.height } );
+        let mut screen = builder.finish_virtual(device, queue);
+
+        Self {
+            screen,
+            action,
+            back,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum LoadErrorAction {
+    None,
+    Retry,
+    Restore,
+    OpenFolder,
+    Back,
+}
+
+// shown when a world fails to load
+pub struct LoadErrorUI {
+    pub screen: cushy::window::VirtualWindow,
+    pub action: cushy::value::Dynamic<LoadErrorAction>,
+}
+
+impl LoadErrorUI {
+    pub fn new(name: String, message: String, can_restore: bool, config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> LoadErrorUI {
+        let action = cushy::value::Dynamic::new( LoadErrorAction::None );
+
+        let action_button = |text: &'static str, on_click: LoadErrorAction| {
+            let mut button = widgets::Button::new( widgets::Label::<&str>::new(text) );
+            button = button.kind( widgets::button::ButtonKind::Solid );
+            button = button.on_click({
+                let action = action.clone();
+                move |click| { action.set(on_click.clone()); }
+            });
+            button.with_styles(WorldSelectUI::make_buttonstyles())
+        };
+
+        let mut list = cushy::widget::WidgetList::new();
+        list.push( widgets::Label::new( format!("Couldn't load {}", name) ) );
+        list.push( widgets::Label::new(message) );
+
+        let mut button_row = cushy::widget::WidgetList::new();
+        button_row.push( action_button("RETRY", LoadErrorAction::Retry).expand() );
+        if can_restore {
+            button_row.push( action_button("RESTORE A BACKUP", LoadErrorAction::Restore).expand() );
+        }
+        button_row.push( action_button("OPEN WORLD FOLDER", LoadErrorAction::OpenFolder).expand() );
+        list.push( button_row.into_columns() );
+        list.push( action_button("Back", LoadErrorAction::Back) );
+
+        let outer = widgets::Container::new( list.into_rows() ).pad_by(
+            styles::Edges {
+                top: Dimension::Px( Px::new(54)),
+                bottom: Dimension::Px( Px::new(54)),
+                left: Dimension::Px( Px::new(54)),
+                right: Dimension::Px( Px::new(54))
+            }
+        ).background_color(
+            styles::Color::new(0,0,0,240)
+        );
+
+        let mut builder = cushy::window::StandaloneWindowBuilder::new( outer.centered() ).transparent();
+        builder = builder.size( figures::Size { width: config.width, height: config.height } );
+        let mut screen = builder.finish_virtual(device, queue);
+
+        Self {
+            screen,
+            action,
+        }
+    }
+}
+
+// shown while a world loads in the background
+pub struct LoadingUI {
+    pub screen: cushy::window::VirtualWindow,
+    pub status: cushy::value::Dynamic<String>,
+    pub progress: cushy::value::Dynamic<f32>,
+}
+
+impl LoadingUI {
+    pub fn new(config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> LoadingUI {
+        let status = cushy::value::Dynamic::new( "".to_string() );
+        let progress = cushy::value::Dynamic::new( 0.0_f32 );
+
+        let bar = widgets::ProgressBar::new( progress.map_each( |fraction| {
+            widgets::progress::Progress::Percent( cushy::animation::ZeroToOne::new(*fraction) )
+        } ) );
+
+        let mut list = cushy::widget::WidgetList::new();
+        list.push( bar.width( Px::new(480) ) );
+        list.push( widgets::Label::new(status.clone()) );
+
+        let mut builder = cushy::window::StandaloneWindowBuilder::new( list.into_rows().centered() ).transparent();
+        builder = builder.size( figures::Size { width: config.width, height: config.height } );
+        let mut screen = builder.finish_virtual(device, queue);
+
+        Self {
+            screen,
+            status,
+            progress,
+        }
+    }
+}
+
 pub struct WorldCreationUI {
     pub screen: cushy::window::VirtualWindow,
     pub create_world: cushy::value::Dynamic<bool>,
     pub world_name: cushy::value::Dynamic<String>,
+    pub world_size: cushy::value::Dynamic<usize>,
+    pub world_seed: cushy::value::Dynamic<String>,
+    pub world_generator: cushy::value::Dynamic<WorldGenerator>,
+    // directory of the template to start from, empty to generate a fresh world
+    pub world_template: cushy::value::Dynamic<String>,
     pub cancel: cushy::value::Dynamic<bool>,
+    // why the last attempt to create the world failed
+    pub status: cushy::value::Dynamic<String>,
 }
 
 impl WorldCreationUI {
-    pub fn new(config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> WorldCreationUI {
+    pub fn new(templates: &[crate::wctx::world_saver::TemplateInfo], config: &wgpu::SurfaceConfiguration, device: &wgpu::Device, queue: &wgpu::Queue) -> WorldCreationUI {
         let world_name = cushy::value::Dynamic::new( "".to_string() );
+        let world_size = cushy::value::Dynamic::new( 0_usize );
+        let world_seed = cushy::value::Dynamic::new( "".to_string() );
+        let world_generator = cushy::value::Dynamic::new( WorldGenerator::Flat );
+        let world_template = cushy::value::Dynamic::new( "".to_string() );
         let create_world = cushy::value::Dynamic::new(false);
         let cancel = cushy::value::Dynamic::new(false);
+        let status = cushy::value::Dynamic::new( "".to_string() );
 
         let name_input = world_name.clone().into_input().placeholder("World name")
         .with( &styles::components::CornerRadius, styles::CornerRadii{ top_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), top_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)) } )
         .with(&styles::components::OutlineColor, styles::Color::new(224,173,83,255))
         .with(&styles::components::HighlightColor, styles::Color::new(245,204,25,255));
 
+        // world size selector
+        let size_label = widgets::Label::new( world_size.map_each( |size| {
+            let width = crate::engine::chunk::CHUNK_SIZE * crate::engine::chunk::WORLD_CHUNKS[*size];
+            format!( "Size: {} ({}x{}x{})", SIZE_NAMES[*size], width, width, width )
+        } ) );
+
+        let mut size_list = cushy::widget::WidgetList::new();
+        for size in 0..SIZE_NAMES.len() {
+            let mut size_button = widgets::Button::new( widgets::Label::<&str>::new( SIZE_NAMES[size] ) );
+            size_button = size_button.kind( widgets::button::ButtonKind::Solid );
+            size_button = size_button.on_click({
+                let world_size = world_size.clone();
+                move |click| { world_size.set(size); }
+            });
+            size_list.push( size_button.with_styles(Self::make_buttonstyles()).expand() );
+        }
+
+        // seed input, left empty for a random seed
+        let seed_input = world_seed.clone().into_input().placeholder("Seed (leave empty for random)")
+        .with( &styles::components::CornerRadius, styles::CornerRadii{ top_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), top_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_left: cushy::styles::Dimension::Px(figures::units::Px::new(0)), bottom_right: cushy::styles::Dimension::Px(figures::units::Px::new(0)) } )
+        .with(&styles::components::OutlineColor, styles::Color::new(224,173,83,255))
+        .with(&styles::components::HighlightColor, styles::Color::new(245,204,25,255));
+
+        // generator picker
+        let generator_label = widgets::Label::new( world_generator.map_each( |generator| {
+            format!( "Generator: {}", generator.pretty_name() )
+        } ) );
+
+        let mut generator_list = cushy::widget::WidgetList::new();
+        for generator in WorldGenerator::ALL {
+            let mut generator_button = widgets::Button::new( widgets::Label::<&str>::new( generator.pretty_name() ) );
+            generator_button = generator_button.kind( widgets::button::ButtonKind::Solid );
+            generator_button = generator_button.on_click({
+                let world_generator = world_generator.clone();
+                move |click| { world_generator.set(generator); }
+            });
+            generator_list.push( generator_button.with_styles(Self::make_buttonstyles()).expand() );
+        }
+
+        // template picker, a template replaces size, seed and generator
+        let template_label = widgets::Label::new( world_template.map_each({
+            let templates = templates.to_vec();
+            move |path| {
+                match templates.iter().find( |t| t.path.to_string_lossy() == path.as_str() ) {
+                    Some(template) => format!( "Template: {} (size and generator come from the template)", template.label() ),
+                    None => "Template: none".to_string(),
+                }
+            }
+        }) );
+
+        let mut template_list = cushy::widget::WidgetList::new();
+        let mut none_button = widgets::Button::new( widgets::Label::<&str>::new("None") );
+        none_button = none_button.kind( widgets::button::ButtonKind::Solid );
+        none_button = none_button.on_click({
+            let world_template = world_template.clone();
+            move |click| { world_template.set( "".to_string() ); }
+        });
+        template_list.push( none_button.with_styles(Self::make_buttonstyles()).expand() );
+        for template in templates.iter() {
+            let mut template_button = widgets::Button::new( widgets::Label::new( template.label() ) );
+            template_button = template_button.kind( widgets::button::ButtonKind::Solid );
+            template_button = template_button.on_click({
+                let world_template = world_template.clone();
+                let path = template.path.to_string_lossy().to_string();
+                move |click| { world_template.set( path.clone() ); }
+            });
+            template_list.push( template_button.with_styles(Self::make_buttonstyles()).expand() );
+        }
+
         let mut create_button = widgets::Button::new( widgets::Label::<&str>::new("Create!") );
         create_button = create_button.kind( widgets::button::ButtonKind::Solid );
         create_button = create_button.on_click({
@@ -204,6 +660,14 @@ impl WorldCreationUI {
 
         let mut list = cushy::widget::WidgetList::new();
         list.push(name_input);
+        list.push(size_label);
+        list.push(size_list.into_columns());
+        list.push(seed_input);
+        list.push(generator_label);
+        list.push(generator_list.into_columns());
+        list.push(template_label);
+        list.push(template_list.into_columns());
+        list.push(widgets::Label::new(status.clone()));
         list.push(create_button.with_styles(Self::make_buttonstyles()));
         list.push(back_button.with_styles(Self::make_buttonstyles()));
 
@@ -215,10 +679,41 @@ impl WorldCreationUI {
             screen,
             create_world,
             world_name,
+            world_size,
+            world_seed,
+            world_generator,
+            world_template,
             cancel,
+            status,
         }
     }
 
+    pub fn get_template(&self) -> Option<std::path::PathBuf> {
+        let path = self.world_template.get();
+        if path == "" {
+            None
+        } else {
+            Some( std::path::PathBuf::from(path) )
+        }
+    }
+
+    pub fn get_settings(&self) -> WorldGenSettings {
+        WorldGenSettings {
+            size: self.world_size.get(),
+            seed: seed_from_string( &self.world_seed.get() ),
+            generator: self.world_generator.get(),
+        }
+    }
+
+    pub fn reset(&self) {
+        self.world_name.set("".to_string());
+        self.world_size.set(0);
+        self.world_seed.set("".to_string());
+        self.world_generator.set(WorldGenerator::Flat);
+        self.world_template.set("".to_string());
+        self.status.set("".to_string());
+    }
+
     pub fn make_buttonstyles() -> styles::Styles {
         let mut buttonstyles = styles::Styles::new();
 
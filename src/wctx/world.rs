@@ -1,53 +1,29 @@
  
 
-use std::path::PathBuf;
+use std::collections::HashMap;
+use std::path::{
+    Path,
+    PathBuf
+};
 use std::io::Error;
 
 use cgmath::SquareMatrix;
 
 use grid_ray::GridRayIter3;
 
-use serde::{
-    Serialize,
-    Deserialize
-};
-
 use wgpu::util::DeviceExt;
 
 
 
 use crate::wctx::camera;
 use crate::wctx::texture;
-use crate::wctx::chunk;
-use crate::wctx::block;
+use crate::engine::chunk;
+use crate::engine::block;
 use crate::wctx::atlas_tex;
-
-// state stored when a game world is saved
-#[derive(Serialize, Deserialize)]
-pub struct WorldSavestate {
-    pub chunk_manager: chunk::ChunkManager,
-    pub block_select: u16,
-    pub camera: camera::Camera,
-}
-
-impl WorldSavestate {
-    pub fn new(size: usize) -> WorldSavestate {
-        let chunk_manager = crate::wctx::chunk::ChunkManager::new(size);
-        let block_select = 1;
-        let camera = camera::Camera::new((63.0, 35.0, 62.0), cgmath::Deg(90.0), cgmath::Deg(-20.0));
-
-        Self {
-            chunk_manager,
-            block_select,
-            camera,
-        }
-    }
-
-    pub fn size(&self) -> usize {
-        self.chunk_manager.size
-    }
-}
-
+use crate::engine::journal;
+use crate::engine::blueprint::Blueprint;
+pub use crate::engine::mesh::Vertex;
+pub use crate::engine::world::WorldSavestate;
 
 // resources used to render the game world
 pub struct WorldRender {
@@ -69,23 +45,31 @@ pub struct WorldRender {
     pub shape_registry: block::BlockShapeRegistry,
     selector_pipeline: wgpu::RenderPipeline,
     selector_bind_group: wgpu::BindGroup,
+    ghost_pipeline: wgpu::RenderPipeline,
+    // the world's blueprint, and the mesh of the part of it not built yet
+    ghost_blocks: HashMap<(usize, usize, usize), (u16, u8)>,
+    ghost_mesh: Vec<chunk::ChunkDrawCache>,
     selected_block: Option<(usize, usize, usize)>,
     select_timer: u8,
-    select_duration: std::time::Duration
+    select_duration: std::time::Duration,
+    pub journal: Option<journal::EditJournal>,
+    // time played since the last save
+    pub session_time: std::time::Duration,
 }
 
 impl WorldRender {
-    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, world: WorldSavestate, world_name: String) -> WorldRender {
+    // world_dir is where the world was read from, the edit journal is only opened for a world that is being played
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue, config: &wgpu::SurfaceConfiguration, world: WorldSavestate, world_name: String, world_dir: &Path, journaled: bool) -> WorldRender {
 
         let mut dl = crate::wctx::data_loader::BlockLoader::create(&device, &queue);
-        let _ = dl.submit_blockshape_direct( crate::wctx::block::make_cube_shape(), &"CubeStatic".into() );
-        let _ = dl.submit_blockshape_direct( crate::wctx::block::make_slope_shape(), &"Slope".into() );
-        let _ = dl.submit_blockshape_direct( crate::wctx::block::make_corner_shape(), &"CornerSlope".into() );
+        for (name, shape) in crate::engine::block::builtin_shapes() {
+            let _ = dl.submit_blockshape_direct( shape, &name );
+        }
 
         let pal_bytes = include_bytes!("../../res/texture/core/palette.png");
         let pal_img = image::load_from_memory(pal_bytes).unwrap();
 
-        dl.load_toml_from_file( PathBuf::from("res/data/block.toml") ).expect("failed to load blocks!");
+        dl.load_toml_from_file( PathBuf::from(crate::wctx::data_loader::BLOCK_DATA_PATH) ).expect("failed to load blocks!");
         dl.do_extract().expect("failed to extract config!");
         dl.resolve_blocks( &device, &queue, &pal_img ).expect("failed to resolve blocks!");
 
@@ -93,6 +77,12 @@ impl WorldRender {
         let block_atlas = dl.texture_atlas;
         let shape_registry = dl.shape_registry;
 
+        // freshly generated worlds are built straight from the current registry
+        let mut world = world;
+        if world.block_table.is_empty() {
+            world.block_table = block_registry.name_table();
+        }
+
         let texture_bind_group_layout =
             device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
                 entries: &[
@@ -327,6 +317,58 @@ impl WorldRender {
             cache: None,
         });
 
+        let ghost_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Ghost Block Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("../ghost_block_shader.wgsl").into()),
+        });
+
+        let ghost_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Ghost Block Pipeline"),
+            layout: Some(&render_pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &ghost_shader,
+                entry_point: "vs_main",
+                buffers: &[
+                    Vertex::desc(),
+                ],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &ghost_shader,
+                entry_point: "fs_main",
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: wgpu::PipelineCompilationOptions::default(),
+            }),
+            primitive: wgpu::PrimitiveState {
+                topology: wgpu::PrimitiveTopology::TriangleList,
+                strip_index_format: None,
+                front_face: wgpu::FrontFace::Ccw,
+                cull_mode: Some(wgpu::Face::Back),
+                polygon_mode: wgpu::PolygonMode::Fill,
+                unclipped_depth: false,
+                conservative: false,
+            },
+            // hidden behind built blocks, but ghosts don't hide each other
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: texture::Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: 1,
+                mask: !0,
+                alpha_to_coverage_enabled: false,
+            },
+            multiview: None,
+            cache: None,
+        });
+
         let sel_shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
             label: Some("Selected Block Shader"),
             source: wgpu::ShaderSource::Wgsl(include_str!("../selected_block_shader.wgsl").into()),
@@ -402,7 +444,28 @@ impl WorldRender {
 
         let projection = camera::Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 100.0);
 
-        Self{
+        let journal = if journaled {
+            match journal::EditJournal::open( world_dir, world.save_id ) {
+                Ok(journal) => Some(journal),
+                Err(e) => {
+                    log::error!("failed to open the edit journal for {}, edits are only kept by saving: {}", world_name, e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+
+        let width = chunk::CHUNK_SIZE * chunk::WORLD_CHUNKS[ world.size() ];
+        let ghost_blocks = match Blueprint::read(world_dir) {
+            Ok(blueprint) => blueprint.resolve( &block_registry, width ),
+            Err(e) => {
+                log::error!("failed to read the blueprint for {}: {}", world_name, e);
+                HashMap::new()
+            }
+        };
+
+        let mut wr = Self{
             world,
             world_name,
             block_registry,
@@ -421,9 +484,44 @@ impl WorldRender {
             colormap_bind_group,
             selector_pipeline,
             selector_bind_group,
+            ghost_pipeline,
+            ghost_blocks,
+            ghost_mesh: Vec::new(),
             selected_block: None,
             select_timer: 0,
             select_duration: std::time::Duration::ZERO,
+            journal,
+            session_time: std::time::Duration::ZERO,
+        };
+        wr.update_ghosts();
+        wr
+    }
+
+    // remeshes the blueprint blocks that don't match the world yet
+    pub fn update_ghosts(&mut self) {
+        self.ghost_mesh.clear();
+        let mut cache = chunk::ChunkDrawCache::default();
+        for (pos, (blockdef, exparam)) in self.ghost_blocks.iter() {
+            let bi = self.world.chunk_manager.get_block(*pos);
+            if bi.blockdef == *blockdef && bi.exparam == *exparam {
+                continue;
+            }
+            let bdef = match self.block_registry.get(*blockdef) {
+                Some(bdef) => bdef,
+                None => continue,
+            };
+            let shape = self.shape_registry.get(bdef.shape_id).unwrap();
+            if !shape.valid_exparam(*exparam) {
+                continue;
+            }
+            // indices are 16 bit, start another buffer well before they run out
+            if cache.vertices.len() > 60000 {
+                self.ghost_mesh.push( std::mem::take(&mut cache) );
+            }
+            shape.generate_draw_buffers( &mut cache.vertices, &mut cache.indices, bdef, *exparam, chunk::BlockDrawContext::default(), (0, 0, 0), *pos );
+        }
+        if !cache.is_empty() {
+            self.ghost_mesh.push(cache);
         }
     }
 
@@ -432,6 +530,11 @@ impl WorldRender {
         self.depth_texture = texture::Texture::create_depth_texture(device, config, "depth_texture");
     }
 
+    // the player's current view, the same matrix the window is drawn with
+    pub fn view_proj(&self) -> cgmath::Matrix4<f32> {
+        self.projection.calc_matrix() * self.world.camera.calc_matrix()
+    }
+
     pub fn process_keyboard(&mut self, key: &winit::keyboard::KeyCode, state: &winit::event::ElementState) -> bool {
         self.camera_controller.process_keyboard(*key, *state)
     }
@@ -457,7 +560,14 @@ impl WorldRender {
     }
 
     pub fn update(&mut self, queue: &wgpu::Queue, mouse_pressed: crate::wctx::MouseOps, dt: std::time::Duration) {
+        self.session_time += dt;
         self.camera_controller.update_camera(&mut self.world.camera, dt);
+
+        // stream in region chunks as the camera approaches them
+        let cam_pos = ( self.world.camera.position.x, self.world.camera.position.y, self.world.camera.position.z );
+        if let Err(e) = self.world.chunk_manager.load_near( cam_pos, chunk::LOAD_RADIUS, chunk::MAX_LOADS_PER_FRAME ) {
+            log::warn!("failed to load chunks for world {}: {}", self.world_name, e);
+        }
         self.camera_uniform.update_view_proj(&self.world.camera, &self.projection);
         queue.write_buffer(
             &self.camera_buffer,
@@ -496,15 +606,47 @@ impl WorldRender {
             self.selected_block = None;
         }
 
+        // position and new block of a break or place
+        let mut target: Option<( (usize, usize, usize), u16 )> = None;
         if hit && mouse_pressed.left_just_now {
-            let mut broken = self.world.chunk_manager.get_mut_block( ( current.x as usize, current.y as usize, current.z as usize ) );
-            broken.blockdef = 0;
-            broken.exparam = 0;
+            target = Some( ( ( current.x as usize, current.y as usize, current.z as usize ), 0 ) );
         } else if hit && mouse_pressed.right_just_now && (last.x >= 0 && last.y >= 0 && last.z >= 0 &&
             last.x < (chunk::CHUNK_SIZE * chunk::WORLD_CHUNKS[self.world.size()]) as i32 && last.y < (chunk::CHUNK_SIZE * chunk::WORLD_CHUNKS[self.world.size()]) as i32 && last.z < (chunk::CHUNK_SIZE * chunk::WORLD_CHUNKS[self.world.size()]) as i32) {
-            let mut placed = self.world.chunk_manager.get_mut_block( ( last.x as usize, last.y as usize, last.z as usize ) );
-            placed.blockdef = self.world.block_select;
-            placed.exparam = 0;
+            target = Some( ( ( last.x as usize, last.y as usize, last.z as usize ), self.world.block_select ) );
+        }
+
+        let mut edit: Option<( (usize, usize, usize), chunk::BlockInstance, chunk::BlockInstance )> = None;
+        if let Some( (pos, blockdef) ) = target {
+            // a placed block can land in a chunk that hasn't streamed in yet. editing its placeholder
+            // would be lost when the real chunk loads over it
+            let chunk_index = ( pos.0 / chunk::CHUNK_SIZE, pos.1 / chunk::CHUNK_SIZE, pos.2 / chunk::CHUNK_SIZE );
+            match self.world.chunk_manager.load_chunk(chunk_index) {
+                Ok(()) => {
+                    let block = self.world.chunk_manager.get_mut_block( pos );
+                    let old = *block;
+                    block.blockdef = blockdef;
+                    block.exparam = 0;
+                    edit = Some( (pos, old, *block) );
+                }
+                Err(e) => log::error!("failed to load chunk {:?} of {} for an edit: {}", chunk_index, self.world_name, e),
+            }
+        }
+
+        if let Some( (pos, _, _) ) = edit {
+            if self.ghost_blocks.contains_key(&pos) {
+                self.update_ghosts();
+            }
+        }
+
+        // every edit goes into the journal, so a crash loses at most the last unflushed second
+        if let Some(journal) = &mut self.journal {
+            let mut res = Ok(());
+            if let Some( (pos, old, new) ) = edit {
+                res = journal.record(pos, &old, &new);
+            }
+            if let Err(e) = res.and_then( |_| journal.flush_if_due() ) {
+                log::error!("failed to write the edit journal for {}: {}", self.world_name, e);
+            }
         }
 
         {
@@ -523,6 +665,10 @@ impl WorldRender {
         self.world.chunk_manager.update_dirty_chunks( &self.block_registry, &self.shape_registry );
     }
 
+    pub fn update_chunks_progress(&mut self, progress: impl FnMut(usize, usize)) {
+        self.world.chunk_manager.update_dirty_chunks_progress( &self.block_registry, &self.shape_registry, progress );
+    }
+
     pub fn render(&self, device: &wgpu::Device, queue: &wgpu::Queue, out_view: &wgpu::TextureView) -> Result<wgpu::CommandEncoder, Error> {
         let draw_chunk_list = self.world.chunk_manager.get_render_chunks();
 
@@ -584,6 +730,54 @@ impl WorldRender {
 
         }
 
+        for g in self.ghost_mesh.iter() {
+            let vertex_buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Ghost Vertex Buffer"),
+                    contents: bytemuck::cast_slice(&g.vertices),
+                    usage: wgpu::BufferUsages::VERTEX,
+                }
+            );
+            let index_buffer = device.create_buffer_init(
+                &wgpu::util::BufferInitDescriptor {
+                    label: Some("Ghost Index Buffer"),
+                    contents: bytemuck::cast_slice(&g.indices),
+                    usage: wgpu::BufferUsages::INDEX,
+                }
+            );
+
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Ghost Render Pass"),
+                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                    view: out_view,
+                    resolve_target: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Load,
+                        store: wgpu::StoreOp::Store,
+                    },
+                })],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_texture.view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: if first {wgpu::LoadOp::Clear(1.0)} else {wgpu::LoadOp::Load},
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                occlusion_query_set: None,
+                timestamp_writes: None,
+            });
+            first = false;
+
+            render_pass.set_pipeline(&self.ghost_pipeline);
+            render_pass.set_bind_group(0, &self.camera_bind_group, &[]);
+            render_pass.set_bind_group(1, &self.diffuse_bind_group, &[]);
+            render_pass.set_bind_group(2, &self.colormap_bind_group, &[]);
+            render_pass.set_vertex_buffer(0, vertex_buffer.slice(..));
+            render_pass.set_index_buffer(index_buffer.slice(..), wgpu::IndexFormat::Uint16);
+            render_pass.draw_indexed(0..g.indices.len() as u32, 0, 0..1);
+        }
+
         // draw the marker for the selected block!
         if let Some(pos) = self.selected_block {
             let sel_vertices = vec![
@@ -787,26 +981,8 @@ impl CameraUniform {
 
 
 
-#[repr(C)]
-#[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
-pub struct Vertex {
-    position: [f32; 3],
-    uv: [f32; 2],
-    array_index: u32,
-    light: f32,
-
-}
-
+// how Vertex is laid out for the block shaders
 impl Vertex {
-    pub fn new(position: [f32; 3], uv: [f32; 2], array_index: u32, light: f32) -> Vertex {
-        Self{
-            position,
-            uv,
-            array_index,
-            light
-        }
-    }
-
     fn desc() -> wgpu::VertexBufferLayout<'static> {
         wgpu::VertexBufferLayout {
             array_stride: std::mem::size_of::<Vertex>() as wgpu::BufferAddress,
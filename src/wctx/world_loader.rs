@@ -1,42 +1,276 @@
  
 use std::io::Error;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::fs::File;
 use std::io::{
     Write,
     Read
 };
 
-use toml::Table;
-
 use figures::units::UPx;
 
+use crate::engine::journal;
 use crate::wctx::world;
+use crate::engine::world_format;
+use crate::wctx::world_saver;
+
+// everything that can go wrong between picking a world and playing it
+#[derive(Debug)]
+pub enum WorldLoadError {
+    // the world isn't in the world list (anymore)
+    NotFound(String),
+    // the world directory has no save file
+    MissingSave(PathBuf),
+    // info.toml is missing or unreadable
+    BadInfo(PathBuf, String),
+    // the save file exists but can't be decoded
+    CorruptSave(PathBuf, String),
+    // the save was written by a newer version of the game
+    NewerVersion{ found: u16, supported: u16 },
+    // the block definitions needed to interpret the save failed to load
+    BlockData(String),
+    Io(Error),
+}
+
+impl std::fmt::Display for WorldLoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            WorldLoadError::NotFound(name) => write!(f, "The world {} could not be found.", name),
+            WorldLoadError::MissingSave(path) => write!(f, "There is no save file in {}.", path.display()),
+            WorldLoadError::BadInfo(path, msg) => write!(f, "The world info in {} could not be read: {}", path.display(), msg),
+            WorldLoadError::CorruptSave(path, msg) => write!(f, "The save file {} is damaged: {}", path.display(), msg),
+            WorldLoadError::NewerVersion{ found, supported } => write!(f, "This world was saved in format version {}, but this version of the game only reads up to {}. Update the game to play it.", found, supported),
+            WorldLoadError::BlockData(msg) => write!(f, "The block definitions could not be loaded: {}", msg),
+            WorldLoadError::Io(e) => write!(f, "The world could not be read: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for WorldLoadError {}
+
+impl WorldLoadError {
+    // whether restoring a backup of the save files could fix this
+    pub fn save_problem(&self) -> bool {
+        match self {
+            WorldLoadError::MissingSave(_) | WorldLoadError::CorruptSave(_, _) | WorldLoadError::NewerVersion{ .. } => true,
+            _ => false,
+        }
+    }
+}
+
+impl From<Error> for WorldLoadError {
+    fn from(e: Error) -> Self {
+        WorldLoadError::Io(e)
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+pub enum LoadStage {
+    ReadingSave,
+    Generating,
+    LoadingBlocks,
+    Meshing,
+}
+
+impl LoadStage {
+    pub fn pretty_name(&self) -> &'static str {
+        match self {
+            LoadStage::ReadingSave => "Reading save",
+            LoadStage::Generating => "Generating terrain",
+            LoadStage::LoadingBlocks => "Loading blocks and textures",
+            LoadStage::Meshing => "Building chunks",
+        }
+    }
+}
+
+pub enum LoadMessage {
+    Progress{ stage: LoadStage, done: usize, total: usize },
+    Done( Result<world::WorldRender, WorldLoadError> ),
+}
+
+// a world being loaded or generated on a background thread
+pub struct LoadTask {
+    receiver: std::sync::mpsc::Receiver<LoadMessage>,
+    pub world_name: String,
+    pub world_dir: PathBuf,
+    // only loading to render preview.png, not to play
+    pub preview_only: bool,
+    // a new world that hasn't been saved yet
+    pub generated: bool,
+    pub stage: LoadStage,
+    pub done: usize,
+    pub total: usize,
+}
+
+impl LoadTask {
+    pub fn load(preview: WorldPreview, name: String, device: std::sync::Arc<wgpu::Device>, queue: std::sync::Arc<wgpu::Queue>, config: wgpu::SurfaceConfiguration) -> LoadTask {
+        let world_dir = PathBuf::from( &preview.path_name );
+        Self::spawn( name, world_dir, false, LoadStage::ReadingSave, move || preview.load_world(), device, queue, config )
+    }
+
+    // loads the whole world, not just the part around the camera, so it can be rendered from outside
+    pub fn load_for_preview(preview: WorldPreview, name: String, device: std::sync::Arc<wgpu::Device>, queue: std::sync::Arc<wgpu::Queue>, config: wgpu::SurfaceConfiguration) -> LoadTask {
+        let world_dir = PathBuf::from( &preview.path_name );
+        Self::spawn( name, world_dir.clone(), true, LoadStage::ReadingSave, move || {
+            let mut wss = preview.load_world()?;
+            wss.chunk_manager.load_all().map_err( |e| WorldLoadError::CorruptSave( crate::engine::region::region_dir(&world_dir), e.to_string() ) )?;
+            Ok(wss)
+        }, device, queue, config )
+    }
+
+    pub fn generate(name: String, world_dir: PathBuf, settings: crate::engine::world_gen::WorldGenSettings, device: std::sync::Arc<wgpu::Device>, queue: std::sync::Arc<wgpu::Queue>, config: wgpu::SurfaceConfiguration) -> LoadTask {
+        let region_dir = crate::engine::region::region_dir(&world_dir);
+        let mut task = Self::spawn( name, world_dir, false, LoadStage::Generating, move || {
+            let mut wss = world::WorldSavestate::generate(&settings);
+            wss.chunk_manager.region_dir = Some(region_dir);
+            Ok(wss)
+        }, device, queue, config );
+        task.generated = true;
+        task
+    }
+
+    fn spawn(name: String, world_dir: PathBuf, preview_only: bool, first_stage: LoadStage, make_world: impl FnOnce() -> Result<world::WorldSavestate, WorldLoadError> + Send + 'static, device: std::sync::Arc<wgpu::Device>, queue: std::sync::Arc<wgpu::Queue>, config: wgpu::SurfaceConfiguration) -> LoadTask {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let world_name = name.clone();
+        let thread_world_dir = world_dir.clone();
+        std::thread::spawn( move || {
+            let _ = sender.send( LoadMessage::Progress{ stage: first_stage, done: 0, total: 0 } );
+            let wss = match make_world() {
+                Ok(wss) => wss,
+                Err(e) => {
+                    let _ = sender.send( LoadMessage::Done( Err(e) ) );
+                    return;
+                }
+            };
+
+            let _ = sender.send( LoadMessage::Progress{ stage: LoadStage::LoadingBlocks, done: 0, total: 0 } );
+            let mut wr = world::WorldRender::new( &device, &queue, &config, wss, world_name, &thread_world_dir, !preview_only );
+
+            // only send when the percentage changes, there can be thousands of chunks
+            let mut last_percent = usize::MAX;
+            wr.update_chunks_progress( |done, total| {
+                let percent = done * 100 / std::cmp::max(total, 1);
+                if percent != last_percent {
+                    last_percent = percent;
+                    let _ = sender.send( LoadMessage::Progress{ stage: LoadStage::Meshing, done, total } );
+                }
+            } );
+
+            let _ = sender.send( LoadMessage::Done( Ok(wr) ) );
+        } );
+
+        Self {
+            receiver,
+            world_name: name,
+            world_dir,
+            preview_only,
+            generated: false,
+            stage: first_stage,
+            done: 0,
+            total: 0,
+        }
+    }
+
+    // takes in progress reports, returns the result once the task is finished
+    pub fn poll(&mut self) -> Option<Result<world::WorldRender, WorldLoadError>> {
+        loop {
+            match self.receiver.try_recv() {
+                Ok( LoadMessage::Progress{ stage, done, total } ) => {
+                    self.stage = stage;
+                    self.done = done;
+                    self.total = total;
+                }
+                Ok( LoadMessage::Done(result) ) => return Some(result),
+                Err( std::sync::mpsc::TryRecvError::Empty ) => return None,
+                Err( std::sync::mpsc::TryRecvError::Disconnected ) => {
+                    return Some( Err( WorldLoadError::Io( Error::new( std::io::ErrorKind::Other, "the loading thread stopped unexpectedly" ) ) ) );
+                }
+            }
+        }
+    }
+
+    // overall progress from 0 to 1, each stage taking a fixed share
+    pub fn fraction(&self) -> f32 {
+        let within = if self.total > 0 { self.done as f32 / self.total as f32 } else { 0.0 };
+        match self.stage {
+            LoadStage::ReadingSave | LoadStage::Generating => 0.3 * within,
+            LoadStage::LoadingBlocks => 0.3,
+            LoadStage::Meshing => 0.4 + 0.6 * within,
+        }
+    }
+
+    pub fn status(&self) -> String {
+        if self.total > 0 {
+            format!( "{}... {} / {}", self.stage.pretty_name(), self.done, self.total )
+        } else {
+            format!( "{}...", self.stage.pretty_name() )
+        }
+    }
+}
 
 #[derive(Clone)]
 pub struct WorldPreview {
     pub path_name: String,
-    pub name: String,
-    pub size: usize,
+    pub info: world_saver::WorldInfo,
+    // false if preview.png is missing or unreadable
+    pub has_preview: bool,
 }
 
 
 impl WorldPreview {
-    pub fn load_world(&self) -> Result<world::WorldSavestate, Error> {
-        let mut pbuf = PathBuf::new();
-        pbuf.push( &self.path_name );
-        pbuf.push("world_savestate.pkl");
+    pub fn load_world(&self) -> Result<world::WorldSavestate, WorldLoadError> {
+        let world_dir = PathBuf::from( &self.path_name );
+        let mut save_path = world_dir.clone();
+        save_path.push(world_format::SAVE_FILE_NAME);
+        let mut legacy_path = world_dir.clone();
+        legacy_path.push(world_format::LEGACY_SAVE_FILE_NAME);
+        if !save_path.exists() && !legacy_path.exists() {
+            return Err( WorldLoadError::MissingSave(world_dir) );
+        }
+        if !save_path.exists() {
+            save_path = legacy_path;
+        }
+
+        match world_format::read_version(&world_dir) {
+            Ok( Some(found) ) if found > world_format::FORMAT_VERSION => {
+                return Err( WorldLoadError::NewerVersion{ found, supported: world_format::FORMAT_VERSION } );
+            }
+            Err(e) => return Err( Self::classify(save_path, e) ),
+            _ => {}
+        }
+
+        let mut deserialized = world_format::read_world_file(&world_dir).map_err( |e| Self::classify(save_path.clone(), e) )?;
+
+        let current = crate::wctx::data_loader::load_block_names( PathBuf::from(crate::wctx::data_loader::BLOCK_DATA_PATH) ).map_err( |e| WorldLoadError::BlockData( e.to_string() ) )?;
+        deserialized.remap_blocks(&current)?;
+        if !deserialized.unknown_blocks.is_empty() {
+            log::warn!( "world {} uses blocks that no longer exist, they will not be drawn: {}", self.info.name, deserialized.unknown_blocks.join(", ") );
+        }
+
+        // edits made after the last save, if the game didn't get to save before exiting
+        let replayed = journal::replay( &PathBuf::from( &self.path_name ), deserialized.save_id, &mut deserialized.chunk_manager )?;
+        if replayed > 0 {
+            log::info!( "recovered {} unsaved edits in world {}", replayed, self.info.name );
+        }
 
-        let mut fi = std::fs::File::open(pbuf)?;
-        let mut bytes = Vec::<u8>::new();
-        fi.read_to_end(&mut bytes)?;
+        // everything around the camera is loaded up front, the rest streams in during play
+        let cam_pos = ( deserialized.camera.position.x, deserialized.camera.position.y, deserialized.camera.position.z );
+        deserialized.chunk_manager.load_near( cam_pos, crate::engine::chunk::LOAD_RADIUS, usize::MAX ).map_err( |e| Self::classify( crate::engine::region::region_dir(&world_dir), e ) )?;
 
-        let deserialized: world::WorldSavestate = serde_pickle::from_slice(&bytes, Default::default()).unwrap();
         Ok(deserialized)
 
     }
 
+    // undecodable data means a damaged save, anything else is a plain I/O failure
+    fn classify(path: PathBuf, e: Error) -> WorldLoadError {
+        match e.kind() {
+            std::io::ErrorKind::InvalidData | std::io::ErrorKind::UnexpectedEof => WorldLoadError::CorruptSave( path, e.to_string() ),
+            std::io::ErrorKind::NotFound => WorldLoadError::MissingSave(path),
+            _ => WorldLoadError::Io(e),
+        }
+    }
+
 }
 
 
@@ -44,93 +278,124 @@ impl WorldPreview {
 pub struct WorldLoader {
     pub previews: Vec<WorldPreview>,
     pub name_map: HashMap<String, usize>,
+    // world directories that couldn't be listed, with the reason
+    pub broken: Vec<(PathBuf, WorldLoadError)>,
+}
+
+pub fn worlds_dir() -> PathBuf {
+    world_saver::WorldSaver::worlds_dir()
+}
+
+// shows a directory in the platform's file manager
+pub fn open_folder(path: &Path) -> Result<(), Error> {
+    #[cfg(target_os = "windows")]
+    let program = "explorer";
+    #[cfg(target_os = "macos")]
+    let program = "open";
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    let program = "xdg-open";
+
+    std::process::Command::new(program).arg(path).spawn()?;
+    Ok(())
 }
 
 impl WorldLoader {
 
+    pub fn broken_dirs(&self) -> Vec<PathBuf> {
+        self.broken.iter().map( |b| b.0.clone() ).collect()
+    }
+
     pub fn load_previews(&mut self, gfx: &cushy::kludgine::Graphics ) -> Vec<(WorldPreview, cushy::kludgine::Texture)> {
         self.previews.clear();
+        self.name_map.clear();
+        self.broken.clear();
 
-        let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
-        let mut pbuf = pdirs.data_dir().to_path_buf();
-        pbuf.push( "worlds/" );
-
+        let pbuf = worlds_dir();
         let mut texes = Vec::<cushy::kludgine::Texture>::new();
 
-        let d_info = fs_extra::dir::get_dir_content2(pbuf.clone(), &fs_extra::dir::DirOptions{ depth: 1_u64 }).unwrap();
+        // first start, nothing to list yet
+        if let Err(e) = std::fs::create_dir_all(&pbuf) {
+            log::error!("failed to create the worlds directory {}: {}", pbuf.display(), e);
+            return Vec::new();
+        }
+        let d_info = match fs_extra::dir::get_dir_content2(pbuf.clone(), &fs_extra::dir::DirOptions{ depth: 1_u64 }) {
+            Ok(d_info) => d_info,
+            Err(e) => {
+                log::error!("failed to list worlds in {}: {}", pbuf.display(), e);
+                return Vec::new();
+            }
+        };
 
         for subd in d_info.directories {
-            let mut lbuf = PathBuf::new();
-            lbuf.push(subd.clone());
-            lbuf.push("info.toml");
-            let mut file = File::open(lbuf);
-            if let Ok(mut fi) = file {
-                let mut contents = String::new();
-                let read_result = fi.read_to_string(&mut contents);
-                if let Ok(size) = read_result {
-                    let table_unwrap = contents.parse::<Table>();
-
-                    if let Ok(tab) = table_unwrap {
-                        let mut texture = cushy::kludgine::Texture::new(
-                            gfx,
-                            figures::Size{width: UPx::new(512), height: UPx::new(512)},
-                            wgpu::TextureFormat::Rgba8UnormSrgb,
-                            wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
-                            wgpu::FilterMode::Linear
-                        );
-
-                        let mut ibuf = PathBuf::new();
-                        ibuf.push(subd.clone());
-                        ibuf.push("preview.png");
-                        let mut ifile = File::open(ibuf);
-                        if let Ok(mut fi) = ifile {
-                            let mut image_data = Vec::<u8>::new();
-                            let read_result = fi.read_to_end(&mut image_data);
-                            if let Ok(size2) = read_result {
-                                let texture2 = crate::wctx::texture::Texture::from_bytes(gfx.device(), gfx.queue(), &image_data, "copy texture").unwrap();
-                                let mut encoder = gfx.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                                    label: Some("TexCopy Encoder"),
-                                });
-                                encoder.copy_texture_to_texture(
-                                    wgpu::ImageCopyTexture{
-                                        texture: &texture2.texture,
-                                        mip_level: 0,
-                                        origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
-                                        aspect: wgpu::TextureAspect::All
-                                    },
-                                    wgpu::ImageCopyTexture{
-                                        texture: &texture.wgpu(),
-                                        mip_level: 0,
-                                        origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
-                                        aspect: wgpu::TextureAspect::All
-                                    },
-                                    texture2.texture.size()
-                                );
-                                gfx.queue().submit( std::iter::once( encoder.finish() ) );
-                            }
-                        }
-
-                        let preview = WorldPreview{
-                            path_name: subd,
-                            name: tab["name"].as_str().expect("name must be a string!").to_string(),
-                            size: tab["size"].as_integer().unwrap().try_into().expect("size should only be 0, 1, 2"),
-                        };
-
-                        self.name_map.insert( preview.name.clone(), self.previews.len() );
-                        self.previews.push(preview);
-                        texes.push(texture);
+            // the listing includes the worlds directory itself, and hidden directories are imports in progress
+            let subd_path = PathBuf::from(&subd);
+            if subd_path == pbuf || subd_path.parent() != Some( pbuf.as_path() ) || subd_path.file_name().map_or( true, |n| n.to_string_lossy().starts_with('.') ) {
+                continue;
+            }
+            let info_load = world_saver::WorldInfo::read( &PathBuf::from( subd.clone() ) );
+            if let Err(e) = &info_load {
+                log::warn!("skipping world directory {}: {}", subd, e);
+                self.broken.push( ( PathBuf::from(&subd), WorldLoadError::BadInfo( PathBuf::from(&subd), e.to_string() ) ) );
+            }
+            if let Ok(info) = info_load {
+                let mut texture = cushy::kludgine::Texture::new(
+                    gfx,
+                    figures::Size{width: UPx::new(512), height: UPx::new(512)},
+                    wgpu::TextureFormat::Rgba8UnormSrgb,
+                    wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST | wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    wgpu::FilterMode::Linear
+                );
 
+                let mut ibuf = PathBuf::new();
+                ibuf.push(subd.clone());
+                ibuf.push("preview.png");
+                let mut has_preview = false;
+                let mut ifile = File::open(ibuf);
+                if let Ok(mut fi) = ifile {
+                    let mut image_data = Vec::<u8>::new();
+                    let read_result = fi.read_to_end(&mut image_data);
+                    // previews can be rendered at any resolution, the list shows them at 512x512
+                    let texture_load = image::load_from_memory(&image_data).map_err( |e| anyhow::anyhow!(e) ).and_then( |img| {
+                        let img = if img.width() != 512 || img.height() != 512 { img.resize_exact( 512, 512, image::imageops::FilterType::Triangle ) } else { img };
+                        crate::wctx::texture::Texture::from_image(gfx.device(), gfx.queue(), &img, Some("copy texture"))
+                    } );
+                    if let (Ok(size2), Ok(texture2)) = (read_result, texture_load) {
+                        let mut encoder = gfx.device().create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                            label: Some("TexCopy Encoder"),
+                        });
+                        encoder.copy_texture_to_texture(
+                            wgpu::ImageCopyTexture{
+                                texture: &texture2.texture,
+                                mip_level: 0,
+                                origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                                aspect: wgpu::TextureAspect::All
+                            },
+                            wgpu::ImageCopyTexture{
+                                texture: &texture.wgpu(),
+                                mip_level: 0,
+                                origin: wgpu::Origin3d{x: 0, y: 0, z: 0},
+                                aspect: wgpu::TextureAspect::All
+                            },
+                            texture2.texture.size()
+                        );
+                        gfx.queue().submit( std::iter::once( encoder.finish() ) );
+                        has_preview = true;
                     }
                 }
+
+                let preview = WorldPreview{
+                    path_name: subd,
+                    info,
+                    has_preview,
+                };
+
+                self.name_map.insert( preview.info.name.clone(), self.previews.len() );
+                self.previews.push(preview);
+                texes.push(texture);
             }
         }
 
-        let mut ret = Vec::<(WorldPreview, cushy::kludgine::Texture)>::new();
-        let mut pvclon = self.previews.clone();
-        for idx in 0..texes.len() {
-            ret.push( ( pvclon.pop().unwrap(), texes.pop().unwrap() ) )
-        }
-        ret
+        self.previews.iter().cloned().zip( texes.into_iter() ).collect()
     }
 
 
@@ -1,123 +1,391 @@
-
-
 use std::io::Error;
-use std::path::PathBuf;
-use std::fs::File;
-use std::io::{
-    Write,
-    Read
-};
+use std::path::{Path, PathBuf};
 
-use fs_extra::dir::create_all;
+use crate::engine::region;
+use crate::wctx::settings::{
+    MapSettings,
+    PreviewSettings
+};
+use crate::engine::world_format;
+use crate::engine::world_gen::WorldGenSettings;
 
-use cgmath::Angle;
+pub use crate::engine::saves::*;
 
 
 const DRAW_WORLD_SCALE: [f32; 3] = [1.0/128.0, 1.0/192.0, 1.0/256.0];
+// map export runs on the event loop, these keep it to a few seconds
+const MAX_MAP_SIZE: u32 = 16384;
+const MAX_MAP_TILES_PER_SIDE: u32 = 4;
+
+// outcome of a background autosave, with the chunks that need writing again if it failed
+type AutosaveResult = Result<usize, ( Vec<(usize, usize, usize)>, Error )>;
+
+// saves changed chunks every so often while a world is open. the chunks are copied on the main thread
+// and written on a background thread, so the frame loop never waits for the disk.
+pub struct Autosaver {
+    interval: std::time::Duration,
+    elapsed: std::time::Duration,
+    pending: Option<std::sync::mpsc::Receiver<AutosaveResult>>,
+    pub last_saved: Option<std::time::Instant>,
+}
+
+impl Autosaver {
+    pub fn new(interval_seconds: u64) -> Autosaver {
+        Self {
+            interval: std::time::Duration::from_secs(interval_seconds),
+            elapsed: std::time::Duration::ZERO,
+            pending: None,
+            last_saved: None,
+        }
+    }
+
+    pub fn reset(&mut self) {
+        self.elapsed = std::time::Duration::ZERO;
+        self.last_saved = None;
+    }
+
+    // returns true when an autosave finished this frame
+    pub fn update(&mut self, dt: std::time::Duration, world_render: &mut crate::wctx::world::WorldRender) -> bool {
+        let finished = self.poll(world_render, false);
+
+        if self.interval.is_zero() {
+            return finished;
+        }
+        self.elapsed += dt;
+        if self.elapsed >= self.interval && self.pending.is_none() {
+            self.elapsed = std::time::Duration::ZERO;
+            self.start(world_render);
+        }
+        finished
+    }
+
+    // blocks until any autosave in flight is done, so a full save doesn't race it for the region files
+    pub fn wait(&mut self, world_render: &mut crate::wctx::world::WorldRender) {
+        self.poll(world_render, true);
+    }
 
-pub struct WorldSaver {
+    fn poll(&mut self, world_render: &mut crate::wctx::world::WorldRender, block: bool) -> bool {
+        let result = match &self.pending {
+            Some(rx) if block => rx.recv().ok(),
+            Some(rx) => match rx.try_recv() {
+                Ok(res) => Some(res),
+                Err(std::sync::mpsc::TryRecvError::Empty) => return false,
+                Err(std::sync::mpsc::TryRecvError::Disconnected) => None,
+            },
+            None => return false,
+        };
+        self.pending = None;
+
+        match result {
+            Some(Ok(count)) => {
+                log::info!("autosaved {} ({} chunks)", world_render.world_name, count);
+                if let Some(journal) = &mut world_render.journal {
+                    if let Err(e) = journal.drop_previous() {
+                        log::warn!("failed to remove the old edit journal for {}: {}", world_render.world_name, e);
+                    }
+                }
+                self.last_saved = Some( std::time::Instant::now() );
+                true
+            }
+            Some(Err((indices, e))) => {
+                log::error!("autosave of {} failed: {}", world_render.world_name, e);
+                world_render.world.chunk_manager.mark_unsaved(&indices);
+                false
+            }
+            None => {
+                log::error!("autosave of {} stopped unexpectedly", world_render.world_name);
+                false
+            }
+        }
+    }
+
+    fn start(&mut self, world_render: &mut crate::wctx::world::WorldRender) {
+        let world_dir = WorldSaver::world_dir( &world_render.world_name );
+        let region_dir = world_render.world.chunk_manager.region_dir.clone().unwrap_or( region::region_dir(&world_dir) );
+        world_render.world.save_id += 1;
+        let meta = match world_format::encode_world(&world_render.world) {
+            Ok(meta) => meta,
+            Err(e) => {
+                log::error!("autosave of {} failed: {}", world_render.world_name, e);
+                return;
+            }
+        };
+        let chunks = world_render.world.chunk_manager.snapshot_unsaved();
+        if let Some(journal) = &mut world_render.journal {
+            if let Err(e) = journal.rotate(world_render.world.save_id) {
+                log::error!("failed to rotate the edit journal for {}: {}", world_render.world_name, e);
+            }
+        }
+
+        let (tx, rx) = std::sync::mpsc::channel::<AutosaveResult>();
+        std::thread::spawn( move || {
+            let indices: Vec<(usize, usize, usize)> = chunks.iter().map( |c| c.0 ).collect();
+            let write = || -> Result<(), Error> {
+                let refs: Vec<( (usize, usize, usize), &crate::engine::chunk::Chunk )> = chunks.iter().map( |c| (c.0, &c.1) ).collect();
+                region::write_chunks( &region_dir, &refs )?;
+                let mut savebuf = world_dir.clone();
+                savebuf.push(world_format::SAVE_FILE_NAME);
+                write_file_atomic( &savebuf, &meta )
+            };
+            let res = match write() {
+                Ok(()) => Ok( indices.len() ),
+                Err(e) => Err( (indices, e) ),
+            };
+            let _ = tx.send(res);
+        });
+        self.pending = Some(rx);
+    }
 }
 
+// the parts of WorldSaver that need the GPU
 impl WorldSaver {
+    // camera for the world overview: looks at the world from the given angles, fitting it into clip space
+    pub fn overview_matrix(size: usize, yaw_degrees: f32, pitch_degrees: f32) -> cgmath::Matrix4<f32> {
+        cgmath::Matrix4::from_translation( cgmath::Vector3::new( 0.0, 0.3, 0.5 ) ) * cgmath::Matrix4::from_nonuniform_scale(1.0, 1.0, -0.1) * cgmath::Matrix4::from_angle_x( cgmath::Deg(pitch_degrees) ) * cgmath::Matrix4::from_angle_y( cgmath::Deg(yaw_degrees) ) * cgmath::Matrix4::from_translation( cgmath::Vector3::new( -0.5, -1.0, -0.5 ) ) * cgmath::Matrix4::from_scale( DRAW_WORLD_SCALE[size] )
+    }
+
+    // copies a texture back to the CPU as tightly packed 4 byte pixels, waiting for the GPU to finish
+    pub fn read_texture(texture: &wgpu::Texture, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<Vec<u8>, Error> {
+        let (width, height) = ( texture.width(), texture.height() );
+        // buffer rows have to be aligned, the padding is cut off again below
+        let row_bytes = 4 * width;
+        let padded_row_bytes = ( row_bytes + wgpu::COPY_BYTES_PER_ROW_ALIGNMENT - 1 ) / wgpu::COPY_BYTES_PER_ROW_ALIGNMENT * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+        let buffer = device.create_buffer(
+            &wgpu::BufferDescriptor {
+                label: Some("world view readback buffer"),
+                size: ( padded_row_bytes * height ) as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false
+            }
+        );
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("Copy Encoder"),
+        });
+        encoder.copy_texture_to_buffer(
+            wgpu::ImageCopyTexture {
+                texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d{x: 0, y:0, z:0},
+                aspect: wgpu::TextureAspect::All
+            },
+            wgpu::ImageCopyBuffer {
+                buffer: &buffer,
+                layout: wgpu::ImageDataLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_row_bytes),
+                    rows_per_image: Some(height),
+                }
+            },
+            wgpu::Extent3d{ width, height, depth_or_array_layers: 1 }
+        );
+        queue.submit( std::iter::once(encoder.finish()) );
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        buffer.slice(..).map_async( wgpu::MapMode::Read, move |res| { let _ = sender.send(res); } );
+        device.poll(wgpu::Maintain::Wait);
+        receiver.recv()
+            .map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?
+            .map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+
+        let mut pixels = Vec::<u8>::with_capacity( ( row_bytes * height ) as usize );
+        {
+            let data = buffer.slice(..).get_mapped_range();
+            for row in data.chunks_exact( padded_row_bytes as usize ) {
+                pixels.extend_from_slice( &row[ ..row_bytes as usize ] );
+            }
+        }
+        buffer.unmap();
+
+        Ok(pixels)
+    }
 
+    // renders the world through cam_matrix and reads the result back, waiting for the GPU to finish
+    pub fn render_view_image(world_render: &crate::wctx::world::WorldRender, cam_matrix: cgmath::Matrix4<f32>, width: u32, height: u32, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<image::RgbaImage, Error> {
+        let tex = crate::wctx::texture::Texture::from_descriptor(
+            device,
+            queue,
+            &wgpu::TextureDescriptor {
+                label: Some("world view render texture"),
+                size: wgpu::Extent3d{ width, height, depth_or_array_layers: 1 },
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: wgpu::TextureFormat::Rgba8UnormSrgb,
+                usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+                view_formats: &[],
+            },
+            wgpu::TextureViewDimension::D2
+        ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
 
-    pub fn create_world_dir(name: String, size: usize) -> PathBuf {
+        world_render.draw_custom_view( cam_matrix.into(), device, queue, &tex );
+
+        let pixels = Self::read_texture( &tex.texture, device, queue )?;
+        image::RgbaImage::from_raw(width, height, pixels).ok_or( Error::new( std::io::ErrorKind::Other, "readback buffer has the wrong size" ) )
+    }
+
+    pub fn encode_png(img: &image::RgbaImage) -> Result<Vec<u8>, Error> {
+        let mut png = Vec::<u8>::new();
+        image::DynamicImage::ImageRgba8( img.clone() ).write_to( &mut std::io::Cursor::new(&mut png), image::ImageOutputFormat::Png ).map_err( |e| Error::new( std::io::ErrorKind::Other, e.to_string() ) )?;
+        Ok(png)
+    }
+
+    // renders preview.png for the world list, the file is complete once this returns.
+    // only chunks that are currently loaded show up, the preview shows the whole world so callers load it all first.
+    pub fn write_preview(world_render: &crate::wctx::world::WorldRender, world_dir: &Path, preview: &PreviewSettings, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<(), Error> {
+        let resolution = preview.resolution.clamp(64, 4096);
+        let matrix = Self::overview_matrix( world_render.world.size(), preview.yaw, preview.pitch );
+        let img = Self::render_view_image( world_render, matrix, resolution, resolution, device, queue )?;
+
+        let mut ibuf = world_dir.to_path_buf();
+        ibuf.push(PREVIEW_FILE_NAME);
+        write_file_atomic( &ibuf, &Self::encode_png(&img)? )
+    }
+
+    pub fn map_dir() -> PathBuf {
         let pdirs = directories::ProjectDirs::from( "", "PhantasmaCora Games", "SGR_Cubes" ).expect("Failed to get project directories");
         let mut pbuf = pdirs.data_dir().to_path_buf();
-        pbuf.push( "worlds/" );
-        pbuf.push( name.clone() );
-        create_all( &pbuf, false );
+        pbuf.push( "maps/" );
+        pbuf
+    }
 
-        let mut ibuf = pbuf.clone();
-        ibuf.push("info.toml");
-        let datastring = format!( "name = \"{}\"\nsize = {}", name, size );
-        let mut file = File::create(ibuf).expect("failed to create file");
-        file.write_all( datastring.as_bytes() ).expect("failed to write to file");
+    // orthographic view of the whole world from the given angles, fitted tightly into clip space.
+    // returns the matrix and the width / height ratio of the picture.
+    pub fn map_matrix(size: usize, yaw_degrees: f32, pitch_degrees: f32) -> (cgmath::Matrix4<f32>, f32) {
+        use cgmath::{ Matrix4, Vector3, Vector4 };
+        let rotation = Matrix4::from_angle_x( cgmath::Deg(pitch_degrees) ) * Matrix4::from_angle_y( cgmath::Deg(yaw_degrees) ) * Matrix4::from_translation( Vector3::new( -0.5, -0.5, -0.5 ) ) * Matrix4::from_scale( DRAW_WORLD_SCALE[size] );
 
-        pbuf
+        let world_width = ( crate::engine::chunk::CHUNK_SIZE * crate::engine::chunk::WORLD_CHUNKS[size] ) as f32;
+        let mut min = Vector3::new( f32::MAX, f32::MAX, f32::MAX );
+        let mut max = Vector3::new( f32::MIN, f32::MIN, f32::MIN );
+        for corner in 0..8 {
+            let p = rotation * Vector4::new(
+                if corner & 1 == 0 { 0.0 } else { world_width },
+                if corner & 2 == 0 { 0.0 } else { world_width },
+                if corner & 4 == 0 { 0.0 } else { world_width },
+                1.0
+            );
+            min = Vector3::new( min.x.min(p.x), min.y.min(p.y), min.z.min(p.z) );
+            max = Vector3::new( max.x.max(p.x), max.y.max(p.y), max.z.max(p.z) );
+        }
+        let extent = max - min;
+
+        // x and y to -1..1, z flipped to 0..1 so the side facing the viewer is nearest
+        let fit = Matrix4::from_translation( Vector3::new( 0.0, 0.0, max.z / extent.z ) )
+            * Matrix4::from_nonuniform_scale( 2.0 / extent.x, 2.0 / extent.y, -1.0 / extent.z )
+            * Matrix4::from_translation( Vector3::new( -( min.x + max.x ) / 2.0, -( min.y + max.y ) / 2.0, 0.0 ) );
+        ( fit * rotation, extent.x / extent.y )
     }
 
-    pub fn save_world(&mut self, world_render: &crate::wctx::world::WorldRender, device: &wgpu::Device, queue: &wgpu::Queue ) {
-        let pbuf = Self::create_world_dir( world_render.world_name.clone(), world_render.world.chunk_manager.size );
+    // renders an orthographic map of the whole world into a new directory under map_dir,
+    // as map.png or, for big maps, a grid of tile_<column>_<row>.png files. returns the directory.
+    pub fn export_map(world_render: &mut crate::wctx::world::WorldRender, settings: &MapSettings, device: &wgpu::Device, queue: &wgpu::Queue) -> Result<PathBuf, Error> {
+        // the map shows everything, not just what's streamed in around the camera
+        world_render.world.chunk_manager.load_all()?;
+        world_render.update_chunks();
 
-        {
-            let mut pklbuf = pbuf.clone();
-            pklbuf.push("world_savestate.pkl");
-            let mut fi = std::fs::File::create(pklbuf).expect("failed to create world file");
-            let serialized = serde_pickle::to_vec(&world_render.world, Default::default()).unwrap();
-            fi.write_all( &serialized ).expect("failed to write world file");
+        let (yaw, pitch) = settings.angles();
+        let (matrix, aspect) = Self::map_matrix( world_render.world.size(), yaw, pitch );
+        let long_side = settings.size.clamp(64, MAX_MAP_SIZE);
+        let (width, height) = if aspect >= 1.0 {
+            ( long_side, std::cmp::max( ( long_side as f32 / aspect ).round() as u32, 1 ) )
+        } else {
+            ( std::cmp::max( ( long_side as f32 * aspect ).round() as u32, 1 ), long_side )
+        };
+        // every tile renders the whole world again, so small tiles are only allowed on small maps
+        let min_tile = ( long_side + MAX_MAP_TILES_PER_SIDE - 1 ) / MAX_MAP_TILES_PER_SIDE;
+        let tile_size = settings.tile_size.max(min_tile).clamp( 64, device.limits().max_texture_dimension_2d );
+        let columns = ( width + tile_size - 1 ) / tile_size;
+        let rows = ( height + tile_size - 1 ) / tile_size;
+
+        let mut out_dir = Self::map_dir();
+        out_dir.push( format!( "{} {}", world_render.world_name, unix_now() ) );
+        std::fs::create_dir_all(&out_dir)?;
+
+        for row in 0..rows {
+            for column in 0..columns {
+                let (x0, y0) = ( column * tile_size, row * tile_size );
+                let (x1, y1) = ( std::cmp::min( x0 + tile_size, width ), std::cmp::min( y0 + tile_size, height ) );
+
+                // stretch this tile's part of clip space over the whole render target, image rows go top down
+                let (left, right) = ( -1.0 + 2.0 * x0 as f32 / width as f32, -1.0 + 2.0 * x1 as f32 / width as f32 );
+                let (top, bottom) = ( 1.0 - 2.0 * y0 as f32 / height as f32, 1.0 - 2.0 * y1 as f32 / height as f32 );
+                let tile_matrix = cgmath::Matrix4::from_nonuniform_scale( 2.0 / ( right - left ), 2.0 / ( top - bottom ), 1.0 )
+                    * cgmath::Matrix4::from_translation( cgmath::Vector3::new( -( left + right ) / 2.0, -( top + bottom ) / 2.0, 0.0 ) )
+                    * matrix;
+
+                let img = Self::render_view_image( world_render, tile_matrix, x1 - x0, y1 - y0, device, queue )?;
+                let mut tile_path = out_dir.clone();
+                if columns * rows == 1 {
+                    tile_path.push("map.png");
+                } else {
+                    tile_path.push( format!( "tile_{}_{}.png", column, row ) );
+                }
+                write_file_atomic( &tile_path, &Self::encode_png(&img)? )?;
+            }
         }
 
+        // enough to stitch the tiles back together
+        let mut layout_path = out_dir.clone();
+        layout_path.push("map.toml");
+        let layout = format!(
+            "world = {}\nview = {}\nyaw = {}\npitch = {}\nwidth = {}\nheight = {}\ntile_size = {}\ncolumns = {}\nrows = {}\n",
+            toml::Value::String( world_render.world_name.clone() ), toml::Value::String( format!("{:?}", settings.view).to_lowercase() ), yaw, pitch, width, height, tile_size, columns, rows
+        );
+        write_file_atomic( &layout_path, layout.as_bytes() )?;
+
+        Ok(out_dir)
+    }
+
+    pub fn save_world(&mut self, world_render: &mut crate::wctx::world::WorldRender, preview: &PreviewSettings, device: &wgpu::Device, queue: &wgpu::Queue ) -> Result<(), Error> {
+        let pbuf = Self::world_dir( &world_render.world_name );
+        let mut info = WorldInfo::read(&pbuf).unwrap_or( WorldInfo::new( world_render.world_name.clone(), &WorldGenSettings::new( world_render.world.size() ) ) );
+        info.size = world_render.world.size();
+        info.last_played = unix_now();
+        info.play_time += world_render.session_time.as_secs();
+        info.game_version = GAME_VERSION.to_string();
+        Self::create_world_dir(&info);
+        // keep the fractional second for next time
+        world_render.session_time = std::time::Duration::from_nanos( world_render.session_time.subsec_nanos() as u64 );
+
+        Self::make_backup(&pbuf)?;
+        world_render.world.save_id += 1;
+
         {
-            let tex = crate::wctx::texture::Texture::from_descriptor(
-                device,
-                queue,
-                &wgpu::TextureDescriptor {
-                    label: Some("world overview render texture"),
-                    size: wgpu::Extent3d{ width: 512, height: 512, depth_or_array_layers: 1 },
-                    mip_level_count: 1,
-                    sample_count: 1,
-                    dimension: wgpu::TextureDimension::D2,
-                    format: wgpu::TextureFormat::Rgba8UnormSrgb,
-                    usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
-                    view_formats: &[],
-                },
-                wgpu::TextureViewDimension::D2
-            ).expect("failed to create texture");
-
-            world_render.draw_custom_view( ( cgmath::Matrix4::from_translation( cgmath::Vector3::new( 0.0, 0.3, 0.5 ) ) * cgmath::Matrix4::from_nonuniform_scale(1.0, 1.0, -0.1) * cgmath::Matrix4::from_angle_x( cgmath::Rad::atan( 2.0_f32.sqrt() / 2.0 ) ) * cgmath::Matrix4::from_angle_y( cgmath::Rad::full_turn() / -8.0 ) * cgmath::Matrix4::from_translation( cgmath::Vector3::new( -0.5, -1.0, -0.5 ) ) * cgmath::Matrix4::from_scale( DRAW_WORLD_SCALE[world_render.world.chunk_manager.size] ) ).into(), device, queue, &tex );
-
-            let buffer = device.create_buffer(
-                &wgpu::BufferDescriptor {
-                    label: None,
-                    size: 512 * 512 * 4,
-                    usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
-                    mapped_at_creation: false
-                }
-            );
+            // only chunks changed since the last save are rewritten
+            let chunk_manager = &mut world_render.world.chunk_manager;
+            if chunk_manager.region_dir.is_none() {
+                chunk_manager.region_dir = Some( region::region_dir(&pbuf) );
+            }
+            chunk_manager.save_unsaved()?;
 
-            let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
-                label: Some("Copy Encoder"),
-            });
-
-            encoder.copy_texture_to_buffer(
-                wgpu::ImageCopyTexture {
-                    texture: &tex.texture,
-                    mip_level: 0,
-                    origin: wgpu::Origin3d{x: 0, y:0, z:0},
-                    aspect: wgpu::TextureAspect::All
-                },
-                wgpu:: ImageCopyBuffer {
-                    buffer: &buffer,
-                    layout: wgpu::ImageDataLayout {
-                        offset: 0,
-                        bytes_per_row: Some(4 * 512),
-                        rows_per_image: Some(512),
-                    }
-                },
-                wgpu::Extent3d{
-                    width: 512,
-                    height: 512,
-                    depth_or_array_layers: 1
-                }
-            );
+            let mut savebuf = pbuf.clone();
+            savebuf.push(world_format::SAVE_FILE_NAME);
+            let serialized = world_format::encode_world(&world_render.world)?;
+            write_file_atomic( &savebuf, &serialized )?;
 
-            queue.submit( std::iter::once(encoder.finish()) );
-
-            let buffer = std::sync::Arc::new(buffer);
-            let capturable = buffer.clone();
-            let mut ibuf = pbuf.clone();
-            buffer.slice(..).map_async(wgpu::MapMode::Read, move |res| {
-                if res.is_ok() {
-                    let data = capturable.slice(..).get_mapped_range();
-                    let img = image::RgbaImage::from_raw(512, 512, (*data).to_vec() ).expect("failed to create image");
-                    ibuf.push("preview.png");
-                    img.save(ibuf);
-                }
-            } );
-            queue.submit( std::iter::empty() );
+            // the old pickled savestate has been migrated to the new format and is no longer needed
+            let mut pklbuf = pbuf.clone();
+            pklbuf.push(world_format::LEGACY_SAVE_FILE_NAME);
+            if pklbuf.exists() {
+                std::fs::remove_file(pklbuf)?;
+            }
+        }
+
+        // everything journaled so far is part of this save
+        if let Some(journal) = &mut world_render.journal {
+            journal.clear(world_render.world.save_id)?;
+        }
 
+        // the world itself is safely saved at this point, a missing thumbnail can be made again later.
+        // the preview takes in the whole world, so chunks not streamed in yet are loaded for it like MAKE PREVIEW does
+        let loaded = world_render.world.chunk_manager.load_all();
+        world_render.update_chunks();
+        if let Err(e) = loaded.and_then( |_| Self::write_preview( world_render, &pbuf, preview, device, queue ) ) {
+            log::warn!("failed to render the preview for {}: {}", world_render.world_name, e);
         }
+
+        Ok(())
     }
 
 }